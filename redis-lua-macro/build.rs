@@ -1,6 +1,8 @@
 use rustc_version::{version_meta, Channel};
 
 fn main() {
+    println!("cargo:rustc-check-cfg=cfg(unstable)");
+
     if version_meta().unwrap().channel != Channel::Stable {
         println!("cargo:rustc-cfg=unstable");
     }
@@ -1,4 +1,9 @@
-use crate::{patterns::*, script::Script};
+use crate::{
+    patterns::*,
+    proc_macro::TokenStream as TokenStream1,
+    script::{Arg, ArgType, Script},
+    to_ident,
+};
 use proc_macro2::TokenStream;
 use quote::quote;
 
@@ -52,6 +57,7 @@ impl<'a> Chain<'a> {
             let varparam = prev.varparam();
             let vartype = prev.vartype();
             let inits = prev.params_f(|_, a| quote! { #a: prev.#a });
+            let defaults = self.defaults_f(|a, _| quote! { #a: prev.#a });
 
             quote! {
                 fn chain(prev: #name, var: #vartype) -> Self {
@@ -59,6 +65,7 @@ impl<'a> Chain<'a> {
                         info: prev.info,
                         inner: prev.inner,
                         #(#inits,)*
+                        #(#defaults,)*
                         #varparam: Some(var),
                     }
                 }
@@ -66,6 +73,11 @@ impl<'a> Chain<'a> {
         } else {
             let args = self.args();
             let inits_new = self.params_f(|_, a| quote! { #a: Some(#a) });
+            let defaults = self.defaults_f(|a, arg| {
+                let value: TokenStream1 = arg.default().unwrap().iter().cloned().collect();
+                let value = to_script_arg(arg, value.into());
+                quote! { #a: #value }
+            });
 
             quote! {
                 fn new(info: redis_lua::Info, inner: I, #(#args),*) -> Self {
@@ -73,6 +85,7 @@ impl<'a> Chain<'a> {
                         info,
                         inner,
                         #(#inits_new,)*
+                        #(#defaults,)*
                     }
                 }
             }
@@ -85,9 +98,13 @@ impl<'a> Chain<'a> {
             let tyname = next.tyname();
             let varname = self.varname();
             let vartype = self.vartype();
+            let typed = self.typed_bound();
 
             quote! {
-                fn #varname<#vartype>(self, var: #vartype) -> #tyname {
+                fn #varname<#vartype>(self, var: #vartype) -> #tyname
+                where
+                    #typed
+                {
                     #name::chain(self, var)
                 }
             }
@@ -124,15 +141,100 @@ impl<'a> Chain<'a> {
         }
     }
 
-    fn impl_adders(&self) -> TokenStream {
-        if self.prev().is_some() {
+    // The setters of the arguments with the default values, which are available at any point of the chain.
+    fn impl_defaults(&self) -> TokenStream {
+        let setters = defaults(self.script).map(|t| {
+            let varname = to_name(t);
+            let param = to_param(t);
+            let value = to_script_arg(t.1, quote! { var });
+            let typed = t.1.ty().map(|ty| {
+                let ty = to_ident(ty);
+                quote! { V: redis_lua::TypedArg<#ty>, }
+            });
+
+            quote! {
+                fn #varname<V>(mut self, var: V) -> Self
+                where
+                    V: redis_lua::serde::Serialize,
+                    #typed
+                {
+                    self.#param = #value;
+                    self
+                }
+            }
+        });
+
+        quote! { #(#setters)* }
+    }
+
+    fn impl_binders(&self) -> TokenStream {
+        if self.prev().is_some() || self.next().is_none() {
             return quote! {};
         }
 
+        let name = new_ident(format!("Chain{}", varlen(self.script)));
+        let types = self.types();
+        let values = vars(self.script).map(|_| quote! { V });
+        let binds = vars(self.script).map(|arg| {
+            let varname = to_name(arg);
+            let key = arg.1.as_rust().to_string();
+            quote! { .#varname(get(#key)?) }
+        });
+        let typed = vars(self.script).filter_map(|(_, arg)| {
+            let ty = to_ident(arg.ty()?);
+            Some(quote! { V: redis_lua::TypedArg<#ty>, })
+        });
+
+        quote! {
+            fn bind_named<V>(
+                self,
+                args: &std::collections::HashMap<&str, V>,
+            ) -> redis_lua::redis::RedisResult<#name<I, #(#types,)* #(#values),*>>
+            where
+                V: Clone,
+                #(#typed)*
+            {
+                let get = |name: &str| {
+                    args.get(name).cloned().ok_or_else(|| {
+                        redis_lua::redis::RedisError::from((
+                            redis_lua::redis::ErrorKind::ClientError,
+                            "missing script argument",
+                            name.to_string(),
+                        ))
+                    })
+                };
+
+                Ok(self #(#binds)*)
+            }
+
+            fn invoke_raw<T, K, A>(
+                self,
+                con: &mut dyn redis_lua::redis::ConnectionLike,
+                keys: &[K],
+                args: &[A],
+            ) -> redis_lua::redis::RedisResult<T>
+            where
+                T: redis_lua::redis::FromRedisValue,
+                K: redis_lua::redis::ToRedisArgs,
+                A: redis_lua::redis::ToRedisArgs,
+                I: redis_lua::Script,
+            {
+                let mut info = vec![];
+                let mut bound = vec![];
+                self.inner.info(&mut info, &mut bound);
+                info.push(self.info);
+                redis_lua::invoke_raw(con, info, keys, args)
+            }
+        }
+    }
+
+    // Every chain can be joined, so the arguments can be partially applied before joining.
+    fn impl_adders(&self) -> TokenStream {
         let tyname = self.tyname();
         let name = self.name();
         let types = self.types();
         let inits_self = self.params_f(|_, a| quote! { #a: self.#a });
+        let defaults_self = self.defaults_f(|a, _| quote! { #a: self.#a });
 
         let impl_takeunit = quote! {
             impl<I, I2, #(#types),*> redis_lua::TakeScript<I2> for #tyname
@@ -147,6 +249,7 @@ impl<'a> Chain<'a> {
                         inner: inner.join(self.inner),
                         info: self.info,
                         #(#inits_self,)*
+                        #(#defaults_self,)*
                     }
                 }
             }
@@ -186,6 +289,8 @@ impl<'a> Chain<'a> {
         }
     }
 
+    // Only the last chain, in which all the `$` arguments are bound, is a script,
+    // so a script missing an argument fails to compile rather than being invoked.
     fn impl_script(&self) -> TokenStream {
         if self.next().is_some() {
             return quote! {};
@@ -194,9 +299,18 @@ impl<'a> Chain<'a> {
         let tyname = self.tyname();
         let types = self.types();
         let bounds = self.bounds();
-        let args = self.params_f(
-            |_, a| quote! { args.push(redis_lua::script_arg(self.#a.as_ref().unwrap())); },
-        );
+        // All the keys have to be pushed before the arguments.
+        let push = |t: (usize, &Arg)| {
+            let a = to_param(t);
+            if t.1.atype() == ArgType::Default {
+                quote! { args.push(self.#a.clone()); }
+            } else {
+                let value = to_script_arg(t.1, quote! { self.#a.as_ref().unwrap() });
+                quote! { args.push(#value); }
+            }
+        };
+        let keys = keys(self.script).map(push);
+        let args = argv(self.script).map(push);
 
         quote! {
             impl<I, #(#types),*> redis_lua::Script for #tyname
@@ -207,21 +321,72 @@ impl<'a> Chain<'a> {
                 fn info(&self, info: &mut Vec<redis_lua::Info>, args: &mut Vec<redis_lua::ScriptArg>) {
                     self.inner.info(info, args);
                     info.push(self.info.clone());
+                    #(#keys)*
                     #(#args)*
                 }
             }
         }
     }
 
+    // `name(x=1, y=2)` with the bound arguments, requiring `Display` of them only when it's used.
+    fn impl_display(&self) -> TokenStream {
+        let tyname = self.tyname();
+        let types = self.types();
+        let bound = caps(self.script)
+            .chain(vars(self.script).take(self.index))
+            .map(|t| {
+                let p = to_param(t);
+                (t.1, quote! { self.#p.as_ref().unwrap() })
+            });
+        let defaults = defaults(self.script).map(|t| {
+            let p = to_param(t);
+            (t.1, quote! { self.#p })
+        });
+        let writes = bound.chain(defaults).enumerate().map(|(i, (arg, value))| {
+            // The name is not a part of the format string, which may contain braces, e.g. `@(format!("{}", x))`.
+            let sep = if i == 0 { "" } else { ", " };
+            let name = arg.as_rust().to_string();
+            quote! { write!(f, "{}{}={}", #sep, #name, #value)?; }
+        });
+
+        quote! {
+            impl<I, #(#types),*> std::fmt::Display for #tyname
+            where
+                #(#types: std::fmt::Display,)*
+            {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "{}(", self.info.name().unwrap_or("script"))?;
+                    #(#writes)*
+                    write!(f, ")")
+                }
+            }
+        }
+    }
+
+    // `$x, #$key`, the arguments in the order of the builder methods.
+    fn signature(&self) -> String {
+        vars(self.script)
+            .map(|(_, arg)| {
+                let sigil = if arg.is_key() { "#$" } else { "$" };
+                format!("{}{}", sigil, arg.as_rust())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
     pub fn gen(&self) -> TokenStream {
         let tyname = self.tyname();
         let types = self.types();
         let mems = self.mems();
+        let signature = self.signature();
 
         let impl_ctors = self.impl_ctors();
         let impl_methods = self.impl_methods();
+        let impl_binders = self.impl_binders();
+        let impl_defaults = self.impl_defaults();
         let impl_adders = self.impl_adders();
         let impl_script = self.impl_script();
+        let impl_display = self.impl_display();
 
         quote! {
             #[derive(Clone, Debug)]
@@ -233,13 +398,27 @@ impl<'a> Chain<'a> {
 
             impl<I, #(#types),*> #tyname
             {
+                /// The arguments of the script in the order they are given, e.g. `$x, #$key`.
+                const SIGNATURE: &'static str = #signature;
+
+                /// The arguments of the script in the order they are given.
+                fn signature(&self) -> &'static str {
+                    Self::SIGNATURE
+                }
+
                 #impl_ctors
 
                 #impl_methods
+
+                #impl_binders
+
+                #impl_defaults
             }
 
             #impl_script
 
+            #impl_display
+
             #impl_adders
         }
     }
@@ -270,6 +449,16 @@ impl<'a> Chain<'a> {
             .collect()
     }
 
+    // The members of the arguments with the default values mapped with the arguments.
+    fn defaults_f<F>(&self, map: F) -> Vec<TokenStream>
+    where
+        F: Fn(TokenStream, &Arg) -> TokenStream,
+    {
+        defaults(self.script)
+            .map(|t| map(to_param(t), t.1))
+            .collect()
+    }
+
     fn params_f<F>(&self, map: F) -> Vec<TokenStream>
     where
         F: Fn(usize, TokenStream) -> TokenStream,
@@ -294,6 +483,7 @@ impl<'a> Chain<'a> {
         caps(self.script)
             .map(to_mem)
             .chain(vars(self.script).map(to_mem).take(self.index))
+            .chain(defaults(self.script).map(to_default_mem))
             .collect()
     }
 
@@ -312,6 +502,11 @@ impl<'a> Chain<'a> {
         vars(self.script).map(to_type).nth(self.index).unwrap()
     }
 
+    // `A3: redis_lua::TypedArg<i64>` if annotated
+    fn typed_bound(&self) -> Option<TokenStream> {
+        vars(self.script).nth(self.index).and_then(to_typed_bound)
+    }
+
     // `A0: redis_lua::serde::Serialize`, ...
     fn bounds(&self) -> Vec<TokenStream> {
         caps(self.script)
@@ -350,13 +545,17 @@ impl<'a> PartialChain<'a> {
     fn impl_methods(&self) -> TokenStream {
         let varname = self.chain.varname();
         let vartype = self.chain.vartype();
+        let typed = self.chain.typed_bound();
 
         if let Some(next) = self.next() {
             let name = next.name();
             let tyname = next.tyname();
 
             quote! {
-                fn #varname<#vartype>(self, var: #vartype) -> #tyname {
+                fn #varname<#vartype>(self, var: #vartype) -> #tyname
+                where
+                    #typed
+                {
                     #name::new(self.chain.#varname(var), self.next)
                 }
             }
@@ -368,6 +567,7 @@ impl<'a> PartialChain<'a> {
                 where
                     S: redis_lua::TakeScript<#tyname>,
                     #vartype: redis_lua::redis::ToRedisArgs,
+                    #typed
                 {
                     let chain = self.chain.#varname(var);
                     let next = self.next;
@@ -378,10 +578,6 @@ impl<'a> PartialChain<'a> {
     }
 
     fn impl_adders(&self) -> TokenStream {
-        if self.chain.prev().is_some() {
-            return quote! {};
-        }
-
         let name = self.name();
         let types = self.chain.types();
 
@@ -1,7 +1,12 @@
-use crate::{file::as_path, proc_macro::Span, script::Script};
+use crate::{
+    commands::{check_arity, check_deterministic, check_static_commands},
+    file::as_path,
+    proc_macro::Span,
+    script::{Script, ARG_PREFIX},
+};
 use full_moon::{
     ast::{owned::Owned, AstError},
-    tokenizer::Token,
+    tokenizer::{Token, TokenType, TokenizerError},
     Error as ParseError,
 };
 use proc_macro_error::{Diagnostic as PDiagnostic, Level as PLevel};
@@ -25,7 +30,7 @@ fn emit_diag_one(span: Vec<Span>, cd: CheckerDiagnostic) {
     let d = cd.diagnostic;
     let msg = format!("in lua: {} ({})", d.message, d.code);
 
-    let pd = match span.get(0).cloned() {
+    let pd = match span.first().cloned() {
         Some(span) => PDiagnostic::spanned(span.into(), convert_level(cd.severity), msg),
         None => PDiagnostic::new(convert_level(cd.severity), msg),
     };
@@ -36,22 +41,60 @@ fn emit_diag_one(span: Vec<Span>, cd: CheckerDiagnostic) {
     pd.emit()
 }
 
-fn emit_parse_err(script: &Script, msg: &str, token: Option<&Token>) {
-    let range = match token {
-        Some(token) => (token.start_position().bytes(), token.end_position().bytes()),
-        None => (0, script.script().len()),
-    };
+fn emit_parse_err(script: &Script, msg: &str, range: Option<(usize, usize)>, note: Option<&str>) {
+    let range = range.unwrap_or((0, script.script().len()));
     let spans = script.range_to_span(range);
 
     let msg = format!("in lua: {} (parse_error)", msg);
 
-    let pd = match spans.get(0).cloned() {
+    let pd = match spans.first().cloned() {
         Some(span) => PDiagnostic::spanned(span.into(), PLevel::Error, msg),
         None => PDiagnostic::new(PLevel::Error, msg),
     };
+    let pd = match note {
+        Some(note) => pd.note(note.to_string()),
+        None => pd,
+    };
     pd.emit();
 }
 
+fn token_range(token: &Token) -> (usize, usize) {
+    (token.start_position().bytes(), token.end_position().bytes())
+}
+
+fn emit_tokenize_err(script: &Script, err: &TokenizerError) {
+    // The position of the error is available only in the message, e.g.
+    // `unclosed string at line 1, column 5`.
+    let msg = err.to_string();
+    let re = regex::Regex::new(r"^(.*) at line ([0-9]+), column ([0-9]+)$").unwrap();
+
+    match re.captures(&msg) {
+        Some(caps) => {
+            let line = caps[2].parse().unwrap_or(1);
+            let column = caps[3].parse().unwrap_or(1);
+            let range = line_column_to_bytes(script.script(), line, column).map(|b| (b, b));
+            emit_parse_err(script, &caps[1], range, None)
+        }
+        None => emit_parse_err(script, &msg, None, None),
+    }
+}
+
+/// Convert 1-indexed line/column to the byte offset in the script.
+fn line_column_to_bytes(script: &str, line: usize, column: usize) -> Option<usize> {
+    let begin: usize = script
+        .split('\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len() + 1)
+        .sum();
+    let line = script.get(begin..)?.split('\n').next()?;
+    let offset: usize = line
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(char::len_utf8)
+        .sum();
+    Some(begin + offset)
+}
+
 fn emit_diag(script: &Script, diags: Vec<CheckerDiagnostic>) {
     for d in diags {
         let label = d.diagnostic.primary_label.range;
@@ -60,7 +103,125 @@ fn emit_diag(script: &Script, diags: Vec<CheckerDiagnostic>) {
     }
 }
 
-fn make_cfg(args: &[String]) -> String {
+/// The identifiers referenced by the script with their ranges.
+fn idents(ast: &full_moon::ast::Ast) -> Vec<(String, (usize, usize))> {
+    ast.iter_tokens()
+        .filter_map(|t| match &*t.token_type() {
+            TokenType::Identifier { identifier } => Some((identifier.to_string(), token_range(t))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Report the arguments which are never referenced by the script, e.g. the ones only in comments.
+fn emit_unused(script: &Script, idents: &[(String, (usize, usize))]) {
+    for arg in script.args() {
+        if idents.iter().any(|(ident, _)| ident == arg.as_lua()) {
+            continue;
+        }
+
+        let msg = format!(
+            "in lua: `{}` is never used (unused_argument)",
+            arg.as_rust()
+        );
+        PDiagnostic::spanned(
+            arg.key().span().into(),
+            convert_level(Severity::Warning),
+            msg,
+        )
+        .emit();
+    }
+}
+
+/// Report the mismatches between the arguments referenced by the script and the declared ones.
+///
+/// The `ARGV` and `KEYS` indices of the declared arguments have to be contiguous from 1,
+/// same as the values passed to the script, and the script can only reference the declared arguments.
+fn emit_out_of_range(script: &Script, idents: &[(String, (usize, usize))]) {
+    let args = script.args();
+
+    for table in &["KEYS", "ARGV"] {
+        let params = args.iter().filter(|arg| arg.as_argv().starts_with(table));
+        for (index, arg) in params.enumerate() {
+            let expected = format!("{}[{}]", table, index + 1);
+            if arg.as_argv() == expected {
+                continue;
+            }
+
+            let msg = format!(
+                "in lua: `{}` is passed as `{}` but expected `{}` (argv_index)",
+                arg.as_rust(),
+                arg.as_argv(),
+                expected
+            );
+            PDiagnostic::spanned(arg.key().span().into(), PLevel::Error, msg).emit();
+        }
+    }
+
+    for (ident, range) in idents {
+        let index = match ident
+            .strip_prefix(ARG_PREFIX)
+            .and_then(|index| index.parse::<usize>().ok())
+        {
+            Some(index) => index,
+            None => continue,
+        };
+        if args.iter().any(|arg| arg.as_lua() == ident) {
+            continue;
+        }
+
+        let msg = format!(
+            "in lua: argument {} is out of range of {} declared arguments (argv_index)",
+            index,
+            args.len()
+        );
+        match script.range_to_span(*range).first().cloned() {
+            Some(span) => PDiagnostic::spanned(span.into(), PLevel::Error, msg).emit(),
+            None => PDiagnostic::new(PLevel::Error, msg).emit(),
+        }
+    }
+}
+
+/// Report the calls of the known Redis commands with the wrong number of arguments.
+fn emit_arity(script: &Script, ast: &full_moon::ast::Ast) {
+    for (msg, range) in check_arity(ast) {
+        let msg = format!("in lua: {} (command_arity)", msg);
+        match script.range_to_span(range).first().cloned() {
+            Some(span) => PDiagnostic::spanned(span.into(), PLevel::Error, msg).emit(),
+            None => PDiagnostic::new(PLevel::Error, msg).emit(),
+        }
+    }
+}
+
+/// Report the calls whose results differ by the call, which may let the replicas diverge.
+fn emit_nondeterministic(script: &Script, ast: &full_moon::ast::Ast) {
+    for (msg, range) in check_deterministic(ast) {
+        let msg = format!("in lua: {} (nondeterministic)", msg);
+        let pd = match script.range_to_span(range).first().cloned() {
+            Some(span) => PDiagnostic::spanned(span.into(), PLevel::Error, msg),
+            None => PDiagnostic::new(PLevel::Error, msg),
+        };
+        pd.help("add `nondeterministic,` in front of the script to allow it".into())
+            .emit();
+    }
+}
+
+fn emit_dynamic_command(script: &Script, ast: &full_moon::ast::Ast) {
+    for (msg, range) in check_static_commands(ast) {
+        let msg = format!("in lua: {} (dynamic_command)", msg);
+        let pd = match script.range_to_span(range).first().cloned() {
+            Some(span) => PDiagnostic::spanned(span.into(), PLevel::Error, msg),
+            None => PDiagnostic::new(PLevel::Error, msg),
+        };
+        pd.help(
+            "use a string literal, or add `dynamic_commands,` in front of the script to allow it"
+                .into(),
+        )
+        .emit();
+    }
+}
+
+fn make_cfg(args: &[String], functions: &[String]) -> String {
     let cfg = include_str!("redis.toml").to_string();
 
     let cfg = args.iter().fold(cfg, |cfg, arg| {
@@ -73,20 +234,61 @@ property = true"#,
         format!("{}\n{}", cfg, new_rule)
     });
 
+    // The functions take any arguments.
+    let cfg = functions.iter().fold(cfg, |cfg, function| {
+        let new_rule = format!(
+            r#"[[{}.args]]
+type = "..."
+required = false"#,
+            function
+        );
+
+        format!("{}\n{}", cfg, new_rule)
+    });
+
     cfg
 }
 
 pub struct Checker {
     defined: Vec<String>,
+    /// The functions defined outside of the script, e.g. by the prelude.
+    functions: Vec<String>,
+    /// The length of the shared prelude at the beginning of the script, whose helpers may be unused.
+    prelude: usize,
+    /// Whether to report the calls whose results differ by the call.
+    deterministic: bool,
+    /// Whether to report the calls whose command name is passed to the script.
+    static_commands: bool,
 }
 
 impl Checker {
     pub fn new() -> Self {
         Self {
             defined: Vec::new(),
+            functions: Vec::new(),
+            prelude: 0,
+            deterministic: cfg!(feature = "deterministic"),
+            static_commands: cfg!(feature = "static-commands"),
         }
     }
 
+    pub fn prelude(&mut self, len: usize) -> &mut Self {
+        self.prelude = len;
+        self
+    }
+
+    /// Allow the calls whose results differ by the call, e.g. `redis.call("time")`.
+    pub fn nondeterministic(&mut self, allow: bool) -> &mut Self {
+        self.deterministic &= !allow;
+        self
+    }
+
+    /// Allow the calls whose command name is passed to the script, e.g. `redis.call(@cmd)`.
+    pub fn dynamic_commands(&mut self, allow: bool) -> &mut Self {
+        self.static_commands &= !allow;
+        self
+    }
+
     pub fn define(&mut self, s: &str) -> &mut Self {
         self.defined.push(s.into());
         self
@@ -97,25 +299,36 @@ impl Checker {
         self
     }
 
+    pub fn functions(&mut self, s: Vec<String>) -> &mut Self {
+        self.functions.extend(s);
+        self
+    }
+
     pub fn check(&self, script: &Script) {
         let ast = match full_moon::parse(script.script()) {
             Ok(ast) => ast.owned(),
-            Err(ParseError::AstError(AstError::UnexpectedToken {
-                token,
-                additional: _,
-            })) => {
+            Err(ParseError::AstError(AstError::UnexpectedToken { token, additional })) => {
+                let msg = match token.to_string().as_str() {
+                    "" => "unexpected end of script".to_string(),
+                    t => format!("unexpected token `{}`", t),
+                };
                 return emit_parse_err(
                     script,
-                    &format!("unexpected token `{}`", token),
-                    Some(&token),
+                    &msg,
+                    Some(token_range(&token)),
+                    additional.as_deref(),
                 );
             }
+            Err(ParseError::TokenizerError(err)) => {
+                return emit_tokenize_err(script, &err);
+            }
             Err(_) => {
-                return emit_parse_err(script, "cannot tokenize lua script", None);
+                return emit_parse_err(script, "cannot parse lua script", None, None);
             }
         };
 
-        let std = StandardLibrary::from_file(&as_path(&make_cfg(&self.defined))).unwrap();
+        let std = StandardLibrary::from_file(&as_path(&make_cfg(&self.defined, &self.functions)))
+            .unwrap();
         let cfg: CheckerConfig<toml::value::Value> =
             toml::from_str(include_str!("selene.toml")).unwrap();
 
@@ -124,9 +337,24 @@ impl Checker {
 
         // Run the linter
         let mut diags = checker.test_on(&ast);
+        diags.retain(|d| {
+            d.diagnostic.code != "unused_variable"
+                || d.diagnostic.primary_label.range.0 as usize >= self.prelude
+        });
         diags.sort_by_key(|d| d.diagnostic.start_position());
 
         // Emit results as compiler messages
         emit_diag(script, diags);
+
+        let idents = idents(&ast);
+        emit_unused(script, &idents);
+        emit_out_of_range(script, &idents);
+        emit_arity(script, &ast);
+        if self.deterministic {
+            emit_nondeterministic(script, &ast);
+        }
+        if self.static_commands {
+            emit_dynamic_command(script, &ast);
+        }
     }
 }
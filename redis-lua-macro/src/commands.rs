@@ -0,0 +1,271 @@
+use crate::script::ARG_PREFIX;
+use full_moon::{
+    ast::{Ast, Call, Expression, FunctionArgs, FunctionCall, Index, Prefix, Suffix, Value, Var},
+    node::Node,
+    tokenizer::TokenType,
+    visitors::Visitor,
+};
+
+/// The number of the arguments, following the command name, taken by the common commands.
+///
+/// `None` as the maximum means any number of arguments, e.g. `DEL key [key ...]`.
+const COMMANDS: &[(&str, usize, Option<usize>)] = &[
+    ("append", 2, Some(2)),
+    ("decr", 1, Some(1)),
+    ("decrby", 2, Some(2)),
+    ("del", 1, None),
+    ("exists", 1, None),
+    ("expire", 2, Some(3)),
+    ("get", 1, Some(1)),
+    ("getset", 2, Some(2)),
+    ("hdel", 2, None),
+    ("hexists", 2, Some(2)),
+    ("hget", 2, Some(2)),
+    ("hgetall", 1, Some(1)),
+    ("hincrby", 3, Some(3)),
+    ("hlen", 1, Some(1)),
+    ("hmget", 2, None),
+    ("hset", 3, None),
+    ("incr", 1, Some(1)),
+    ("incrby", 2, Some(2)),
+    ("llen", 1, Some(1)),
+    ("lpop", 1, Some(2)),
+    ("lpush", 2, None),
+    ("lrange", 3, Some(3)),
+    ("mget", 1, None),
+    ("pexpire", 2, Some(3)),
+    ("rpop", 1, Some(2)),
+    ("rpush", 2, None),
+    ("sadd", 2, None),
+    ("scard", 1, Some(1)),
+    ("set", 2, None),
+    ("setex", 3, Some(3)),
+    ("sismember", 2, Some(2)),
+    ("smembers", 1, Some(1)),
+    ("srem", 2, None),
+    ("ttl", 1, Some(1)),
+    ("zadd", 3, None),
+    ("zcard", 1, Some(1)),
+    ("zincrby", 3, Some(3)),
+    ("zrange", 3, None),
+    ("zrem", 2, None),
+    ("zscore", 2, Some(2)),
+];
+
+/// The commands whose results differ by the call, so the replicas may diverge from the primary
+/// unless the effects of the script are replicated.
+const NONDETERMINISTIC: &[&str] = &[
+    "hrandfield",
+    "lastsave",
+    "randomkey",
+    "spop",
+    "srandmember",
+    "time",
+    "zrandmember",
+];
+
+/// Collects the misuses of the known commands by `redis.call` and `redis.pcall`.
+#[derive(Default)]
+struct Arity {
+    errors: Vec<(String, (usize, usize))>,
+}
+
+impl<'ast> Visitor<'ast> for Arity {
+    fn visit_function_call(&mut self, call: &FunctionCall<'ast>) {
+        let args = match redis_call(call) {
+            Some(args) => args,
+            None => return,
+        };
+        let command = match args.first().and_then(|arg| string(arg)) {
+            Some(command) => command,
+            None => return,
+        };
+        let (min, max) = match COMMANDS.iter().find(|c| c.0.eq_ignore_ascii_case(&command)) {
+            Some((_, min, max)) => (*min, *max),
+            None => return,
+        };
+
+        // The last argument expands to multiple values if it's a call or `...`.
+        let given = args.len() - 1;
+        let variadic = args.last().is_some_and(|arg| is_multi(arg));
+
+        let msg = if given < min && !variadic {
+            format!(
+                "`{}` takes at least {} but {} given",
+                command,
+                arguments(min),
+                given
+            )
+        } else if max.is_some_and(|max| given > max) {
+            format!(
+                "`{}` takes at most {} but {} given",
+                command,
+                arguments(max.unwrap()),
+                given
+            )
+        } else {
+            return;
+        };
+
+        if let Some((start, end)) = call.range() {
+            self.errors.push((msg, (start.bytes(), end.bytes())));
+        }
+    }
+}
+
+/// Collects the calls whose results differ by the call, e.g. `redis.call("time")` or `os.clock()`.
+#[derive(Default)]
+struct Nondeterministic {
+    errors: Vec<(String, (usize, usize))>,
+}
+
+impl<'ast> Visitor<'ast> for Nondeterministic {
+    fn visit_function_call(&mut self, call: &FunctionCall<'ast>) {
+        let msg = match redis_call(call) {
+            Some(args) => match args.first().and_then(|arg| string(arg)) {
+                Some(command)
+                    if NONDETERMINISTIC
+                        .iter()
+                        .any(|c| c.eq_ignore_ascii_case(&command)) =>
+                {
+                    format!("`{}` returns a different result by the call", command)
+                }
+                _ => return,
+            },
+            None if is_os_clock(call) => "`os.clock` returns a different result by the call".into(),
+            None => return,
+        };
+
+        if let Some((start, end)) = call.range() {
+            self.errors.push((msg, (start.bytes(), end.bytes())));
+        }
+    }
+}
+
+#[derive(Default)]
+struct DynamicCommand {
+    errors: Vec<(String, (usize, usize))>,
+}
+
+impl<'ast> Visitor<'ast> for DynamicCommand {
+    fn visit_function_call(&mut self, call: &FunctionCall<'ast>) {
+        let dynamic = redis_call(call)
+            .and_then(|args| args.first().copied())
+            .is_some_and(is_arg);
+
+        if let (true, Some((start, end))) = (dynamic, call.range()) {
+            self.errors.push((
+                "the command name is passed to the script by `@` or `$`".into(),
+                (start.bytes(), end.bytes()),
+            ));
+        }
+    }
+}
+
+/// Whether the expression is the value of `@` or `$`, e.g. `@cmd`.
+fn is_arg(expr: &Expression) -> bool {
+    match expr {
+        Expression::Value {
+            value, binop: None, ..
+        } => match &**value {
+            Value::Var(Var::Name(name)) => name.to_string().trim().starts_with(ARG_PREFIX),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Whether the call is `os.clock()`.
+fn is_os_clock(call: &FunctionCall) -> bool {
+    match call.prefix() {
+        Prefix::Name(name) if name.to_string().trim() == "os" => {}
+        _ => return false,
+    }
+
+    matches!(
+        call.iter_suffixes().next(),
+        Some(Suffix::Index(Index::Dot { name, .. })) if name.to_string().trim() == "clock"
+    )
+}
+
+fn arguments(n: usize) -> String {
+    match n {
+        1 => "1 argument".into(),
+        n => format!("{} arguments", n),
+    }
+}
+
+/// The arguments of `redis.call(...)` or `redis.pcall(...)`.
+fn redis_call<'a, 'ast>(call: &'a FunctionCall<'ast>) -> Option<Vec<&'a Expression<'ast>>> {
+    match call.prefix() {
+        Prefix::Name(name) if name.to_string().trim() == "redis" => {}
+        _ => return None,
+    }
+
+    let suffixes: Vec<_> = call.iter_suffixes().collect();
+    match suffixes.as_slice() {
+        [Suffix::Index(Index::Dot { name, .. }), Suffix::Call(Call::AnonymousCall(FunctionArgs::Parentheses { arguments, .. }))]
+            if ["call", "pcall"].contains(&name.to_string().trim()) =>
+        {
+            Some(arguments.iter().collect())
+        }
+        _ => None,
+    }
+}
+
+/// The string literal, e.g. the command name.
+fn string(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Value {
+            value, binop: None, ..
+        } => match &**value {
+            Value::String(token) => match &*token.token_type() {
+                TokenType::StringLiteral { literal, .. } => Some(literal.to_string()),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether the expression can be multiple values, e.g. `unpack(t)` or `...`.
+fn is_multi(expr: &Expression) -> bool {
+    match expr {
+        Expression::Value {
+            value, binop: None, ..
+        } => match &**value {
+            Value::FunctionCall(_) => true,
+            Value::Symbol(token) => token.to_string().trim() == "...",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Find the calls of the known commands with the wrong number of arguments, e.g. `redis.call("get")`.
+///
+/// Returns the messages with the ranges of the calls.
+pub fn check_arity(ast: &Ast) -> Vec<(String, (usize, usize))> {
+    let mut arity = Arity::default();
+    arity.visit_ast(ast);
+    arity.errors
+}
+
+/// Find the calls whose results differ by the call, e.g. `redis.call("srandmember", key)`.
+///
+/// Returns the messages with the ranges of the calls.
+pub fn check_deterministic(ast: &Ast) -> Vec<(String, (usize, usize))> {
+    let mut nondeterministic = Nondeterministic::default();
+    nondeterministic.visit_ast(ast);
+    nondeterministic.errors
+}
+
+/// Find the calls whose command name is passed to the script, e.g. `redis.call(@cmd, key)`.
+///
+/// Returns the messages with the ranges of the calls.
+pub fn check_static_commands(ast: &Ast) -> Vec<(String, (usize, usize))> {
+    let mut dynamic = DynamicCommand::default();
+    dynamic.visit_ast(ast);
+    dynamic.errors
+}
@@ -0,0 +1,88 @@
+use crate::proc_macro::{Delimiter, Ident, TokenStream as TokenStream1, TokenTree};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+fn is_punct(t: &TokenTree, c: char) -> bool {
+    matches!(t, TokenTree::Punct(p) if p.as_char() == c)
+}
+
+/// Split the name and the variants of a fieldless enum, e.g. `enum Color { Red, Green = 3 }`.
+pub fn split_enum(input: TokenStream1) -> (Ident, Vec<Ident>) {
+    let tokens: Vec<_> = input.into_iter().collect();
+
+    // Skip the attributes and the visibility in front of `enum`.
+    let pos = match tokens
+        .iter()
+        .position(|t| matches!(t, TokenTree::Ident(i) if i.to_string() == "enum"))
+    {
+        Some(pos) => pos,
+        None => proc_macro_error::abort_call_site!("`ScriptArg` can only be derived for enums"),
+    };
+
+    let (name, body) = match &tokens[pos + 1..] {
+        [TokenTree::Ident(name), TokenTree::Group(body)]
+            if body.delimiter() == Delimiter::Brace =>
+        {
+            (name.clone(), body.stream())
+        }
+        [TokenTree::Ident(name), t, ..] if is_punct(t, '<') => proc_macro_error::abort!(
+            t.span(),
+            "`ScriptArg` can't be derived for the generic enum `{}`",
+            name
+        ),
+        _ => proc_macro_error::abort_call_site!("expected the variants of the enum"),
+    };
+
+    let body: Vec<_> = body.into_iter().collect();
+    let mut variants = Vec::new();
+
+    for variant in body.split(|t| is_punct(t, ',')) {
+        // Skip the attributes of the variant, e.g. `#[default]`.
+        let mut rest = variant;
+        while let [pound, TokenTree::Group(_), tail @ ..] = rest {
+            if !is_punct(pound, '#') {
+                break;
+            }
+            rest = tail;
+        }
+
+        match rest {
+            [] => {}
+            [TokenTree::Ident(variant)] => variants.push(variant.clone()),
+            [TokenTree::Ident(variant), eq, ..] if is_punct(eq, '=') => {
+                variants.push(variant.clone())
+            }
+            [TokenTree::Ident(variant), t, ..] => proc_macro_error::abort!(
+                t.span(),
+                "`ScriptArg` can only be derived for the enums without fields, but `{}` has fields",
+                variant
+            ),
+            [t, ..] => proc_macro_error::abort!(t.span(), "expected a variant"),
+        }
+    }
+
+    (name, variants)
+}
+
+/// Implement `Serialize` for the fieldless enum, which serializes the variants as their discriminants.
+pub fn gen_script_arg(name: &Ident, variants: &[Ident]) -> TokenStream {
+    let name = crate::to_ident(&TokenTree::Ident(name.clone()));
+    let variants: Vec<_> = variants
+        .iter()
+        .map(|v| crate::to_ident(&TokenTree::Ident(v.clone())))
+        .collect();
+
+    quote! {
+        impl redis_lua::serde::Serialize for #name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: redis_lua::serde::Serializer,
+            {
+                let discriminant = match self {
+                    #(#name::#variants => #name::#variants as i64,)*
+                };
+                serializer.serialize_i64(discriminant)
+            }
+        }
+    }
+}
@@ -3,20 +3,25 @@ extern crate proc_macro;
 use self::proc_macro::{TokenStream as TokenStream1, TokenTree};
 use proc_macro2::TokenStream;
 use proc_macro_error::proc_macro_error;
-use quote::quote;
+use quote::{quote, quote_spanned};
 
 mod chains;
 mod check;
+mod commands;
+mod derive;
 mod file;
 mod patterns;
+mod returns;
 mod script;
+mod template;
 mod token;
 
 use crate::{
     chains::ChainIter,
     check::Checker,
-    patterns::{all, caps},
-    script::Script,
+    patterns::{all, argv, caps, keys},
+    script::{ArgType, Script},
+    token::Tokens,
 };
 
 use proc_macro_hack::proc_macro_hack;
@@ -46,37 +51,468 @@ fn gen_all(script: &Script) -> TokenStream {
 #[proc_macro_error]
 #[proc_macro_hack]
 pub fn lua(input: TokenStream1) -> TokenStream1 {
-    let script = Script::new(input, true);
+    let (options, input) = split_options(input);
+
+    // Both scripts are generated, and rustc selects one by the condition.
+    if let Some((cond, then, other)) = split_cfg(&input) {
+        let then = gen_lua(&Script::new(then, true), quote! {}, &options);
+        let other = gen_lua(&Script::new(other, true), quote! {}, &options);
+        return quote! {
+            {
+                #[cfg(#cond)]
+                let script = #then;
+                #[cfg(not(#cond))]
+                let script = #other;
+                script
+            }
+        }
+        .into();
+    }
+
+    gen_lua(&Script::new(input, true), quote! {}, &options).into()
+}
+
+#[proc_macro_error]
+#[proc_macro_derive(ScriptArg)]
+pub fn derive_script_arg(input: TokenStream1) -> TokenStream1 {
+    let (name, variants) = derive::split_enum(input);
+    derive::gen_script_arg(&name, &variants).into()
+}
+
+#[proc_macro_error]
+#[proc_macro_hack]
+pub fn lua_fmt(input: TokenStream1) -> TokenStream1 {
+    let (params, input) = template::split_params(input);
+    let names: Vec<_> = params.iter().map(|(name, _)| name.clone()).collect();
+    let input = template::replace_placeholders(input, &names);
+
+    let options = Options {
+        format: params.into_iter().map(|(_, value)| value).collect(),
+        ..Options::default()
+    };
+
+    gen_lua(&Script::new(input, true), quote! {}, &options).into()
+}
+
+#[proc_macro_error]
+#[proc_macro_hack]
+pub fn lua_module(input: TokenStream1) -> TokenStream1 {
+    let (prelude, fns) = split_module(input);
+
+    // The prelude is checked as a part of each script, where its helpers may be unused.
+    let prelude_len = match &prelude {
+        Some(prelude) => {
+            let script = Script::new(prelude.clone(), true);
+            if let Some(arg) = script.args().first() {
+                proc_macro_error::abort!(
+                    arg.key().span(),
+                    "the prelude of `lua_module!` can't take arguments"
+                );
+            }
+            script.script().len()
+        }
+        None => 0,
+    };
+
+    let names: Vec<_> = fns.iter().map(|(name, _)| to_ident(name)).collect();
+    let types: Vec<_> = (0..fns.len())
+        .map(|i| patterns::new_ident(format!("S{}", i)))
+        .collect();
+    let scripts = fns.iter().map(|(name, body)| {
+        let mut input = prelude.clone().unwrap_or_default();
+        input.extend(body.clone());
+        let options = Options {
+            name: Some(name.to_string()),
+            prelude_len,
+            ..Options::default()
+        };
+        gen_lua(&Script::new(input, true), quote! {}, &options)
+    });
+
+    quote! {
+        {
+            /// The scripts of `lua_module!` by their names.
+            #[derive(Clone, Debug)]
+            struct LuaModule<#(#types),*> {
+                #(#names: #types,)*
+            }
+
+            LuaModule {
+                #(#names: #scripts,)*
+            }
+        }
+    }
+    .into()
+}
+
+/// Split the module, e.g. `prelude { ... } fn add { ... } fn sub() { ... }`, into the prelude and the scripts.
+fn split_module(input: TokenStream1) -> (Option<TokenStream1>, Vec<(TokenTree, TokenStream1)>) {
+    use proc_macro::Delimiter;
+
+    let tokens: Vec<_> = input.into_iter().collect();
+    let mut rest = tokens.as_slice();
+    let mut prelude = None;
+    let mut fns = Vec::new();
+
+    if let [TokenTree::Ident(ident), TokenTree::Group(body), tail @ ..] = rest {
+        if ident.to_string() == "prelude" && body.delimiter() == Delimiter::Brace {
+            prelude = Some(body.stream());
+            rest = tail;
+        }
+    }
+
+    loop {
+        match rest {
+            [] => break,
+            [TokenTree::Ident(fn_), name @ TokenTree::Ident(_), tail @ ..]
+                if fn_.to_string() == "fn" =>
+            {
+                // The parentheses are optional, e.g. `fn add() { ... }`.
+                let tail = match tail {
+                    [TokenTree::Group(params), tail @ ..]
+                        if params.delimiter() == Delimiter::Parenthesis
+                            && params.stream().is_empty() =>
+                    {
+                        tail
+                    }
+                    tail => tail,
+                };
+                match tail {
+                    [TokenTree::Group(body), tail @ ..] if body.delimiter() == Delimiter::Brace => {
+                        fns.push((name.clone(), body.stream()));
+                        rest = tail;
+                    }
+                    _ => proc_macro_error::abort!(
+                        name.span(),
+                        "expected the script of `{}` in braces",
+                        name
+                    ),
+                }
+            }
+            [t, ..] => proc_macro_error::abort!(t.span(), "expected `fn name {{ ... }}`"),
+        }
+    }
+
+    if fns.is_empty() {
+        proc_macro_error::abort_call_site!("expected at least one `fn name {{ ... }}`");
+    }
+
+    (prelude, fns)
+}
+
+/// Split the conditional script, e.g. `if cfg!(feature = "trace") { ... } else { ... }`,
+/// into the condition and the scripts. The script is empty if `else` is omitted.
+fn split_cfg(input: &TokenStream1) -> Option<(TokenStream, TokenStream1, TokenStream1)> {
+    use proc_macro::Delimiter;
+
+    let tokens: Vec<_> = input.clone().into_iter().collect();
+
+    let (cond, then, rest) = match tokens.as_slice() {
+        [TokenTree::Ident(if_), TokenTree::Ident(cfg), TokenTree::Punct(bang), TokenTree::Group(cond), TokenTree::Group(then), rest @ ..]
+            if if_.to_string() == "if"
+                && cfg.to_string() == "cfg"
+                && bang.as_char() == '!'
+                && cond.delimiter() == Delimiter::Parenthesis
+                && then.delimiter() == Delimiter::Brace =>
+        {
+            (cond, then, rest)
+        }
+        _ => return None,
+    };
+
+    let other = match rest {
+        [] => TokenStream1::new(),
+        [TokenTree::Ident(else_), TokenTree::Group(other)]
+            if else_.to_string() == "else" && other.delimiter() == Delimiter::Brace =>
+        {
+            other.stream()
+        }
+        _ => proc_macro_error::abort!(
+            rest[0].span(),
+            "expected `else {{ ... }}` after the conditional script"
+        ),
+    };
+
+    Some((cond.stream().into(), then.stream(), other))
+}
 
+/// Options in front of the script, e.g. `name = "transfer", protected, { ... }`.
+#[derive(Clone, Debug, Default)]
+struct Options {
+    /// The name of the script.
+    name: Option<String>,
+    /// Whether the script is run in `pcall`.
+    protected: bool,
+    /// Whether the script is invoked by `EVAL_RO`.
+    readonly: bool,
+    /// Whether the script may call the commands whose results differ by the call.
+    nondeterministic: bool,
+    /// Whether the script may pass the command name to `redis.call` by `@` or `$`.
+    dynamic_commands: bool,
+    /// The identity of the script in the cache.
+    cache_key: Option<String>,
+    /// The values substituted into the placeholders of `lua_fmt!` at runtime.
+    format: Vec<TokenStream>,
+    /// The length of the prelude of `lua_module!` at the beginning of the script.
+    prelude_len: usize,
+    /// The helpers of the preludes given by `Script::use_prelude` which the script calls.
+    prelude: Vec<String>,
+}
+
+/// Split the options from the script.
+///
+/// The script may be enclosed in braces after the options.
+fn split_options(input: TokenStream1) -> (Options, TokenStream1) {
+    let tokens: Vec<_> = input.clone().into_iter().collect();
+    let mut options = Options::default();
+    let mut rest = tokens.as_slice();
+
+    loop {
+        match rest {
+            [TokenTree::Ident(ident), TokenTree::Punct(eq), TokenTree::Literal(lit), TokenTree::Punct(comma), tail @ ..]
+                if eq.as_char() == '=' && comma.as_char() == ',' =>
+            {
+                let value = lit.to_string();
+                let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                    Some(value) => value.to_string(),
+                    None => proc_macro_error::abort!(
+                        lit.span(),
+                        "expected a string literal as the value of `{}`",
+                        ident
+                    ),
+                };
+                match ident.to_string().as_str() {
+                    "name" => options.name = Some(value),
+                    "cache_key" => options.cache_key = Some(value),
+                    "prelude" => {
+                        options.prelude = value
+                            .split(|c: char| c == ',' || c.is_whitespace())
+                            .filter(|name| !name.is_empty())
+                            .map(|name| name.to_string())
+                            .collect()
+                    }
+                    _ => break,
+                }
+                rest = tail;
+            }
+            [TokenTree::Ident(ident), TokenTree::Punct(comma), tail @ ..]
+                if ident.to_string() == "protected" && comma.as_char() == ',' =>
+            {
+                options.protected = true;
+                rest = tail;
+            }
+            [TokenTree::Ident(ident), TokenTree::Punct(comma), tail @ ..]
+                if ident.to_string() == "readonly" && comma.as_char() == ',' =>
+            {
+                options.readonly = true;
+                rest = tail;
+            }
+            [TokenTree::Ident(ident), TokenTree::Punct(comma), tail @ ..]
+                if ident.to_string() == "nondeterministic" && comma.as_char() == ',' =>
+            {
+                options.nondeterministic = true;
+                rest = tail;
+            }
+            [TokenTree::Ident(ident), TokenTree::Punct(comma), tail @ ..]
+                if ident.to_string() == "dynamic_commands" && comma.as_char() == ',' =>
+            {
+                options.dynamic_commands = true;
+                rest = tail;
+            }
+            _ => break,
+        }
+    }
+
+    if rest.len() == tokens.len() {
+        return (options, input);
+    }
+
+    let script = match rest {
+        [TokenTree::Group(group)] if group.delimiter() == proc_macro::Delimiter::Brace => {
+            group.stream()
+        }
+        _ => rest.iter().cloned().collect(),
+    };
+
+    (options, script)
+}
+
+#[proc_macro_error]
+#[proc_macro_hack]
+pub fn include_lua(input: TokenStream1) -> TokenStream1 {
+    let (path, span) = match input.into_iter().collect::<Vec<_>>().as_slice() {
+        [TokenTree::Literal(lit)] => (lit.to_string(), lit.span()),
+        _ => proc_macro_error::abort_call_site!("expected a path to a lua script"),
+    };
+    let path = match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        Some(path) => path,
+        None => proc_macro_error::abort_call_site!("expected a path to a lua script"),
+    };
+
+    // Same as `include_str!`, the path is relative to the file of the macro invocation.
+    let path = match span.local_file() {
+        Some(file) => file.parent().unwrap_or(&file).join(path),
+        None => std::path::Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default())
+            .join(path),
+    };
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) => {
+            proc_macro_error::abort_call_site!("cannot read {}: {}", path.display(), e)
+        }
+    };
+
+    // Rebuild when the script changes.
+    let path = std::env::current_dir()
+        .map(|dir| dir.join(&path))
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string();
+    let depend = quote! { const _: &str = include_str!(#path); };
+
+    gen_lua(
+        &Script::from_tokens(Tokens::from_source(&source, span), true),
+        depend,
+        &Options::default(),
+    )
+    .into()
+}
+
+fn gen_lua(script: &Script, depend: TokenStream, options: &Options) -> TokenStream {
     Checker::new()
-        .defines(all(&script).map(|(_, arg)| arg.as_lua().into()).collect())
-        .check(&script);
+        .define("KEYS")
+        .define(token::PREV)
+        .defines(all(script).map(|(_, arg)| arg.as_lua().into()).collect())
+        .functions(options.prelude.clone())
+        .prelude(options.prelude_len)
+        .nondeterministic(options.nondeterministic)
+        .dynamic_commands(options.dynamic_commands)
+        .check(script);
+
+    let defs = gen_all(script);
+
+    let (body_str, script_str) = if options.protected {
+        script.protect()
+    } else {
+        (script.script().to_string(), script.wrap().to_string())
+    };
 
-    let defs = gen_all(&script);
+    // Each argument is initialized from its own `ARGV[n]` in the same order as the arguments of `Info`.
+    let argv_len = argv(script).count();
+    let indices = crate::script::argv_indices(&script_str);
+    if !indices.iter().copied().eq(1..=argv_len) {
+        proc_macro_error::abort_call_site!(
+            "the script reads `ARGV` at {:?} but declares {} arguments",
+            indices,
+            argv_len
+        );
+    }
 
-    let body_str = script.script();
-    let script_str = script.wrap();
+    // Keys come first, same as the order of the values passed to the script.
+    let names = keys(script).chain(argv(script)).map(|(_, arg)| {
+        let name = arg.as_rust().to_string();
+        quote! { #name }
+    });
 
-    let args = all(&script).map(|(_, arg)| {
+    let sources = keys(script).chain(argv(script)).map(|(_, arg)| {
+        if arg.atype() == ArgType::Cap {
+            quote! { redis_lua::ArgSource::Capture }
+        } else {
+            quote! { redis_lua::ArgSource::Argument }
+        }
+    });
+
+    let args = argv(script).map(|(_, arg)| {
         let arg = arg.as_lua().to_string();
         quote! { #arg }
     });
 
-    let caps = caps(&script).map(|(_, arg)| {
+    let keys = keys(script).map(|(_, arg)| {
+        let key = arg.as_lua().to_string();
+        quote! { #key }
+    });
+
+    let name = options
+        .name
+        .as_ref()
+        .map(|name| quote! { .with_name(#name) });
+    let cache_key = options
+        .cache_key
+        .as_ref()
+        .map(|key| quote! { .with_cache_key(#key) });
+    // The body of the protected script begins with the line of `pcall`.
+    let location = script.line().map(|line| {
+        let line = if options.protected {
+            line.saturating_sub(1)
+        } else {
+            line
+        } as u32;
+        quote! { .with_location(file!(), #line) }
+    });
+    let readonly = if options.readonly {
+        quote! { .with_readonly() }
+    } else {
+        quote! {}
+    };
+
+    // The script of `lua_fmt!` is completed at runtime.
+    let (new, script_str, body_str, values) = if options.format.is_empty() {
+        (
+            quote! { new },
+            quote! { #script_str },
+            quote! { #body_str },
+            quote! {},
+        )
+    } else {
+        let values = &options.format;
+        (
+            quote! { new_owned },
+            quote! { redis_lua::fmt_script(#script_str, &__redis_lua_fmt) },
+            quote! { redis_lua::fmt_script(#body_str, &__redis_lua_fmt) },
+            quote! { let __redis_lua_fmt = [#((#values).to_string()),*]; },
+        )
+    };
+
+    // The bound is checked where the value is captured so that the error points at `@`.
+    let caps = caps(script).map(|(_, arg)| {
+        let span = arg.as_rust().span().into();
         let arg = to_ident(arg.as_rust());
-        quote! { #arg }
+        quote_spanned! { span=> redis_lua::capture(#arg) }
     });
 
-    let script_code = quote! {
+    quote! {
         {
             use redis_lua::Script;
 
+            #depend
             #defs
+            #values
 
-            Chain0::new(redis_lua::Info::new(#script_str, #body_str, &[#(#args),*]), (), #(#caps),*)
+            Chain0::new(
+                redis_lua::Info::#new(#script_str, #body_str, &[#(#args),*]).with_keys(&[#(#keys),*]).with_names(&[#(#names),*]).with_sources(&[#(#sources),*])#name #readonly #cache_key #location,
+                (),
+                #(#caps),*
+            )
         }
-    };
-    script_code.into()
+    }
+}
+
+#[proc_macro_error]
+#[proc_macro_hack]
+pub fn lua_prelude(input: TokenStream1) -> TokenStream1 {
+    let script = Script::new(input, true);
+    if let Some(arg) = script.args().first() {
+        proc_macro_error::abort!(arg.key().span(), "the prelude can't take arguments");
+    }
+
+    // The helpers may be unused by any script.
+    Checker::new().prelude(script.script().len()).check(&script);
+
+    let source = script.script();
+    quote! {
+        redis_lua::Prelude::new(#source)
+    }
+    .into()
 }
 
 #[proc_macro_error]
@@ -84,7 +520,7 @@ pub fn lua(input: TokenStream1) -> TokenStream1 {
 pub fn lua_s(input: TokenStream1) -> TokenStream1 {
     let script = Script::new(input, false);
 
-    Checker::new().define("ARGV").check(&script);
+    Checker::new().define("ARGV").define("KEYS").check(&script);
 
     let script = script.script();
     let script_code = quote! {
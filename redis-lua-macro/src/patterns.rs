@@ -1,6 +1,7 @@
 use crate::{
     proc_macro::{Ident, Span, TokenStream as TokenStream1, TokenTree},
     script::{Arg, ArgType, Script},
+    to_ident,
 };
 use proc_macro2::TokenStream;
 use quote::quote;
@@ -22,6 +23,18 @@ pub fn vars(script: &Script) -> impl Iterator<Item = (usize, &Arg)> {
     all(script).filter(|(_, arg)| arg.atype() == ArgType::Var)
 }
 
+pub fn defaults(script: &Script) -> impl Iterator<Item = (usize, &Arg)> {
+    all(script).filter(|(_, arg)| arg.atype() == ArgType::Default)
+}
+
+pub fn keys(script: &Script) -> impl Iterator<Item = (usize, &Arg)> {
+    all(script).filter(|(_, arg)| arg.is_key())
+}
+
+pub fn argv(script: &Script) -> impl Iterator<Item = (usize, &Arg)> {
+    all(script).filter(|(_, arg)| !arg.is_key())
+}
+
 pub fn to_name((_index, arg): (usize, &Arg)) -> TokenStream {
     new_ident(arg.as_rust().to_string())
 }
@@ -40,6 +53,21 @@ pub fn to_mem(t: (usize, &Arg)) -> TokenStream {
     quote! { #p: Option<#t> }
 }
 
+// `a3: redis_lua::ScriptArg` for the argument with the default value, which is serialized when it's set
+pub fn to_default_mem(t: (usize, &Arg)) -> TokenStream {
+    let p = to_param(t);
+    quote! { #p: redis_lua::ScriptArg }
+}
+
+// `redis_lua::script_arg(&10)` for `$x = 10`, or `redis_lua::script_key` for `#$x = "key"`
+pub fn to_script_arg(arg: &Arg, value: TokenStream) -> TokenStream {
+    if arg.is_key() {
+        quote! { redis_lua::script_key(&#value) }
+    } else {
+        quote! { redis_lua::script_arg(&#value) }
+    }
+}
+
 pub fn to_arg(t: (usize, &Arg)) -> TokenStream {
     let p = to_param(t);
     let t = to_type(t);
@@ -56,6 +84,13 @@ pub fn to_bound_life(t: (usize, &Arg)) -> TokenStream {
     quote! { #t: redis_lua::serde::Serialize + 'a }
 }
 
+// `A3: redis_lua::TypedArg<i64>` for `$x: i64`
+pub fn to_typed_bound(t: (usize, &Arg)) -> Option<TokenStream> {
+    let ty = to_ident(t.1.ty()?);
+    let t = to_type(t);
+    Some(quote! { #t: redis_lua::TypedArg<#ty> })
+}
+
 pub fn varlen(script: &Script) -> usize {
     vars(script).count()
 }
@@ -0,0 +1,105 @@
+use crate::token::Token;
+use std::collections::BTreeSet;
+
+/// Tracks the Lua blocks which are closed by `end` or `until`.
+#[derive(Default)]
+struct Blocks {
+    /// Whether each open block is a function.
+    blocks: Vec<bool>,
+    /// Number of `for` and `while` waiting for their `do`.
+    loops: usize,
+}
+
+impl Blocks {
+    fn feed(&mut self, t: &Token) {
+        if t.is_arg() {
+            return;
+        }
+
+        if t.is("function") {
+            self.blocks.push(true);
+        } else if t.is("if") || t.is("repeat") {
+            self.blocks.push(false);
+        } else if t.is("for") || t.is("while") {
+            self.blocks.push(false);
+            self.loops += 1;
+        } else if t.is("do") {
+            if self.loops > 0 {
+                self.loops -= 1;
+            } else {
+                self.blocks.push(false);
+            }
+        } else if t.is("end") || t.is("until") {
+            self.blocks.pop();
+        }
+    }
+
+    fn in_function(&self) -> bool {
+        self.blocks.contains(&true)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+/// Find the top-level `return` statements with multiple values, e.g. `return a, b`.
+///
+/// Returns the indices of the first and the last token of the values of each statement.
+fn multi_returns(tokens: &[Token]) -> Vec<(usize, usize)> {
+    let mut blocks = Blocks::default();
+    let mut returns = Vec::new();
+
+    for (i, t) in tokens.iter().enumerate() {
+        if !t.is_arg() && t.is("return") && !blocks.in_function() {
+            if let Some(range) = values(&tokens[i + 1..]) {
+                returns.push((i + 1 + range.0, i + 1 + range.1));
+            }
+        }
+        blocks.feed(t);
+    }
+
+    returns
+}
+
+/// The range of the values of a `return` statement if there are multiple values.
+fn values(tokens: &[Token]) -> Option<(usize, usize)> {
+    // Blocks and brackets in the values, e.g. `function() ... end` and `(a, b)`.
+    let mut blocks = Blocks::default();
+    let mut depth = 0usize;
+    let mut commas = 0;
+    let mut last = None;
+
+    for (i, t) in tokens.iter().enumerate() {
+        let top = depth == 0 && blocks.is_empty();
+
+        if top
+            && !t.is_arg()
+            && ["end", "else", "elseif", "until", ";"]
+                .iter()
+                .any(|s| t.is(s))
+        {
+            break;
+        }
+        if top && t.is(",") {
+            commas += 1;
+        }
+        if t.is("(") || t.is("{") || t.is("[") {
+            depth += 1;
+        } else if t.is(")") || t.is("}") || t.is("]") {
+            depth = depth.saturating_sub(1);
+        }
+        blocks.feed(t);
+        last = Some(i);
+    }
+
+    match last {
+        Some(last) if commas > 0 => Some((0, last)),
+        _ => None,
+    }
+}
+
+/// Indices of the tokens to be preceded by `{` and followed by `}` respectively.
+pub fn wrap_multi_returns(tokens: &[Token]) -> (BTreeSet<usize>, BTreeSet<usize>) {
+    multi_returns(tokens).into_iter().unzip()
+}
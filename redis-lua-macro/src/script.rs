@@ -1,13 +1,19 @@
 use crate::{
     proc_macro::{Span, TokenStream, TokenTree},
-    token::{retokenize, Pos, Token},
+    returns::wrap_multi_returns,
+    token::{retokenize, span_line, Pos, Token, Tokens},
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The prefix of the Lua locals which hold the values of `@` and `$`.
+pub const ARG_PREFIX: &str = "__redis_lua_arg_";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArgType {
     Cap,
     Var,
+    /// `$` with the default value, which may be omitted.
+    Default,
 }
 
 #[derive(Debug, Clone)]
@@ -40,19 +46,34 @@ impl Arg {
         &self.rust
     }
 
-    /// As lua internal variable, e.g. `__internal_from_args1`
+    /// As lua internal variable, e.g. `__redis_lua_arg_1`
     pub fn as_lua(&self) -> &str {
         &self.lua
     }
 
-    /// As `ARGV` parameter, e.g. `ARGV[1]`
+    /// As `ARGV` parameter, e.g. `ARGV[1]`, or `KEYS` parameter, e.g. `KEYS[1]`
     pub fn as_argv(&self) -> &str {
         &self.argv
     }
 
+    /// Type annotation, e.g. `i64` of `$x: i64`
+    pub fn ty(&self) -> Option<&TokenTree> {
+        self.key.ty()
+    }
+
+    /// Default value, e.g. `10` of `$x = 10`
+    pub fn default(&self) -> Option<&[TokenTree]> {
+        self.key.default()
+    }
+
     pub fn atype(&self) -> ArgType {
         self.atype
     }
+
+    /// Passed as `KEYS` instead of `ARGV`
+    pub fn is_key(&self) -> bool {
+        self.key.is_key()
+    }
 }
 
 #[derive(Debug)]
@@ -67,14 +88,56 @@ impl Args {
         let tt = token.tree();
         let key = token.clone();
 
-        match self.0.iter().find(|arg| arg.key() == &key) {
-            Some(arg) => arg.clone(),
+        match self.0.iter_mut().find(|arg| arg.key() == &key) {
+            Some(arg) => {
+                // The type annotation may be given to any of the occurrences.
+                match (arg.ty(), token.ty()) {
+                    (Some(ty1), Some(ty2)) if ty1.to_string() != ty2.to_string() => {
+                        proc_macro_error::abort!(
+                            ty2.span(),
+                            "`{}` is annotated with `{}` and `{}`",
+                            arg.as_rust(),
+                            ty1,
+                            ty2
+                        )
+                    }
+                    _ => {}
+                }
+
+                // So is the default value.
+                let to_string =
+                    |v: &[TokenTree]| v.iter().map(|t| t.to_string()).collect::<String>();
+                match (arg.default(), token.default()) {
+                    (Some(v1), Some(v2)) if to_string(v1) != to_string(v2) => {
+                        proc_macro_error::abort!(
+                            v2[0].span(),
+                            "`{}` defaults to `{}` and `{}`",
+                            arg.as_rust(),
+                            to_string(v1),
+                            to_string(v2)
+                        )
+                    }
+                    (None, Some(_)) => arg.atype = ArgType::Default,
+                    _ => {}
+                }
+
+                arg.key = arg.key.clone().merge(token);
+                arg.clone()
+            }
             None => {
                 let rust = tt.clone();
-                let lua = format!("__internal_from_args_{}", self.0.len());
-                let argv = format!("ARGV[{}]", self.0.len() + 1);
+                let lua = format!("{}{}", ARG_PREFIX, self.0.len());
+                let argv = if token.is_key() {
+                    let index = self.0.iter().filter(|arg| arg.is_key()).count();
+                    format!("KEYS[{}]", index + 1)
+                } else {
+                    let index = self.0.iter().filter(|arg| !arg.is_key()).count();
+                    format!("ARGV[{}]", index + 1)
+                };
                 let atype = if token.is_cap() {
                     ArgType::Cap
+                } else if token.default().is_some() {
+                    ArgType::Default
                 } else {
                     ArgType::Var
                 };
@@ -97,11 +160,25 @@ pub struct Script {
     wrapped: String,
     spans: BTreeMap<usize, Span>,
     args: Args,
+    /// The line of the first line of the script in the Rust source.
+    line: Option<usize>,
 }
 
 impl Script {
     pub fn new(tokens: TokenStream, convert_args: bool) -> Self {
-        let tokens = retokenize(tokens);
+        Self::from_tokens(Tokens::from(tokens), convert_args)
+    }
+
+    pub fn from_tokens(tokens: Tokens, convert_args: bool) -> Self {
+        let tokens: Vec<_> = retokenize(tokens).into_iter().collect();
+
+        // Multiple values of top-level `return` are wrapped in a table, e.g. `return {a, b}`,
+        // because Redis only takes the first value.
+        let (opens, closes) = if convert_args {
+            wrap_multi_returns(&tokens)
+        } else {
+            Default::default()
+        };
 
         // Script string to be checked & emitted.
         let mut script = String::new();
@@ -109,18 +186,26 @@ impl Script {
         // Table to map lua code span to rust code span.
         let mut spans = BTreeMap::new();
 
-        // Script argument lists (i.e. `ARGV`).
+        // Script argument lists (i.e. `ARGV` and `KEYS`).
         let mut args = Args::new();
 
         let mut pos = Option::<Pos>::None;
 
-        for t in tokens {
+        let first_line = tokens.first().and_then(|t| span_line(&t.span()));
+
+        for (index, t) in tokens.into_iter().enumerate() {
             let (code, span) = if t.is_arg() && convert_args {
                 let arg = args.add(&t);
                 (arg.as_lua().into(), t.span())
             } else {
                 (t.to_string(), t.span())
             };
+            let code = match (opens.contains(&index), closes.contains(&index)) {
+                (true, true) => format!("{{{}}}", code),
+                (true, false) => format!("{{{}", code),
+                (false, true) => format!("{}}}", code),
+                (false, false) => code,
+            };
 
             let (line, col) = (t.start().line, t.start().column);
             let (prev_line, prev_col) = pos
@@ -129,10 +214,13 @@ impl Script {
                 .unwrap_or_else(|| (line, col));
 
             if line > prev_line {
-                script.push_str("\n");
+                // Keep the blank lines so that the lines of the script match the Rust source.
+                for _ in prev_line..line {
+                    script.push('\n');
+                }
             } else if line == prev_line {
                 for _ in 0..col.saturating_sub(prev_col) {
-                    script.push_str(" ");
+                    script.push(' ');
                 }
             }
             let begin = script.len();
@@ -140,7 +228,7 @@ impl Script {
             let end = script.len();
 
             for i in begin..=end {
-                spans.insert(i, span.clone());
+                spans.insert(i, span);
             }
 
             pos = Some(t.end());
@@ -150,7 +238,7 @@ impl Script {
 
         // `wrapped` contains `script` plus variable initialization logic at the top.
         // Only `script` part is checked by the linter. The linter is configured
-        // so that it allows only special local variables like `__internal_0` but doesn't
+        // so that it allows only special local variables like `__redis_lua_arg_0` but doesn't
         // allow `ARGV`. This is to prevent script authers from accidentally writing
         // `ARGV[x]` where `x` is larger than actual arguments given by a command.
         let wrapped = if convert_args {
@@ -158,13 +246,24 @@ impl Script {
                 // Generating these lines.
                 //
                 // ```
-                // local __internal_0 = ARGV[0];
-                // local __internal_1 = ARGV[1];
-                // local __internal_2 = ARGV[2];
+                // local __redis_lua_arg_0 = ARGV[1];
+                // local __redis_lua_arg_1 = ARGV[2];
+                // local __redis_lua_arg_2 = ARGV[3];
                 // ```
                 s + &format!("local {} = {}; ", arg.as_lua(), arg.as_argv())
             });
-            format!("{}\n{}", wrapper, script)
+            let keys: Vec<_> = args
+                .args()
+                .iter()
+                .filter(|arg| arg.is_key())
+                .map(|arg| arg.as_lua())
+                .collect();
+            format!(
+                "{}local KEYS = {{{}}};\n{}",
+                wrapper,
+                keys.join(", "),
+                script
+            )
         } else {
             "".into()
         };
@@ -174,6 +273,7 @@ impl Script {
             wrapped,
             spans,
             args,
+            line: first_line,
         }
     }
 
@@ -189,11 +289,43 @@ impl Script {
         self.args.args()
     }
 
+    /// The line of the first line of the script in the Rust source.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// The script and the wrapped script in which the script runs in `pcall`.
+    ///
+    /// The Lua errors are converted to error replies, e.g. the error of `redis.call` or `error("...")`.
+    pub fn protect(&self) -> (String, String) {
+        let script = format!(
+            "local __redis_lua_ok, __redis_lua_res = pcall(function()\n{}\nend)\n\
+             if not __redis_lua_ok then return redis.error_reply(type(__redis_lua_res) == \"table\" \
+             and __redis_lua_res.err or tostring(__redis_lua_res)) end\n\
+             return __redis_lua_res",
+            self.script
+        );
+        let init = &self.wrapped[..self.wrapped.len() - self.script.len()];
+        let wrapped = format!("{}{}", init, script);
+        (script, wrapped)
+    }
+
     /// Convert lua code span to rust code span.
     pub fn range_to_span(&self, range: (usize, usize)) -> Vec<Span> {
         self.spans
             .range(range.0..=range.1)
-            .map(|(_, v)| v.clone())
+            .map(|(_, v)| *v)
             .collect()
     }
 }
+
+/// The distinct indices of `ARGV[n]` read by the script, e.g. to initialize the arguments.
+pub fn argv_indices(script: &str) -> BTreeSet<usize> {
+    script
+        .match_indices("ARGV[")
+        .filter_map(|(begin, m)| {
+            let rest = &script[begin + m.len()..];
+            rest[..rest.find(']')?].parse().ok()
+        })
+        .collect()
+}
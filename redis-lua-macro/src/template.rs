@@ -0,0 +1,133 @@
+use crate::proc_macro::{Delimiter, Group, Ident, Spacing, TokenStream as TokenStream1, TokenTree};
+use proc_macro2::TokenStream;
+use std::str::FromStr;
+
+/// The placeholder of the value `index` left in the script for `redis_lua::fmt_script`.
+fn marker(index: usize) -> String {
+    format!("{{redis_lua_fmt_{}}}", index)
+}
+
+fn is_comma(t: &TokenTree) -> bool {
+    match t {
+        TokenTree::Punct(p) => p.as_char() == ',' && p.spacing() == Spacing::Alone,
+        _ => false,
+    }
+}
+
+/// Split the placeholders from the script of `lua_fmt!`, e.g. `prefix = "app", { ... }`.
+///
+/// Returns the names and the expressions of the values with the script.
+pub fn split_params(input: TokenStream1) -> (Vec<(Ident, TokenStream)>, TokenStream1) {
+    let tokens: Vec<_> = input.into_iter().collect();
+    let mut params = Vec::new();
+    let mut rest = tokens.as_slice();
+
+    loop {
+        match rest {
+            [TokenTree::Group(script)] if script.delimiter() == Delimiter::Brace => {
+                return (params, script.stream());
+            }
+            [TokenTree::Ident(name), TokenTree::Punct(eq), tail @ ..] if eq.as_char() == '=' => {
+                // The value is the tokens until the next comma.
+                let end = match tail.iter().position(is_comma) {
+                    Some(end) => end,
+                    None => proc_macro_error::abort!(
+                        name.span(),
+                        "expected `,` after the value of `{}`",
+                        name
+                    ),
+                };
+                if end == 0 {
+                    proc_macro_error::abort!(eq.span(), "expected a value of `{}`", name);
+                }
+                let value: TokenStream1 = tail[..end].iter().cloned().collect();
+                params.push((name.clone(), value.into()));
+                rest = &tail[end + 1..];
+            }
+            [t, ..] => proc_macro_error::abort!(
+                t.span(),
+                "expected `name = value,` or the script in braces"
+            ),
+            [] => proc_macro_error::abort_call_site!("expected the script in braces"),
+        }
+    }
+}
+
+/// Replace the placeholders, e.g. `{prefix}`, in the string literals of the script with the markers
+/// substituted at runtime. `{{` and `}}` are literal braces.
+pub fn replace_placeholders(script: TokenStream1, names: &[Ident]) -> TokenStream1 {
+    let mut used = vec![false; names.len()];
+    let script = replace_stream(script, names, &mut used);
+
+    for (name, used) in names.iter().zip(used) {
+        if !used {
+            proc_macro_error::abort!(name.span(), "`{{{}}}` is never used in the script", name);
+        }
+    }
+
+    script
+}
+
+fn replace_stream(stream: TokenStream1, names: &[Ident], used: &mut [bool]) -> TokenStream1 {
+    stream
+        .into_iter()
+        .map(|t| match t {
+            TokenTree::Group(g) => {
+                let mut group = Group::new(g.delimiter(), replace_stream(g.stream(), names, used));
+                group.set_span(g.span());
+                TokenTree::Group(group)
+            }
+            TokenTree::Literal(lit) => {
+                let text = lit.to_string();
+                if !text.starts_with('"') {
+                    return TokenTree::Literal(lit);
+                }
+                let text = replace_literal(&text, names, used).unwrap_or_else(|name| {
+                    proc_macro_error::abort!(lit.span(), "unknown placeholder `{{{}}}`", name)
+                });
+                let mut new = match TokenStream1::from_str(&text).map(|s| s.into_iter().next()) {
+                    Ok(Some(TokenTree::Literal(new))) => new,
+                    _ => return TokenTree::Literal(lit),
+                };
+                new.set_span(lit.span());
+                TokenTree::Literal(new)
+            }
+            t => t,
+        })
+        .collect()
+}
+
+/// Replace the placeholders in the text of a string literal, or return the unknown name.
+fn replace_literal(text: &str, names: &[Ident], used: &mut [bool]) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push(c);
+            rest = &rest[2..];
+            continue;
+        }
+        if c == '{' {
+            let len = rest[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - 1);
+            let name = &rest[1..1 + len];
+            if len > 0 && rest[1 + len..].starts_with('}') {
+                match names.iter().position(|n| n.to_string() == name) {
+                    Some(index) => {
+                        used[index] = true;
+                        out.push_str(&marker(index));
+                        rest = &rest[2 + len..];
+                        continue;
+                    }
+                    None => return Err(name.to_string()),
+                }
+            }
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Ok(out)
+}
@@ -1,9 +1,9 @@
 use itertools::Itertools;
-use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
+use proc_macro::{Delimiter, Group, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
 use proc_macro2::Span as Span2;
 use std::{
     fmt::{self, Display, Formatter},
-    iter::IntoIterator,
+    iter::{IntoIterator, Peekable},
     vec::IntoIter,
 };
 
@@ -34,7 +34,7 @@ impl Pos {
 }
 
 fn span_pos(span: &Span) -> (Pos, Pos) {
-    let span2: Span2 = span.clone().into();
+    let span2: Span2 = (*span).into();
     let start = span2.start();
     let end = span2.end();
 
@@ -50,6 +50,15 @@ fn span_pos(span: &Span) -> (Pos, Pos) {
     )
 }
 
+/// The line of the span in the Rust source if the compiler provides it.
+pub fn span_line(span: &Span) -> Option<usize> {
+    let span2: Span2 = (*span).into();
+    match span2.start().line {
+        0 => None,
+        line => Some(line),
+    }
+}
+
 fn parse_pos(span: &Span) -> Option<(usize, usize)> {
     // Workaround to somehow retrieve location information in span in stable rust :(
 
@@ -100,11 +109,17 @@ pub struct Token {
     start: Pos,
     end: Pos,
     attr: TokenAttr,
+    /// Prefixed by `#`, i.e. passed as `KEYS`
+    key: bool,
+    /// Type annotation of `$`, e.g. `i64` of `$x: i64`
+    ty: Option<TokenTree>,
+    /// Default value of `$`, e.g. `10` of `$x = 10`
+    default: Option<Vec<TokenTree>>,
 }
 
 impl std::cmp::PartialEq for Token {
     fn eq(&self, other: &Self) -> bool {
-        self.source == other.source && self.attr == other.attr
+        self.source == other.source && self.attr == other.attr && self.key == other.key
     }
 }
 
@@ -119,6 +134,9 @@ impl Token {
             end,
             tree,
             attr: TokenAttr::None,
+            key: false,
+            ty: None,
+            default: None,
         }
     }
 
@@ -136,6 +154,9 @@ impl Token {
             start,
             end,
             attr: TokenAttr::None,
+            key: false,
+            ty: None,
+            default: None,
         }
     }
 
@@ -155,6 +176,29 @@ impl Token {
         self.attr == TokenAttr::Cap
     }
 
+    pub fn is_key(&self) -> bool {
+        self.key
+    }
+
+    pub fn ty(&self) -> Option<&TokenTree> {
+        self.ty.as_ref()
+    }
+
+    pub fn default(&self) -> Option<&[TokenTree]> {
+        self.default.as_deref()
+    }
+
+    /// Take the type annotation and the default value from the other occurrence of the same argument.
+    pub fn merge(mut self, other: &Token) -> Self {
+        if self.ty.is_none() {
+            self.ty = other.ty.clone();
+        }
+        if self.default.is_none() {
+            self.default = other.default.clone();
+        }
+        self
+    }
+
     pub fn span(&self) -> Span {
         self.tree.span()
     }
@@ -167,7 +211,7 @@ impl Token {
         self.end
     }
 
-    fn is(&self, s: &str) -> bool {
+    pub fn is(&self, s: &str) -> bool {
         self.source == s
     }
 
@@ -175,28 +219,67 @@ impl Token {
         self.attr = attr;
         self
     }
+
+    fn key(mut self) -> Self {
+        self.key = true;
+        self
+    }
+
+    /// Extend the token to the end of the other token.
+    fn extend(mut self, other: &Token) -> Self {
+        self.end = other.end;
+        self
+    }
 }
 
 #[derive(Debug)]
 pub struct Tokens(Vec<Token>);
 
-pub fn retokenize(tt: TokenStream) -> Tokens {
+pub fn retokenize(tokens: Tokens) -> Tokens {
     Tokens(
-        tt.into_iter()
-            .map(|tt| Tokens::from(tt))
-            .flatten()
+        tokens
+            .into_iter()
             .peekable()
             .batching(|iter| {
                 // Find variable/capture tokens
                 let t = iter.next()?;
+                if t.is("#") {
+                    if let Some(key) = key_prefix(iter) {
+                        return Some(key);
+                    }
+                }
                 if t.is("@") {
+                    if let Some(prev) = prev_value(&t, iter) {
+                        return Some(prev);
+                    }
+                }
+                if t.is("#") && iter.peek().is_some_and(|n| n.is("@") || n.is("$")) {
+                    // `#` + `@ident` => `#@ident`, `#` + `$ident` => `#$ident`
+                    let t = iter.next().unwrap();
+                    let attr = if t.is("@") {
+                        TokenAttr::Cap
+                    } else {
+                        TokenAttr::Var
+                    };
+                    let t = sigil_operand(iter.next(), &t);
+                    let t = if attr == TokenAttr::Cap {
+                        field_path(t, iter)
+                    } else {
+                        default_value(type_annotation(t, iter), iter)
+                    };
+                    Some(t.attr(attr).key())
+                } else if (t.is("@") || t.is("$")) && iter.peek().is_some_and(|n| n.is(&t.source)) {
+                    // `@@` => `@`, `$$` => `$`, i.e. escaped literal
+                    let n = iter.next().unwrap();
+                    Some(t.extend(&n))
+                } else if t.is("@") {
                     // `@` + `ident` => `@ident`
-                    let t = iter.next().expect("@ must trail an identifier");
-                    Some(t.attr(TokenAttr::Cap))
+                    let t = sigil_operand(iter.next(), &t);
+                    Some(field_path(t, iter).attr(TokenAttr::Cap))
                 } else if t.is("$") {
                     // `$` + `ident` => `@ident`
-                    let t = iter.next().expect("$ must trail an identifier");
-                    Some(t.attr(TokenAttr::Var))
+                    let t = sigil_operand(iter.next(), &t);
+                    Some(default_value(type_annotation(t, iter), iter).attr(TokenAttr::Var))
                 } else {
                     Some(t)
                 }
@@ -205,6 +288,343 @@ pub fn retokenize(tt: TokenStream) -> Tokens {
     )
 }
 
+/// The token trailing `@` or `$`.
+fn sigil_operand(operand: Option<Token>, sigil: &Token) -> Token {
+    match operand {
+        Some(t) if !t.is("#") && !t.is("@") && !t.is("$") => t,
+        _ => proc_macro_error::abort!(
+            Span2::from(sigil.span()),
+            "`{}` must trail an identifier", sigil;
+            help = "use `{}{}` for a literal `{}`", sigil, sigil, sigil
+        ),
+    }
+}
+
+/// The Lua local of `@@prev`, same as the one defined by `Script::and_then`.
+pub const PREV: &str = "__redis_lua_prev";
+
+/// `@` + `@` + `prev` => `__redis_lua_prev`, i.e. the return value of the previous script of `and_then`.
+fn prev_value(at: &Token, iter: &mut Peekable<IntoIter<Token>>) -> Option<Token> {
+    let mut ahead = iter.clone();
+    if !ahead.next()?.is("@") {
+        return None;
+    }
+    let prev = ahead.next()?;
+    if !prev.is("prev") {
+        return None;
+    }
+    *iter = ahead;
+
+    Some(Token {
+        source: PREV.into(),
+        ..at.clone().extend(&prev)
+    })
+}
+
+/// `"prefix"` + `@ident`, or `prefix` + `:` + ... + `@ident` following `#`
+/// => `(redis_lua::key_concat("prefix", &ident))`, i.e. the key is concatenated on the client side.
+fn key_prefix(iter: &mut Peekable<IntoIter<Token>>) -> Option<Token> {
+    let mut ahead = iter.clone();
+    let first = ahead.next()?;
+
+    let prefix = match &first.tree {
+        TokenTree::Literal(lit) if lit.to_string().starts_with('"') => {
+            if !ahead.next().is_some_and(|t| t.is("@")) {
+                return None;
+            }
+            lit.clone()
+        }
+        TokenTree::Ident(_) => {
+            // `app:user:@id` is tokenized as `app`, `:`, `user`, `:`, `@` and `id`.
+            let mut prefix = format!("{}:", first.source);
+            if !ahead.next().is_some_and(|t| t.is(":")) {
+                return None;
+            }
+            loop {
+                match ahead.next() {
+                    Some(t) if t.is("@") => break,
+                    Some(t) if matches!(t.tree, TokenTree::Ident(_)) => {
+                        if !ahead.next().is_some_and(|t| t.is(":")) {
+                            return None;
+                        }
+                        prefix += &format!("{}:", t.source);
+                    }
+                    _ => return None,
+                }
+            }
+            let mut lit = Literal::string(&prefix);
+            lit.set_span(first.span());
+            lit
+        }
+        _ => return None,
+    };
+
+    let at = Token::new(TokenTree::Punct(Punct::new('@', Spacing::Alone)));
+    let value = field_path(sigil_operand(ahead.next(), &at), &mut ahead);
+    *iter = ahead;
+
+    let mut args: TokenStream = vec![
+        TokenTree::Literal(prefix),
+        TokenTree::Punct(Punct::new(',', Spacing::Alone)),
+        TokenTree::Punct(Punct::new('&', Spacing::Alone)),
+        value.tree.clone(),
+    ]
+    .into_iter()
+    .collect();
+    args = respan(args, value.span());
+
+    let mut call: TokenStream = "redis_lua::key_concat".parse().unwrap();
+    call.extend(vec![TokenTree::Group(Group::new(
+        Delimiter::Parenthesis,
+        args,
+    ))]);
+    let mut group = Group::new(Delimiter::Parenthesis, call);
+    group.set_span(first.span());
+
+    Some(
+        Token {
+            source: group.to_string(),
+            tree: TokenTree::Group(group),
+            start: first.start,
+            end: value.end,
+            attr: TokenAttr::Cap,
+            key: true,
+            ty: None,
+            default: None,
+        }
+        .key(),
+    )
+}
+
+/// `ident` + `.field` + ... => `ident.field...`, i.e. the field path is captured as a single token.
+fn field_path(t: Token, iter: &mut Peekable<IntoIter<Token>>) -> Token {
+    let mut path = vec![t.tree.clone()];
+    let mut end = t.end;
+
+    while iter.peek().is_some_and(is_field_dot) {
+        let mut ahead = iter.clone();
+        let dot = ahead.next().unwrap();
+        match ahead.next() {
+            Some(field) if matches!(field.tree, TokenTree::Ident(_)) => {
+                iter.next();
+                iter.next();
+                path.push(dot.tree);
+                path.push(field.tree);
+                end = field.end;
+            }
+            _ => break,
+        }
+    }
+
+    if path.len() == 1 {
+        return t;
+    }
+
+    let mut group = Group::new(Delimiter::None, path.into_iter().collect());
+    group.set_span(t.span());
+
+    Token {
+        source: group.stream().to_string(),
+        tree: TokenTree::Group(group),
+        start: t.start,
+        end,
+        attr: t.attr,
+        key: t.key,
+        ty: t.ty,
+        default: t.default,
+    }
+}
+
+/// `ident` + `:` + `type` => `ident: type`, i.e. the type annotation of `$`.
+///
+/// `:` followed by a call, e.g. `$x:upper()`, is a Lua method call.
+fn type_annotation(t: Token, iter: &mut Peekable<IntoIter<Token>>) -> Token {
+    let is_colon = |t: &Token| matches!(&t.tree, TokenTree::Punct(p) if p.as_char() == ':' && p.spacing() == Spacing::Alone);
+    if !iter.peek().is_some_and(is_colon) {
+        return t;
+    }
+
+    let mut ahead = iter.clone();
+    ahead.next();
+    let ty = match ahead.next() {
+        Some(ty) if matches!(ty.tree, TokenTree::Ident(_)) => ty,
+        _ => return t,
+    };
+    let is_call = ahead
+        .next()
+        .is_some_and(|n| n.is("(") || n.is("{") || matches!(n.tree, TokenTree::Literal(_)));
+    if is_call {
+        return t;
+    }
+
+    iter.next();
+    iter.next();
+    let mut t = t.extend(&ty);
+    t.ty = Some(ty.tree);
+    t
+}
+
+/// `ident` + `=` + `literal` => `ident = literal`, i.e. the default value of `$`.
+///
+/// `=` followed by other than a literal, e.g. `$x = y`, is a Lua assignment.
+fn default_value(t: Token, iter: &mut Peekable<IntoIter<Token>>) -> Token {
+    let is_assign = |t: &Token| matches!(&t.tree, TokenTree::Punct(p) if p.as_char() == '=' && p.spacing() == Spacing::Alone);
+    if !iter.peek().is_some_and(is_assign) {
+        return t;
+    }
+
+    let mut ahead = iter.clone();
+    ahead.next();
+    let value = match (ahead.next(), ahead.next()) {
+        (Some(v), _)
+            if matches!(v.tree, TokenTree::Literal(_)) || v.is("true") || v.is("false") =>
+        {
+            vec![v]
+        }
+        (Some(minus), Some(v)) if minus.is("-") && matches!(v.tree, TokenTree::Literal(_)) => {
+            vec![minus, v]
+        }
+        _ => return t,
+    };
+
+    iter.next();
+    for _ in &value {
+        iter.next();
+    }
+    let mut t = t.extend(value.last().unwrap());
+    t.default = Some(value.into_iter().map(|v| v.tree).collect());
+    t
+}
+
+/// `.` which is not a part of `..` or `...`.
+fn is_field_dot(t: &Token) -> bool {
+    matches!(&t.tree, TokenTree::Punct(p) if p.as_char() == '.' && p.spacing() == Spacing::Alone)
+}
+
+impl Tokens {
+    /// Tokenize a script which doesn't come from the macro input, e.g. an included file.
+    ///
+    /// Such tokens have no location information, so it is recovered from the source.
+    ///
+    /// The tokens are given `span` so that captured variables resolve where the script is used.
+    pub fn from_source(source: &str, span: Span) -> Self {
+        let source = blank_comments(source);
+        let ts: TokenStream = match source.parse() {
+            Ok(ts) => ts,
+            Err(e) => proc_macro_error::abort_call_site!("cannot tokenize lua script: {}", e),
+        };
+
+        let mut tokens = Tokens::from(respan(ts, span));
+        let mut offset = 0;
+
+        for t in &mut tokens.0 {
+            let (begin, end) = match locate(&source[offset..], t) {
+                Some((begin, end)) => (offset + begin, offset + end),
+                None => proc_macro_error::abort_call_site!("cannot locate `{}` in lua script", t),
+            };
+            t.start = offset_pos(&source, begin);
+            t.end = offset_pos(&source, end);
+            offset = end;
+        }
+
+        tokens
+    }
+}
+
+fn respan(ts: TokenStream, span: Span) -> TokenStream {
+    ts.into_iter()
+        .map(|mut tt| {
+            if let TokenTree::Group(g) = &tt {
+                let mut group = Group::new(g.delimiter(), respan(g.stream(), span));
+                group.set_span(span);
+                tt = TokenTree::Group(group);
+            }
+            tt.set_span(span);
+            tt
+        })
+        .collect()
+}
+
+/// Find the byte range of the token in the source.
+fn locate(source: &str, t: &Token) -> Option<(usize, usize)> {
+    match t.tree() {
+        // `@(expr)` is a single token; its text is normalized so find the balanced parentheses.
+        TokenTree::Group(_) if t.source.len() > 1 => {
+            let begin = source.find('(')?;
+            let mut depth = 0;
+            for (i, c) in source[begin..].char_indices() {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    return Some((begin, begin + i + 1));
+                }
+            }
+            None
+        }
+        _ => {
+            let begin = source.find(t.source.as_str())?;
+            Some((begin, begin + t.source.len()))
+        }
+    }
+}
+
+/// The line (1-indexed) and the column (0-indexed) of the byte offset.
+fn offset_pos(source: &str, offset: usize) -> Pos {
+    let head = &source[..offset];
+    let line = head.matches('\n').count() + 1;
+    let column = head[head.rfind('\n').map(|i| i + 1).unwrap_or(0)..]
+        .chars()
+        .count();
+    Pos::new(line, column)
+}
+
+/// Replace Lua comments with spaces keeping the byte offsets of the other tokens.
+fn blank_comments(source: &str) -> String {
+    let mut blanked = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                blanked.push(c);
+                if c == '\\' {
+                    blanked.extend(chars.next());
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None if c == '"' || c == '\'' => {
+                blanked.push(c);
+                quote = Some(c);
+            }
+            None if c == '-' && chars.peek() == Some(&'-') => {
+                let mut comment: String = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' && !is_long_comment(&comment) {
+                        break;
+                    }
+                    comment.push(chars.next().unwrap());
+                    if is_long_comment(&comment) && comment.ends_with("]]") {
+                        break;
+                    }
+                }
+                blanked.extend(comment.chars().map(|c| if c == '\n' { c } else { ' ' }));
+            }
+            None => blanked.push(c),
+        }
+    }
+
+    blanked
+}
+
+fn is_long_comment(comment: &str) -> bool {
+    comment.starts_with("--[[")
+}
+
 fn delimiter(d: Delimiter) -> (String, String) {
     let (b, e) = match d {
         Delimiter::Parenthesis => ("(", ")"),
@@ -224,6 +644,30 @@ impl IntoIterator for Tokens {
     }
 }
 
+impl From<TokenStream> for Tokens {
+    fn from(ts: TokenStream) -> Self {
+        let mut tts = ts.into_iter().peekable();
+        let mut tokens = Vec::new();
+
+        while let Some(tt) = tts.next() {
+            let is_cap = matches!(&tt, TokenTree::Punct(p) if p.as_char() == '@');
+
+            tokens.extend(Tokens::from(tt));
+
+            // `@` + `(expr)` => `@(expr)`, i.e. the whole expression is captured as a single token.
+            if is_cap {
+                if let Some(TokenTree::Group(g)) = tts.peek() {
+                    if g.delimiter() == Delimiter::Parenthesis {
+                        tokens.push(Token::new(tts.next().unwrap()));
+                    }
+                }
+            }
+        }
+
+        Tokens(tokens)
+    }
+}
+
 impl From<TokenTree> for Tokens {
     fn from(tt: TokenTree) -> Self {
         let tts = match tt.clone() {
@@ -232,7 +676,7 @@ impl From<TokenTree> for Tokens {
 
                 vec![Token::new_delim(b, tt.clone(), true)]
                     .into_iter()
-                    .chain(g.stream().into_iter().map(|tt| Tokens::from(tt)).flatten())
+                    .chain(Tokens::from(g.stream()))
                     .chain(vec![Token::new_delim(e, tt.clone(), false)])
                     .collect()
             }
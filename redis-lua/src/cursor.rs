@@ -0,0 +1,69 @@
+use crate::script::Script;
+use redis::{ConnectionLike, FromRedisValue, RedisResult, Value};
+use std::marker::PhantomData;
+
+/// Iterates the batches of the script which returns `{cursor, batch}`, same as `SCAN`.
+///
+/// The script is created for each cursor by the given function, starting with `"0"`, and invoked with
+/// the cursor returned by the previous invocation until it returns `"0"`. Each item is a batch,
+/// so the large result is never decoded at once.
+pub struct ScriptCursor<'a, F, T> {
+    con: &'a mut dyn ConnectionLike,
+    script: F,
+    cursor: Option<String>,
+    _batch: PhantomData<fn() -> T>,
+}
+
+impl<'a, F, S, T> ScriptCursor<'a, F, T>
+where
+    F: FnMut(String) -> S,
+    S: Script,
+    T: FromRedisValue,
+{
+    /// Create the cursor which invokes the script given by `script` for each cursor.
+    pub fn new(con: &'a mut dyn ConnectionLike, script: F) -> Self {
+        Self {
+            con,
+            script,
+            cursor: Some("0".into()),
+            _batch: PhantomData,
+        }
+    }
+}
+
+impl<F, S, T> Iterator for ScriptCursor<'_, F, T>
+where
+    F: FnMut(String) -> S,
+    S: Script,
+    T: FromRedisValue,
+{
+    type Item = RedisResult<Vec<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.cursor.take()?;
+        let value: Value = match (self.script)(cursor).invoke(&mut *self.con) {
+            Ok(value) => value,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let (cursor, batch) = match value {
+            Value::Bulk(items) if items.len() == 2 => match String::from_redis_value(&items[0]) {
+                Ok(cursor) => (cursor, Vec::<T>::from_redis_value(&items[1])),
+                Err(e) => return Some(Err(e)),
+            },
+            _ => {
+                return Some(Err((
+                    redis::ErrorKind::TypeError,
+                    "expected {cursor, batch} from the script",
+                )
+                    .into()))
+            }
+        };
+
+        // The iteration ends when the script returns the cursor `"0"`, or fails.
+        if cursor != "0" && batch.is_ok() {
+            self.cursor = Some(cursor);
+        }
+        Some(batch)
+    }
+}
@@ -0,0 +1,89 @@
+use redis::{ErrorKind, RedisError};
+use std::{convert::Infallible, fmt, str::FromStr};
+
+/// The placeholder detail of the error reply which has no detail.
+const NO_DETAIL: &str = "Unknown extension error encountered";
+
+/// Error of the script invocation with the typed error raised by the script.
+///
+/// `E` defaults to `Infallible` for the invocations which don't parse the error reply.
+#[derive(Debug)]
+pub enum ScriptError<E = Infallible> {
+    /// The error reply of the script, e.g. `redis.error_reply("...")`, parsed as the user error.
+    Script(E),
+    /// The error reply of the script which cannot be parsed as the user error.
+    Unmatched(String),
+    /// Other errors, e.g. connection errors.
+    Redis(RedisError),
+    /// The script didn't finish in time.
+    Timeout,
+    /// The return value of the script cannot be decoded, e.g. invalid JSON.
+    Decode(String),
+    /// The return value of the script is rejected by `TryFrom`, with the reason.
+    Convert(redis::Value, String),
+    /// The argument cannot be encoded, e.g. a map with non-string keys in JSON.
+    Encode(String),
+}
+
+/// The error reply of the script, e.g. `redis.error_reply("...")` or a Lua runtime error,
+/// without the Redis error prefix `ERR`.
+fn error_reply(e: &RedisError) -> Option<String> {
+    match (e.kind(), e.code(), e.detail()) {
+        (ErrorKind::ResponseError, _, Some(detail)) => Some(detail.to_string()),
+        (ErrorKind::ResponseError, Some(code), None) => Some(code.to_string()),
+        (ErrorKind::ExtensionError, Some(code), Some(NO_DETAIL)) => Some(code.to_string()),
+        (ErrorKind::ExtensionError, Some(code), Some(detail)) => {
+            Some(format!("{} {}", code, detail))
+        }
+        _ => None,
+    }
+}
+
+impl<E: FromStr> ScriptError<E> {
+    /// Convert the error of the script invocation.
+    ///
+    /// The error reply is parsed without the Redis error prefix `ERR`.
+    pub fn from_redis(e: RedisError) -> Self {
+        let raw = match error_reply(&e) {
+            Some(raw) => raw,
+            None => return Self::Redis(e),
+        };
+
+        match raw.parse() {
+            Ok(e) => Self::Script(e),
+            Err(_) => Self::Unmatched(raw),
+        }
+    }
+}
+
+impl ScriptError {
+    /// Convert the error of the script invocation keeping the error reply of the script as `Unmatched`.
+    pub fn from_reply(e: RedisError) -> Self {
+        match error_reply(&e) {
+            Some(raw) => Self::Unmatched(raw),
+            None => Self::Redis(e),
+        }
+    }
+}
+
+impl<E> From<RedisError> for ScriptError<E> {
+    fn from(e: RedisError) -> Self {
+        Self::Redis(e)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ScriptError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Script(e) => write!(f, "script error: {}", e),
+            Self::Unmatched(e) => write!(f, "script error: {}", e),
+            Self::Redis(e) => write!(f, "{}", e),
+            Self::Timeout => write!(f, "script timed out"),
+            Self::Decode(e) => write!(f, "cannot decode script result: {}", e),
+            Self::Convert(v, e) => write!(f, "cannot convert script result {:?}: {}", v, e),
+            Self::Encode(e) => write!(f, "cannot encode script argument: {}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ScriptError<E> {}
@@ -0,0 +1,169 @@
+use crate::error::ScriptError;
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    ser, Serialize,
+};
+use serde_json::Value;
+
+type Result<T> = std::result::Result<T, serde_json::Error>;
+
+/// The value serialized as a JSON string.
+///
+/// The value is passed to the script as a single string argument, which can be decoded by `cjson.decode`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonArg(String);
+
+impl JsonArg {
+    /// The JSON string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Serialize for JsonArg {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Serialize the value to a JSON string to pass it to the script as a single argument.
+///
+/// Fails with `ScriptError::Encode` if the value cannot be represented in JSON, e.g. a map with non-string keys.
+pub fn json_arg<T: Serialize + ?Sized>(value: &T) -> std::result::Result<JsonArg, ScriptError> {
+    serde_json::to_string(value)
+        .map(JsonArg)
+        .map_err(|e| ScriptError::Encode(e.to_string()))
+}
+
+/// Deserialize the value from a JSON string, e.g. the one returned by `cjson.encode`.
+pub(crate) fn from_slice<T: DeserializeOwned>(v: &[u8]) -> Result<T> {
+    let value: Value = serde_json::from_slice(v)?;
+    T::deserialize(Cjson(value))
+}
+
+/// JSON value which is decoded as `cjson` encodes it: an empty table is encoded as an empty object,
+/// so an empty object is decoded as an empty sequence as well.
+struct Cjson(Value);
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for Cjson {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Cjson {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Value::Array(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter().map(Cjson));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Object(v) => {
+                let mut map = MapDeserializer::new(v.into_iter().map(|(k, v)| (Key(k), Cjson(v))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Value::Object(v) if v.is_empty() => {
+                Cjson(Value::Array(vec![])).deserialize_any(visitor)
+            }
+            v => Cjson(v).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Key of objects, which can be deserialized as a number as well, e.g. `HashMap<u32, _>`.
+struct Key(String);
+
+macro_rules! deserialize_number_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self.0.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => visitor.visit_string(self.0),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for Key {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Key {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.0)
+    }
+
+    deserialize_number_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
@@ -98,6 +98,78 @@
 //!    error: aborting due to previous error
 //! ```
 //!
+//! Runtime errors of Redis refer to the lines of the generated script, e.g. `user_script:3`. The errors of
+//! [`Script::invoke`][] and [`Script::invoke_async`][] have the file and the line of the `lua!` body appended,
+//! e.g. `user_script:3 (src/main.rs:42)`. [`Script::locate`][] maps a line of the generated script in the same way.
+//! Source maps are unavailable with the `minify` feature: minifying joins the lines, so `locate` returns `None`
+//! and nothing is appended to the errors.
+//!
+//! With the `log` feature, the invocations are logged to the `redis_lua` target with the names of the scripts,
+//! the SHA1 digest, the numbers of the keys and the arguments, and the elapsed time. Failures are logged as warnings.
+//! The records go through the [`log`](https://docs.rs/log) facade rather than `tracing`, so that the crate doesn't
+//! pull in a subscriber stack; `tracing` subscribers can collect them with `tracing-log`.
+//!
+//! Arguments which are never used by the script, e.g. `@x` only in a comment, are reported as warnings.
+//!
+//! Syntax errors such as a missing `end` or characters which are not Lua are also reported at compile time.
+//!
+//! ```rust,compile_fail
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! # let script =
+//! lua!(
+//!   if 3 > 1 then
+//!     return 3
+//! );
+//! # }
+//! ```
+//!
+//! ```rust,compile_fail
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! # let script =
+//! lua!(
+//!   return 3 ! 1
+//! );
+//! # }
+//! ```
+//!
+//! The calls of the common commands by `redis.call` and `redis.pcall` with too few or too many arguments,
+//! e.g. `GET`, `SET` and `HSET`, are reported too. The calls whose last argument is a function call or `...`,
+//! which can expand to multiple values, are checked only for too many arguments.
+//!
+//! ```rust,compile_fail
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! # let script =
+//! lua!(
+//!   return redis.call("set", #$key);
+//! );
+//! # }
+//! ```
+//!
+//! With the `deterministic` feature, the calls whose results differ by the call, e.g. `redis.call("time")` or
+//! `os.clock()`, are reported too. `nondeterministic` in front of the script, e.g.
+//! `lua!(nondeterministic, return redis.call("time"))`, allows them. With the `static-commands` feature,
+//! the command names passed by `@` or `$`, e.g. `redis.call(@cmd, #$key)`, are reported, unless
+//! `dynamic_commands` is in front of the script.
+//!
+//! `@` or `$` which doesn't trail an identifier is reported too. Use `@@` and `$$` for a literal `@` and `$`.
+//!
+//! ```rust,compile_fail
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! # let script =
+//! lua!(
+//!   return 1 + @
+//! );
+//! # }
+//! ```
+//!
 //! # Capturing a variable
 //!
 //! `@` with an identifier allows to capture a Rust variable in the script. It allows to capture any types which implement [`serde::Serialize`][].
@@ -116,6 +188,87 @@
 //! # }
 //! ```
 //!
+//! `@` with a field path, e.g. `@order.total` or `@self.count`, captures the field. To index a captured
+//! table in Lua instead, wrap it in parentheses, e.g. `(@table).field`.
+//!
+//! `@` with a parenthesized expression captures the result of the expression. The expression is evaluated once
+//! when the script object is created.
+//!
+//! ```rust
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! # let mut cli = redis::Client::open("redis://localhost").unwrap();
+//! #
+//! let v = vec![1, 2, 3];
+//!
+//! let script = lua!(return @(v.len()) + 2);
+//! let num: usize = script.invoke(&mut cli).unwrap();
+//! assert_eq!(num, 5);
+//! # }
+//! ```
+//!
+//! The captured values which don't implement [`serde::Serialize`][], e.g. a function captured by mistake, are
+//! reported where they are captured. Call the function instead, e.g. `@(some_fn())`.
+//!
+//! ```rust,compile_fail
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! fn limit() -> usize {
+//!     10
+//! }
+//!
+//! let script = lua!(return @limit + 2);
+//! # }
+//! ```
+//!
+//! `#[derive(ScriptArg)]` serializes a fieldless enum as its discriminant, so `@color` passes e.g. `2`
+//! instead of `@(color as i64)`. The enums with fields or generics are rejected.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script, ScriptArg};
+//! #
+//! # fn main() {
+//! #[derive(ScriptArg)]
+//! enum Color {
+//!     Red,
+//!     Green,
+//!     Blue = 10,
+//! }
+//!
+//! let color = Color::Blue;
+//! let script = lua!(return @color + 1);
+//! assert_eq!(script.explain().args(), &[b"10".to_vec()]);
+//! # }
+//! ```
+//!
+//! ```rust,compile_fail
+//! # use redis_lua::ScriptArg;
+//! #[derive(ScriptArg)]
+//! enum Shape {
+//!     Circle(f64),
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! Variables are captured by move. To keep using a large value after creating the script, capture a reference
+//! with `@(&x)`; the script object borrows the value instead of cloning it.
+//!
+//! ```rust
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! # let mut cli = redis::Client::open("redis://localhost").unwrap();
+//! #
+//! let v = vec![1, 2, 3];
+//!
+//! let script = lua!(return #(@(&v)));
+//! let num: usize = script.invoke(&mut cli).unwrap();
+//! assert_eq!(num, v.len());
+//! # }
+//! ```
+//!
 //! # Argument substitution
 //!
 //! `$` with an identifier allows to substitute a variable before actually running the script. Same as `@`, any types which implement [`serde::Serialize`][] can be substituted.
@@ -149,8 +302,245 @@
 //! # }
 //! ```
 //!
+//! `bind_named` substitutes all the variables at once by name. It fails if any of the variables is missing.
+//!
+//! ```rust
+//! # use redis_lua::lua;
+//! # use std::collections::HashMap;
+//! #
+//! # fn main() {
+//! # let mut cli = redis::Client::open("redis://localhost").unwrap();
+//! #
+//! let script = lua!(return $x + $y);
+//! let args: HashMap<&str, usize> = vec![("x", 1), ("y", 2)].into_iter().collect();
+//! let num: usize = script.bind_named(&args).unwrap().invoke(&mut cli).unwrap();
+//! assert_eq!(num, 3);
+//! # }
+//! ```
+//!
+//! `$x: T` annotates the type of the variable with a type name, e.g. `i64` or `String`.
+//! The method to substitute the variable then only takes `T`, so a wrong type fails with a clear error.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let script = lua!(return $x: i64 + 2);
+//! assert_eq!(script.x(-1).explain().args(), &[b"-1".to_vec()]);
+//! # }
+//! ```
+//!
+//! `$x = value` gives the variable a default value, which is a literal, e.g. `10` or `"queue"`. The method to
+//! substitute the variable may then be omitted, and it can be called at any point of the chain.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let script = lua!(return $x + ($step = 1));
+//! assert_eq!(script.clone().x(10).explain().args(), &[b"10".to_vec(), b"1".to_vec()]);
+//! assert_eq!(script.step(5).x(10).explain().args(), &[b"10".to_vec(), b"5".to_vec()]);
+//! # }
+//! ```
+//!
 //! The script object is clonable if all the variables it captures are clonable or it captures no variables.
 //!
+//! # Naming a script
+//!
+//! `name = "..."` in front of the script names it. The name is put in the generated script as a comment,
+//! and [`Script::names`][] returns the names of the joined scripts, e.g. for logging.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let script = lua!(name = "add", {
+//!   return $x + 2
+//! });
+//! assert_eq!(script.x(1).names(), vec!["add"]);
+//! # }
+//! ```
+//!
+//! The script implements `Display` if the bound arguments do, which shows the call rather than the source,
+//! e.g. `add(x=1)`. Unnamed scripts are shown as `script(...)`. Only the last script of joined scripts is shown.
+//!
+//! # Protected scripts
+//!
+//! `protected` in front of the script runs it in `pcall`, so a Lua error raised by the script, e.g. `error("...")`
+//! or a failing `redis.call`, is returned as an error reply. Options can be combined, e.g. `name = "...", protected`.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let script = lua!(protected, {
+//!   error("INSUFFICIENT_BALANCE", 0)
+//! });
+//! assert!(script.source().contains("pcall"));
+//! # }
+//! ```
+//!
+//! # Conditional scripts
+//!
+//! `if cfg!(...) { ... } else { ... }` selects the script at compile time by the configuration predicate,
+//! e.g. a feature of the calling crate, without branching at runtime. `else` may be omitted for an empty script,
+//! which allows to join optional fragments such as logging.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let trace = lua!(if cfg!(feature = "trace") {
+//!   redis.log(redis.LOG_NOTICE, "incr")
+//! });
+//! let script = trace.join(lua!(return 1 + 2));
+//! # let _ = script.source();
+//! # }
+//! ```
+//!
+//! # Keys
+//!
+//! `#` in front of `@` or `$` passes the value as `KEYS` instead of `ARGV`. Redis Cluster
+//! relies on `KEYS` to route the script to the node which owns the keys.
+//!
+//! ```rust
+//! # use redis_lua::lua;
+//! #
+//! # fn main() {
+//! # let mut cli = redis::Client::open("redis://localhost").unwrap();
+//! #
+//! let script = lua!(
+//!   redis.call("set", #$key, $value);
+//!   return redis.call("get", #$key);
+//! );
+//! let num: usize = script.key("counter").value(3).invoke(&mut cli).unwrap();
+//! assert_eq!(num, 3);
+//! # }
+//! ```
+//!
+//! `#"prefix"@x` or `#prefix:@x` passes the key concatenating the prefix and the captured value, e.g. `user:42`
+//! for `#"user:"@id`, by [`key_concat`][]. Tables can't be concatenated.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let id = 42;
+//! let script = lua!(return redis.call("get", #"{user}:"@id););
+//! assert_eq!(script.explain().keys(), &[b"{user}:42".to_vec()]);
+//! # }
+//! ```
+//!
+//! `#` in front of other tokens is the Lua length operator as usual. To take the length of a captured table,
+//! wrap it in parentheses, e.g. `#(@vec)`.
+//!
+//! `KEYS` in the script is the table of the keys of the script, so `#KEYS` counts them
+//! even if the script is joined with other scripts.
+//!
+//! `with_numkeys(n)` lets the script read the first `n` keys passed by `with_keys` from `KEYS` by hand,
+//! e.g. when porting a script written for `EVAL`. The script fails with an error reply unless exactly `n` keys
+//! are passed.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let script = lua!(return KEYS[1];).with_numkeys(1).with_keys(vec!["counter"]);
+//! # let _ = script.source();
+//! # }
+//! ```
+//!
+//! `on_db(n)` runs the script against the database `n` without changing the database of the connection.
+//! The scripts joined after it have to select their database by `on_db` too.
+//!
+//! ```rust
+//! # use redis_lua::{lua, Script};
+//! #
+//! # fn main() {
+//! let script = lua!(return redis.call("dbsize");).on_db(2);
+//! # let _ = script.source();
+//! # }
+//! ```
+//!
+//! # Including a script file
+//!
+//! `include_lua!` reads a Lua script from a file at compile time. Same as `include_str!`, the path is
+//! relative to the file where the macro is invoked. `@` and `$` work the same as in `lua!`.
+//!
+//! ```rust,ignore
+//! let amount = 3;
+//! let script = redis_lua::include_lua!("scripts/transfer.lua");
+//! let res: usize = script.from("a").to("b").invoke(&mut cli).unwrap();
+//! ```
+//!
+//! # Script modules
+//!
+//! [`lua_module!`][] defines several named scripts in one block, with an optional prelude of Lua helpers
+//! put at the beginning of every script. The scripts are the fields of the returned value.
+//!
+//! ```rust
+//! # use redis_lua::{lua_module, Script};
+//! #
+//! # fn main() {
+//! let scripts = lua_module! {
+//!     prelude {
+//!         local function clamp(x) return math.max(0, math.min(100, x)) end
+//!     }
+//!     fn add { return clamp($x + $y) }
+//!     fn sub { return clamp($x - $y) }
+//! };
+//! assert_eq!(scripts.add.x(1).y(2).names(), vec!["add"]);
+//! # }
+//! ```
+//!
+//! [`lua_prelude!`][] defines the Lua helpers shared by the scripts defined anywhere. `use_prelude` puts them at
+//! the beginning of the generated script once however many of the joined scripts use them. `prelude = "..."`
+//! declares the helpers called by the script, which `lua!` can't see otherwise.
+//!
+//! ```rust
+//! # use redis_lua::{lua, lua_prelude, Script};
+//! #
+//! # fn main() {
+//! let prelude = lua_prelude! {
+//!     local function clamp(x) return math.max(0, math.min(100, x)) end
+//! };
+//! let add = lua!(prelude = "clamp", { return clamp($x + $y) }).x(1).y(2);
+//! let sub = lua!(prelude = "clamp", { return clamp($x - $y) }).x(3).y(4);
+//! let joined = add.use_prelude(prelude).join(sub.use_prelude(prelude));
+//! assert_eq!(joined.source().matches("local function clamp").count(), 1);
+//! # }
+//! ```
+//!
+//! The prelude can't take arguments.
+//!
+//! ```compile_fail
+//! # fn main() {
+//! let prelude = redis_lua::lua_prelude! {
+//!     local function offset(x) return x + $offset end
+//! };
+//! # }
+//! ```
+//!
+//! # Templated scripts
+//!
+//! [`lua_fmt!`][] substitutes the values into the placeholders in the string literals of the script at runtime,
+//! e.g. `{prefix}`, rather than passing them to the script. `{{` and `}}` are literal braces.
+//!
+//! ```rust
+//! # use redis_lua::{lua_fmt, Script};
+//! #
+//! # fn main() {
+//! let prefix = "app";
+//! let script = lua_fmt!(prefix = prefix, {
+//!   return redis.call("incrby", "{prefix}:counter", $n);
+//! });
+//! assert!(script.n(1).source().contains("\"app:counter\""));
+//! # }
+//! ```
+//!
+//! Each distinct text is a distinct script with its own SHA1 digest, which is cached by Redis,
+//! so the values should be from a small set. The text is dropped with the script.
+//!
 //! # Type conversion
 //!
 //! `@` and `$` allow to pass Rust variables to Lua scripts. Primitive types and strings are converted to
@@ -160,6 +550,77 @@
 //!
 //! Complicated types such as structs, tuples, maps and non-u8 vectors are converted to Lua tables.
 //! The name of struct members become the key of tables.
+//! A table is passed as a single argument, so vectors and slices never shift the other arguments.
+//!
+//! Redis takes only the first value returned by a script. So multiple values returned by `return` at the
+//! top level of the script, e.g. `return a, b, c`, are returned as a table, which can be decoded as a tuple.
+//! `return` in functions defined in the script is kept as is.
+//!
+//! With the `serde` feature, [`json_arg`][] serializes a value to a JSON string by `serde_json`, which is passed as a single
+//! argument and decoded by `cjson.decode` in the script. It fails with `ScriptError::Encode` if the value
+//! cannot be represented in JSON, e.g. a map with non-string keys.
+//!
+//! ```rust,ignore
+//! let order = redis_lua::json_arg(&order)?;
+//! let script = lua!(
+//!   local order = cjson.decode(@order)
+//!   return order.items[1].name
+//! );
+//! ```
+//!
+//! [`Script::invoke_json`][] is the other way around. It deserializes the JSON string returned by the script,
+//! e.g. `return cjson.encode(order)`. `cjson` encodes an empty table as `{}`, so an empty object is decoded
+//! as an empty sequence as well.
+//!
+//! [`Script::invoke_value`][] returns the reply as `redis::Value` without conversion, e.g. for the scripts
+//! which return either a table or a scalar.
+//!
+//! Redis converts only the array part of a Lua table. Flat key-value pairs, e.g. `{k1, v1, k2, v2}`, are decoded
+//! into a `HashMap` by `invoke`, and [`Script::invoke_map`][] also takes an array of pairs, e.g. `{{k1, v1}, {k2, v2}}`.
+//!
+//! `invoke::<bool>` takes Lua `true` and nonzero integers as `true`, and `false`, `nil` and `0` as `false`.
+//! [`Script::invoke_bool`][] also takes the empty string, `"0"` and the empty table as `false`, and any other
+//! values as `true`.
+//!
+//! [`Script::invoke_into`][] converts the returned value by `TryFrom<redis::Value>`, e.g. into an enum of the status
+//! replies. A failed conversion is `ScriptError::Convert` with the returned value. [`Status`][] converts a status
+//! or string reply.
+//!
+//! ```rust,ignore
+//! let res: redis_lua::Status = lua!(return redis.status_reply("QUEUED")).invoke_into(&mut con)?;
+//! ```
+//!
+//! [`Script::invoke_timed`][] returns the time the script took on the server, measured by `TIME`, with the returned value.
+//!
+//! [`ScriptCursor`][] iterates the batches of the script which returns `{cursor, batch}`, same as `SCAN`,
+//! invoking the script with the returned cursor until it returns `"0"`.
+//!
+//! ```rust,ignore
+//! let script = lua!(
+//!     local res = redis.call("sscan", #$key, $cursor, "count", 100)
+//!     return {res[1], res[2]}
+//! );
+//! for batch in ScriptCursor::new(&mut con, |cursor| script.clone().key("members").cursor(cursor)) {
+//!     let members: Vec<String> = batch?;
+//! }
+//! ```
+//!
+//! # Retrying
+//!
+//! [`Script::invoke_retry`][] retries the script on transient errors with exponential backoff given by
+//! [`RetryPolicy`][]. The errors of the script are never retried. The script may have run before the connection
+//! failed, so retry only idempotent scripts.
+//!
+//! # Testing without Redis
+//!
+//! With the `testing` feature, `MockConnection` replies the given values instead of Redis and records the script
+//! invocations with the generated script, `KEYS` and `ARGV`, e.g. to check the arguments are bound in order.
+//!
+//! ```rust,ignore
+//! let mut con = redis_lua::MockConnection::new().reply(redis::Value::Int(3));
+//! let res: usize = lua!(return $x + $y).x(1).y(2).invoke(&mut con)?;
+//! assert_eq!(con.last_call().unwrap().args, vec![b"1".to_vec(), b"2".to_vec()]);
+//! ```
 //!
 //! # Limitation
 //!
@@ -169,7 +630,21 @@
 
 use proc_macro_hack::proc_macro_hack;
 
+mod cursor;
+mod error;
+#[cfg(feature = "serde")]
+mod json;
+mod minify;
+#[cfg(feature = "testing")]
+mod mock;
+mod options;
+mod pipeline;
+mod prepared;
+mod recorder;
+mod retry;
 mod script;
+mod template;
+mod transaction;
 mod types;
 
 pub use futures;
@@ -180,10 +655,65 @@ pub use serde;
 #[proc_macro_hack]
 pub use redis_lua_macro::lua;
 
+/// Macro to embed Lua script in a file in Rust code.
+///
+/// Same as `include_str!`, the path is relative to the file where the macro is invoked.
+#[proc_macro_hack]
+pub use redis_lua_macro::include_lua;
+
+/// Macro to embed Lua script in Rust code with the placeholders in the string literals substituted at runtime.
+///
+/// The values are substituted into the text of the script, unlike `@` and `$` which pass the values to the
+/// script, e.g. for key prefixes. Each distinct text is a distinct script on the server, and is kept until the
+/// end of the program.
+#[proc_macro_hack]
+pub use redis_lua_macro::lua_fmt;
+
+/// Macro to define several named scripts in one block, sharing a prelude of Lua helpers.
+///
+/// The scripts are the fields of the returned value by their names.
+#[proc_macro_hack]
+pub use redis_lua_macro::lua_module;
+
+/// Macro to define the Lua helpers shared by the scripts, used by `Script::use_prelude`.
+///
+/// Returns a `Prelude`. The prelude can't take arguments.
+#[proc_macro_hack]
+pub use redis_lua_macro::lua_prelude;
+
 /// Macro to convert Lua script to string.
 #[proc_macro_hack]
 pub use redis_lua_macro::lua_s;
 
-pub use script::{gen_script, Info, Script, ScriptJoin, TakeScript};
+/// Derive macro to capture a fieldless enum as its discriminant, e.g. `@color` as `2`.
+pub use redis_lua_macro::ScriptArg;
+
+pub use cursor::ScriptCursor;
+pub use error::ScriptError;
+#[cfg(feature = "serde")]
+pub use json::{json_arg, JsonArg};
+pub use minify::minify;
+#[cfg(feature = "testing")]
+pub use mock::{MockCall, MockConnection};
+pub use options::ScriptOptions;
+pub use pipeline::ScriptPipeline;
+pub use prepared::{PreparedInvocation, PreparedScript};
+pub use recorder::{ScriptRecord, ScriptRecorder};
+pub use retry::RetryPolicy;
+#[doc(hidden)]
+pub use script::invoke_raw;
+pub use script::{
+    gen_function, gen_script, gen_script_minified, load_all_async, script_kill, script_kill_async,
+    try_gen_script, ArgSource, Info, Prelude, Script, ScriptAndThen, ScriptCollect,
+    ScriptComponent, ScriptDescriptor, ScriptHashtag, ScriptJoin, ScriptNumKeys, ScriptOnDb,
+    ScriptPlan, ScriptPrelude, ScriptReadonly, ScriptRepeat, ScriptThen, ScriptWithKeys,
+    TakeScript, DEFAULT_MAX_SCRIPT_LEN,
+};
+#[doc(hidden)]
+pub use template::fmt_script;
+pub use transaction::ScriptTransaction;
 
-pub use types::{script_arg, ScriptArg};
+pub use types::{
+    bytes_arg, capture, float_arg, key_concat, script_arg, script_key, BytesArg, Capture, FloatArg,
+    ScriptArg, Status, TypedArg,
+};
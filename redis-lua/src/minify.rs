@@ -0,0 +1,88 @@
+/// Remove comments and collapse insignificant whitespace in a Lua script.
+///
+/// String literals are kept as is, and a space is kept where removing it would merge two tokens.
+pub fn minify(source: &str) -> String {
+    let mut minified = String::with_capacity(source.len());
+    let mut rest = source;
+    // Whether whitespace or a comment was skipped since the last token.
+    let mut space = false;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+            space = true;
+        } else if rest.starts_with("--") {
+            let end = match long_bracket(&rest[2..]) {
+                Some(len) => 2 + len,
+                None => rest.find('\n').unwrap_or(rest.len()),
+            };
+            rest = &rest[end..];
+            space = true;
+        } else {
+            let len = match c {
+                '"' | '\'' => quoted(rest, c),
+                '[' => long_bracket(rest).unwrap_or(1),
+                _ => c.len_utf8(),
+            };
+            let (token, tail) = rest.split_at(len);
+
+            if space
+                && minified
+                    .chars()
+                    .last()
+                    .is_some_and(|last| need_space(last, c))
+            {
+                minified.push(' ');
+            }
+            minified += token;
+
+            rest = tail;
+            space = false;
+        }
+    }
+
+    minified
+}
+
+/// The length of the quoted string at the beginning of the source.
+fn quoted(source: &str, quote: char) -> usize {
+    let mut escaped = false;
+
+    for (i, c) in source.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return i + 1,
+            _ => {}
+        }
+    }
+
+    source.len()
+}
+
+/// The length of the long bracket, e.g. `[[...]]` or `[==[...]==]`, at the beginning of the source.
+fn long_bracket(source: &str) -> Option<usize> {
+    let level = source.strip_prefix('[')?.find(|c| c != '=')?;
+    if source[1 + level..].chars().next()? != '[' {
+        return None;
+    }
+
+    let close = format!("]{}]", "=".repeat(level));
+    let begin = level + 2;
+    let end = source[begin..]
+        .find(&close)
+        .map(|i| begin + i + close.len())
+        .unwrap_or(source.len());
+
+    Some(end)
+}
+
+/// Whether the two characters have to be separated by a space not to be merged into one token.
+fn need_space(last: char, next: char) -> bool {
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    // Characters which make up the operators of more than one character, e.g. `..`, `==` and `[[`.
+    let op = |c: char| "-.=<>~[".contains(c);
+
+    // `1 ..` can't be `1..` which is a malformed number.
+    (word(last) && word(next)) || (op(last) && op(next)) || (last.is_ascii_digit() && next == '.')
+}
@@ -0,0 +1,231 @@
+use futures::prelude::*;
+use redis::{ErrorKind, RedisError, RedisResult, Value};
+use std::collections::{HashMap, VecDeque};
+
+/// A script invocation recorded by `MockConnection`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockCall {
+    /// The command, e.g. `EVALSHA` or `EVAL_RO`.
+    pub command: String,
+    /// The generated script.
+    pub source: String,
+    /// The values of `KEYS` in order.
+    pub keys: Vec<Vec<u8>>,
+    /// The values of `ARGV` in order.
+    pub args: Vec<Vec<u8>>,
+}
+
+/// A connection which records the script invocations and replies the given values instead of Redis, e.g. for
+/// unit tests.
+///
+/// Scripts are cached on `EVAL` and `SCRIPT LOAD`, and `EVALSHA` of an unknown script fails with `NOSCRIPT`,
+/// same as Redis. Only the invocations which run the script are recorded and consume the replies.
+#[derive(Debug, Default)]
+pub struct MockConnection {
+    replies: VecDeque<RedisResult<Value>>,
+    scripts: HashMap<String, String>,
+    calls: Vec<MockCall>,
+    queued: Option<Vec<Value>>,
+}
+
+impl MockConnection {
+    /// Create the connection without any replies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the reply to the next script invocation which has no reply yet.
+    ///
+    /// The invocations without replies get `nil`.
+    pub fn reply(mut self, value: Value) -> Self {
+        self.replies.push_back(Ok(value));
+        self
+    }
+
+    /// Add the error to the next script invocation which has no reply yet, e.g. the error reply of the script.
+    pub fn fail(mut self, error: RedisError) -> Self {
+        self.replies.push_back(Err(error));
+        self
+    }
+
+    /// The recorded script invocations in order.
+    pub fn calls(&self) -> &[MockCall] {
+        &self.calls
+    }
+
+    /// The last recorded script invocation.
+    pub fn last_call(&self) -> Option<&MockCall> {
+        self.calls.last()
+    }
+
+    fn run(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        let (parts, _) = parse_command(cmd)?;
+        self.run_parts(parts)
+    }
+
+    fn run_parts(&mut self, parts: Vec<Vec<u8>>) -> RedisResult<Value> {
+        let mut parts = parts.into_iter();
+        let name = String::from_utf8_lossy(&parts.next().unwrap_or_default()).to_uppercase();
+
+        match name.as_str() {
+            "MULTI" => {
+                self.queued = Some(vec![]);
+                return Ok(Value::Okay);
+            }
+            "EXEC" => return Ok(Value::Bulk(self.queued.take().unwrap_or_default())),
+            _ => {}
+        }
+
+        let res = self.run_command(name, parts);
+        match &mut self.queued {
+            Some(queued) => {
+                queued.push(res?);
+                Ok(Value::Status("QUEUED".into()))
+            }
+            None => res,
+        }
+    }
+
+    fn run_command(
+        &mut self,
+        name: String,
+        mut parts: impl Iterator<Item = Vec<u8>>,
+    ) -> RedisResult<Value> {
+        let mut next = || String::from_utf8_lossy(&parts.next().unwrap_or_default()).into_owned();
+
+        let source = match name.as_str() {
+            "EVAL" | "EVAL_RO" => {
+                let source = next();
+                let sha = redis::Script::new(&source).get_hash().to_string();
+                self.scripts.insert(sha, source.clone());
+                source
+            }
+            "EVALSHA" | "EVALSHA_RO" => match self.scripts.get(&next()) {
+                Some(source) => source.clone(),
+                None => {
+                    return Err(RedisError::from((
+                        ErrorKind::NoScriptError,
+                        "No matching script. Please use EVAL.",
+                    )))
+                }
+            },
+            "SCRIPT" if next().eq_ignore_ascii_case("LOAD") => {
+                let source = next();
+                let sha = redis::Script::new(&source).get_hash().to_string();
+                self.scripts.insert(sha.clone(), source);
+                return Ok(Value::Data(sha.into_bytes()));
+            }
+            _ => return Ok(Value::Okay),
+        };
+
+        let numkeys: usize = next().parse().unwrap_or(0);
+        let mut values: Vec<_> = parts.collect();
+        let args = values.split_off(numkeys.min(values.len()));
+
+        self.calls.push(MockCall {
+            command: name,
+            source,
+            keys: values,
+            args,
+        });
+        self.replies.pop_front().unwrap_or(Ok(Value::Nil))
+    }
+
+    fn run_all(&mut self, cmds: &[u8], offset: usize, count: usize) -> RedisResult<Vec<Value>> {
+        let mut replies = Vec::new();
+        let mut rest = cmds;
+        while !rest.is_empty() {
+            let (parts, tail) = parse_command(rest)?;
+            replies.push(self.run_parts(parts)?);
+            rest = tail;
+        }
+        Ok(replies.into_iter().skip(offset).take(count).collect())
+    }
+}
+
+impl redis::ConnectionLike for MockConnection {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        self.run(cmd)
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        self.run_all(cmd, offset, count)
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+
+    fn check_connection(&mut self) -> bool {
+        true
+    }
+
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+impl redis::aio::ConnectionLike for MockConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a redis::Cmd) -> redis::RedisFuture<'a, Value> {
+        let res = self.run(&cmd.get_packed_command());
+        future::ready(res).boxed()
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a redis::Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisFuture<'a, Vec<Value>> {
+        let res = self.run_all(&cmd.get_packed_pipeline(), offset, count);
+        future::ready(res).boxed()
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
+
+fn invalid() -> RedisError {
+    RedisError::from((ErrorKind::ClientError, "invalid packed command"))
+}
+
+/// Read the number terminated by CRLF, e.g. `3` of `*3\r\n`, returning the rest.
+fn parse_number(cmd: &[u8]) -> RedisResult<(usize, &[u8])> {
+    let end = cmd
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .ok_or_else(invalid)?;
+    let n = std::str::from_utf8(&cmd[..end])
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(invalid)?;
+    Ok((n, &cmd[end + 2..]))
+}
+
+/// Split the first packed command, i.e. an array of bulk strings, into its parts, returning the rest.
+fn parse_command(cmd: &[u8]) -> RedisResult<(Vec<Vec<u8>>, &[u8])> {
+    let (len, mut rest) = match cmd.split_first() {
+        Some((b'*', rest)) => parse_number(rest)?,
+        _ => return Err(invalid()),
+    };
+
+    let mut parts = Vec::with_capacity(len);
+    for _ in 0..len {
+        let (n, tail) = match rest.split_first() {
+            Some((b'$', tail)) => parse_number(tail)?,
+            _ => return Err(invalid()),
+        };
+        if tail.len() < n + 2 {
+            return Err(invalid());
+        }
+        parts.push(tail[..n].to_vec());
+        rest = &tail[n + 2..];
+    }
+    Ok((parts, rest))
+}
@@ -0,0 +1,80 @@
+use crate::recorder::{ScriptRecord, ScriptRecorder};
+use std::{fmt, sync::Arc};
+
+/// The hook called on the `NOSCRIPT` fallback with the names and the SHA1 digest of the script.
+type NoScriptHook = Arc<dyn Fn(&[&'static str], &str) + Send + Sync>;
+
+/// Options of the script invocation.
+#[derive(Clone, Default)]
+pub struct ScriptOptions {
+    on_noscript: Option<NoScriptHook>,
+    max_len: Option<usize>,
+    recorder: Option<Arc<dyn ScriptRecorder>>,
+}
+
+impl ScriptOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hook which is called when the script is not cached on the server
+    /// and is sent again by `EVAL`, e.g. to count the fallbacks.
+    ///
+    /// The hook takes the names of the joined scripts and the SHA1 digest of the script.
+    pub fn on_noscript<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[&'static str], &str) + Send + Sync + 'static,
+    {
+        self.on_noscript = Some(Arc::new(f));
+        self
+    }
+
+    /// Fail the invocation without sending the script if the generated script is longer than `max_len` bytes,
+    /// e.g. `DEFAULT_MAX_SCRIPT_LEN`. There's no limit by default.
+    pub fn max_script_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Record the invocations to the recorder with the generated script, the keys, the arguments and the reply,
+    /// e.g. to replay them against a test server.
+    pub fn recorder<R>(mut self, recorder: Arc<R>) -> Self
+    where
+        R: ScriptRecorder + 'static,
+    {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub(crate) fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    pub(crate) fn noscript(&self, names: &[&'static str], sha1: &str) {
+        if let Some(f) = &self.on_noscript {
+            f(names, sha1);
+        }
+    }
+
+    /// Whether the invocations are recorded, so the record is built only if so.
+    pub(crate) fn recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub(crate) fn record(&self, record: ScriptRecord) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(record);
+        }
+    }
+}
+
+impl fmt::Debug for ScriptOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScriptOptions")
+            .field("on_noscript", &self.on_noscript.is_some())
+            .field("max_len", &self.max_len)
+            .field("recorder", &self.recorder.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,109 @@
+use crate::script::{evalsha_cmd, gen_script, load_cmd, Script};
+use futures::prelude::*;
+
+/// The script invocations which are sent together in one round trip.
+///
+/// Each invocation is sent by `EVALSHA`, so the body of the same script is not repeated.
+/// If Redis replies `NOSCRIPT`, the scripts are loaded once by `SCRIPT LOAD` and the entire
+/// pipeline is sent again; use `Script::load` beforehand if the scripts must not be retried.
+#[derive(Clone, Default)]
+pub struct ScriptPipeline {
+    /// The `EVALSHA` commands in order.
+    invocations: Vec<redis::Cmd>,
+    /// The `SCRIPT LOAD` commands of the distinct scripts by their SHA1 digests.
+    loads: Vec<(String, redis::Cmd)>,
+}
+
+impl ScriptPipeline {
+    /// Create an empty pipeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a script invocation to the pipeline.
+    pub fn script<S: Script>(mut self, script: S) -> Self {
+        self.push(script);
+        self
+    }
+
+    /// The number of the script invocations.
+    pub fn len(&self) -> usize {
+        self.invocations.len()
+    }
+
+    /// Whether the pipeline has no script invocations.
+    pub fn is_empty(&self) -> bool {
+        self.invocations.is_empty()
+    }
+
+    fn push<S: Script>(&mut self, script: S) {
+        let mut info = vec![];
+        let mut args = vec![];
+        script.info(&mut info, &mut args);
+
+        let prepared = gen_script(&info, &args);
+        let hash = prepared.get_hash();
+        if !self.loads.iter().any(|(h, _)| h == hash) {
+            self.loads.push((hash.into(), load_cmd(&info, &args)));
+        }
+        self.invocations.push(evalsha_cmd(&info, &prepared, &args));
+    }
+
+    fn pipe(&self) -> redis::Pipeline {
+        let mut pipe = redis::pipe();
+        for cmd in &self.invocations {
+            pipe.add_command(cmd.clone());
+        }
+        pipe
+    }
+
+    fn load_pipe(&self) -> redis::Pipeline {
+        let mut pipe = redis::pipe();
+        for (_, cmd) in &self.loads {
+            pipe.add_command(cmd.clone()).ignore();
+        }
+        pipe
+    }
+
+    /// Invoke all the scripts returning their results in order.
+    pub fn invoke<T>(&self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<Vec<T>>
+    where
+        T: redis::FromRedisValue,
+    {
+        let pipe = self.pipe();
+        match pipe.query(con) {
+            Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+                self.load_pipe().query::<()>(con)?;
+                pipe.query(con)
+            }
+            res => res,
+        }
+    }
+
+    /// Invoke all the scripts asynchronously.
+    pub fn invoke_async<'a, C, T>(&'a self, con: &'a mut C) -> redis::RedisFuture<'a, Vec<T>>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        T: redis::FromRedisValue + Send,
+    {
+        async move {
+            let pipe = self.pipe();
+            match pipe.query_async(con).await {
+                Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+                    self.load_pipe().query_async::<_, ()>(con).await?;
+                    pipe.query_async(con).await
+                }
+                res => res,
+            }
+        }
+        .boxed()
+    }
+}
+
+impl<S: Script> Extend<S> for ScriptPipeline {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        for script in iter {
+            self.push(script);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{
+    script::{eval_cmd, evalsha_cmd, gen_script, locate_error, Info, Script},
+    types::ScriptArg,
+};
+use futures::prelude::*;
+
+/// The generated script with the buffers of the script information and the arguments,
+/// which are reused by the invocations with new arguments, e.g. in a tight loop.
+pub struct PreparedScript {
+    script: redis::Script,
+    info: Vec<Info>,
+    args: Vec<ScriptArg>,
+    /// The script information of the script bound last, compared with the prepared one.
+    bound: Vec<Info>,
+    /// Whether each argument is a key and packed, which changes the generated script.
+    layout: Vec<(bool, bool)>,
+}
+
+impl PreparedScript {
+    /// Generate the script. The arguments are replaced by `bind` before each invocation.
+    pub fn new<S: Script>(script: &S) -> Self {
+        let mut info = vec![];
+        let mut args = vec![];
+        script.info(&mut info, &mut args);
+
+        Self {
+            script: gen_script(&info, &args),
+            layout: layout(&args).collect(),
+            info,
+            args,
+            bound: vec![],
+        }
+    }
+
+    /// The generated script.
+    pub fn script(&self) -> &redis::Script {
+        &self.script
+    }
+
+    /// Bind the keys and the arguments of the script, usually the same script as the prepared one
+    /// with new arguments, reusing the buffers.
+    ///
+    /// The script is generated again only if it differs from the prepared one.
+    pub fn bind<S: Script>(&mut self, script: S) -> PreparedInvocation<'_> {
+        self.bound.clear();
+        self.args.clear();
+        script.info(&mut self.bound, &mut self.args);
+
+        if self.bound != self.info || !layout(&self.args).eq(self.layout.iter().copied()) {
+            self.script = gen_script(&self.bound, &self.args);
+            self.layout = layout(&self.args).collect();
+            std::mem::swap(&mut self.info, &mut self.bound);
+        }
+
+        PreparedInvocation { prepared: self }
+    }
+}
+
+fn layout(args: &[ScriptArg]) -> impl Iterator<Item = (bool, bool)> + '_ {
+    args.iter().map(|arg| (arg.is_key(), arg.pack()))
+}
+
+/// The invocation of the prepared script with the arguments bound by `PreparedScript::bind`.
+pub struct PreparedInvocation<'a> {
+    prepared: &'a PreparedScript,
+}
+
+impl<'a> PreparedInvocation<'a> {
+    /// Invoke the script by `EVALSHA`, falling back to `EVAL` on `NOSCRIPT`.
+    pub fn invoke<T>(self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+    {
+        let PreparedScript {
+            script, info, args, ..
+        } = self.prepared;
+
+        let res = match con.req_command(&evalsha_cmd(info, script, args)) {
+            Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+                con.req_command(&eval_cmd(info, args))
+            }
+            res => res,
+        };
+        res.and_then(|value| T::from_redis_value(&value))
+            .map_err(|e| locate_error(info, args, e))
+    }
+
+    /// Invoke the script asynchronously.
+    pub fn invoke_async<'b, C, T>(self, con: &'b mut C) -> redis::RedisFuture<'b, T>
+    where
+        'a: 'b,
+        C: redis::aio::ConnectionLike + Send,
+        T: redis::FromRedisValue + Send,
+    {
+        async move {
+            let PreparedScript {
+                script, info, args, ..
+            } = self.prepared;
+
+            let res = match con
+                .req_packed_command(&evalsha_cmd(info, script, args))
+                .await
+            {
+                Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+                    con.req_packed_command(&eval_cmd(info, args)).await
+                }
+                res => res,
+            };
+            res.and_then(|value| T::from_redis_value(&value))
+                .map_err(|e| locate_error(info, args, e))
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,123 @@
+use crate::script::{gen_source, Info};
+use crate::types::ScriptArg;
+use redis::ToRedisArgs;
+use std::sync::Mutex;
+
+/// An invocation of a script recorded by `ScriptRecorder`, which can be replayed by `replay`.
+///
+/// Implements `Serialize` with the `serde` feature, e.g. to write the log as JSON lines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptRecord {
+    names: Vec<&'static str>,
+    source: String,
+    keys: Vec<Vec<u8>>,
+    args: Vec<Vec<u8>>,
+    result: Result<redis::Value, String>,
+}
+
+impl ScriptRecord {
+    pub(crate) fn new(
+        info: &[Info],
+        args: &[ScriptArg],
+        result: &redis::RedisResult<redis::Value>,
+    ) -> Self {
+        let (keys, argv): (Vec<_>, Vec<_>) = args.iter().partition(|arg| arg.is_key());
+        Self {
+            names: info.iter().filter_map(|i| i.name()).collect(),
+            source: gen_source(info, args),
+            keys: keys.iter().flat_map(|key| key.to_redis_args()).collect(),
+            args: argv.iter().flat_map(|arg| arg.to_redis_args()).collect(),
+            result: result.as_ref().cloned().map_err(|e| e.to_string()),
+        }
+    }
+
+    /// The names of the joined scripts, same as `Script::names`.
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+
+    /// The generated script.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The values of `KEYS` in order.
+    pub fn keys(&self) -> &[Vec<u8>] {
+        &self.keys
+    }
+
+    /// The values of `ARGV` in order.
+    pub fn args(&self) -> &[Vec<u8>] {
+        &self.args
+    }
+
+    /// The reply of the script, or the error message.
+    pub fn result(&self) -> Result<&redis::Value, &str> {
+        self.result.as_ref().map_err(|e| e.as_str())
+    }
+
+    /// Run the recorded script with the recorded keys and arguments again by `EVAL`, e.g. against a test server.
+    pub fn replay(&self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<redis::Value> {
+        redis::cmd("EVAL")
+            .arg(&self.source)
+            .arg(self.keys.len())
+            .arg(&self.keys)
+            .arg(&self.args)
+            .query(con)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ScriptRecord {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("ScriptRecord", 5)?;
+        s.serialize_field("names", &self.names)?;
+        s.serialize_field("source", &self.source)?;
+        s.serialize_field("keys", &self.keys)?;
+        s.serialize_field("args", &self.args)?;
+        match &self.result {
+            Ok(value) => s.serialize_field("result", &RecordedValue(value))?,
+            Err(e) => s.serialize_field("error", e)?,
+        }
+        s.end()
+    }
+}
+
+/// The reply serialized as `null`, an integer, bytes, a string for statuses, or a sequence.
+#[cfg(feature = "serde")]
+struct RecordedValue<'a>(&'a redis::Value);
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for RecordedValue<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.0 {
+            redis::Value::Nil => serializer.serialize_none(),
+            redis::Value::Int(v) => serializer.serialize_i64(*v),
+            redis::Value::Data(v) => serializer.serialize_bytes(v),
+            redis::Value::Bulk(values) => serializer.collect_seq(values.iter().map(RecordedValue)),
+            redis::Value::Status(v) => serializer.serialize_str(v),
+            redis::Value::Okay => serializer.serialize_str("OK"),
+        }
+    }
+}
+
+/// The sink of the invocations of the scripts, set by `ScriptOptions::recorder`.
+pub trait ScriptRecorder: Send + Sync {
+    /// Record the invocation after the reply, or the error, is received.
+    fn record(&self, record: ScriptRecord);
+}
+
+/// Keeps the records in memory in the order of the invocations.
+impl ScriptRecorder for Mutex<Vec<ScriptRecord>> {
+    fn record(&self, record: ScriptRecord) {
+        self.lock().unwrap().push(record);
+    }
+}
@@ -0,0 +1,74 @@
+use redis::{ErrorKind, RedisError};
+use std::time::Duration;
+
+/// The policy to retry the script invocation on transient errors with exponential backoff.
+///
+/// Only the connection-level errors are retried, e.g. I/O errors, `TRYAGAIN` and `LOADING`.
+/// The errors of the script, e.g. `WRONGTYPE` or `redis.error_reply`, are never retried,
+/// so the script should be idempotent as it may have run before the connection failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create the default policy, which retries 3 times waiting 10ms, 20ms and 40ms.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of the retries.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the wait before the first retry, which doubles for each retry.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the upper limit of the wait before a retry.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Whether the error is transient, so the invocation can be retried.
+    pub fn is_retryable(&self, e: &RedisError) -> bool {
+        matches!(
+            e.kind(),
+            ErrorKind::IoError
+                | ErrorKind::TryAgain
+                | ErrorKind::BusyLoadingError
+                | ErrorKind::ClusterDown
+                | ErrorKind::MasterDown
+        )
+    }
+
+    /// The wait before the retry after the failed attempt, or `None` if no more retries are allowed.
+    pub(crate) fn retry(&self, attempt: usize, e: &RedisError) -> Option<Duration> {
+        if attempt >= self.max_retries || !self.is_retryable(e) {
+            return None;
+        }
+        let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+        Some(
+            self.backoff
+                .checked_mul(factor)
+                .map_or(self.max_backoff, |wait| wait.min(self.max_backoff)),
+        )
+    }
+}
@@ -1,21 +1,298 @@
-use crate::types::ScriptArg;
+use crate::{
+    error::ScriptError,
+    minify::minify,
+    options::ScriptOptions,
+    recorder::ScriptRecord,
+    retry::RetryPolicy,
+    types::{key_hashtag, raw_arg, script_key, ScriptArg},
+};
 use futures::prelude::*;
+use redis::ToRedisArgs;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex, OnceLock},
+};
 
-/// Script information which is generated by proc-macro.
+/// The text of the script, which is owned if it's completed at runtime, e.g. by `lua_fmt!`.
+///
+/// Compared by the text, same as `str`.
 #[derive(Clone, Debug)]
+enum Text {
+    Static(&'static str),
+    Owned(Arc<str>),
+}
+
+impl Deref for Text {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Text::Static(text) => text,
+            Text::Owned(text) => text,
+        }
+    }
+}
+
+impl PartialEq for Text {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Text {}
+
+/// Script information which is generated by proc-macro.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Info {
     /// The entire script including arguments initialization.
-    script: &'static str,
+    script: Text,
     /// The script excluding arguments initialization.
-    body: &'static str,
+    body: Text,
     /// The list of arguments.
     args: &'static [&'static str],
+    /// The list of keys.
+    keys: &'static [&'static str],
+    /// The names of the keys and the arguments in Rust.
+    names: &'static [&'static str],
+    /// Where the keys and the arguments come from, in the same order as `names`.
+    sources: &'static [ArgSource],
+    /// The name of the script, e.g. for logging.
+    name: Option<&'static str>,
+    /// Whether the script doesn't write, so it can be invoked by `EVAL_RO`.
+    readonly: bool,
+    /// The identity of the script in the cache instead of the pointers.
+    cache_key: Option<&'static str>,
+    /// The file and the line of the first line of the body in Rust.
+    location: Option<(&'static str, u32)>,
+    /// How the script is composed with the others.
+    kind: Kind,
+}
+
+/// Kind of script information.
+///
+/// Groups are represented by `Begin` and `End` around the script information of the group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Kind {
+    /// Script body.
+    Body,
+    /// Beginning of a group.
+    Begin(Group),
+    /// End of a group.
+    End,
+    /// Keys which are not referenced by the scripts.
+    Keys(usize),
+    /// Lua helpers put at the beginning of the generated script once, e.g. by `Script::use_prelude`.
+    Prelude,
+}
+
+/// Where a key or an argument passed to the script comes from, for `Script::arg_layout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ArgSource {
+    /// A captured Rust variable, e.g. `@x`.
+    Capture,
+    /// An argument given by the builder method, e.g. `$x`.
+    Argument,
+    /// A key passed by `with_keys`.
+    Keys,
+}
+
+/// How the scripts in a group are composed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Group {
+    /// Returns the return values of all the scripts as a table.
+    Collect,
+    /// The scripts read the given number of the keys passed by `with_keys` from `KEYS` by hand.
+    NumKeys(usize),
+    /// The scripts run against the given database.
+    Db(i64),
+    /// Returns the elapsed time on the server in microseconds with the return value of the last script.
+    Timed,
+    /// The scripts whose return value decides whether the others in the enclosing `Then` run.
+    Guard,
+    /// Runs the scripts following the `Guard` only if it returns a truthy value, otherwise returns `nil`.
+    Then,
+    /// Runs the scripts following the `Guard` with its return value as `@@prev`.
+    AndThen,
+    /// Runs the given number of windows of the same scripts in a loop, returning their return values as a table.
+    Repeat(usize),
+    /// The scripts which can't be run, failing the whole script with the reason, e.g. a hash tag on a table key.
+    Invalid(&'static str),
 }
 
 impl Info {
     /// Create the new script information.
     pub fn new(script: &'static str, body: &'static str, args: &'static [&'static str]) -> Self {
-        Self { script, body, args }
+        Self::with_text(Text::Static(script), Text::Static(body), args)
+    }
+
+    /// Create the script information with the script completed at runtime, e.g. by `lua_fmt!`.
+    ///
+    /// The script is dropped with the script information, and it's not kept in the cache of the generated scripts.
+    pub fn new_owned(script: String, body: String, args: &'static [&'static str]) -> Self {
+        Self::with_text(Text::Owned(script.into()), Text::Owned(body.into()), args)
+    }
+
+    fn with_text(script: Text, body: Text, args: &'static [&'static str]) -> Self {
+        Self {
+            script,
+            body,
+            args,
+            keys: &[],
+            names: &[],
+            sources: &[],
+            name: None,
+            readonly: false,
+            cache_key: None,
+            location: None,
+            kind: Kind::Body,
+        }
+    }
+
+    /// Create the script information checking that `script` reads `ARGV[1]` to `ARGV[n]` for the `n` arguments,
+    /// e.g. for the script information built by hand, where an argument missed by the script is easily overlooked.
+    pub fn new_checked(
+        script: &'static str,
+        body: &'static str,
+        args: &'static [&'static str],
+    ) -> redis::RedisResult<Self> {
+        let indices = argv_indices(script);
+        if !indices.iter().copied().eq(1..=args.len()) {
+            return Err(redis::RedisError::from((
+                redis::ErrorKind::ClientError,
+                "arguments mismatch",
+                format!(
+                    "the script reads `ARGV` at {:?} but declares {} arguments",
+                    indices,
+                    args.len()
+                ),
+            )));
+        }
+
+        Ok(Self::new(script, body, args))
+    }
+
+    fn begin(group: Group) -> Self {
+        Self {
+            kind: Kind::Begin(group),
+            ..Self::new("", "", &[])
+        }
+    }
+
+    fn end() -> Self {
+        Self {
+            kind: Kind::End,
+            ..Self::new("", "", &[])
+        }
+    }
+
+    fn extra_keys(len: usize) -> Self {
+        Self {
+            kind: Kind::Keys(len),
+            ..Self::new("", "", &[])
+        }
+    }
+
+    fn prelude(source: &'static str) -> Self {
+        Self {
+            kind: Kind::Prelude,
+            ..Self::new(source, source, &[])
+        }
+    }
+
+    /// Set the list of keys.
+    pub fn with_keys(mut self, keys: &'static [&'static str]) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Set the names of the keys followed by the arguments in Rust, e.g. `x` for `@x`.
+    pub fn with_names(mut self, names: &'static [&'static str]) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// Set where the keys followed by the arguments come from.
+    pub fn with_sources(mut self, sources: &'static [ArgSource]) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Set the name of the script.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Mark the script as read-only.
+    pub fn with_readonly(mut self) -> Self {
+        self.readonly = true;
+        self
+    }
+
+    /// Identify the script by the key in the cache of the generated scripts.
+    ///
+    /// The scripts with the same key share the generated script, so they must be identical.
+    pub fn with_cache_key(mut self, key: &'static str) -> Self {
+        self.cache_key = Some(key);
+        self
+    }
+
+    /// Set the file and the line of the first line of the body in Rust, e.g. to locate the errors.
+    pub fn with_location(mut self, file: &'static str, line: u32) -> Self {
+        self.location = Some((file, line));
+        self
+    }
+
+    /// The entire script including arguments initialization.
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+
+    /// The script excluding arguments initialization.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// The list of arguments.
+    pub fn args(&self) -> &'static [&'static str] {
+        self.args
+    }
+
+    /// The list of keys.
+    pub fn keys(&self) -> &'static [&'static str] {
+        self.keys
+    }
+
+    /// The names of the keys followed by the arguments in Rust.
+    pub fn names(&self) -> &'static [&'static str] {
+        self.names
+    }
+
+    /// Where the keys followed by the arguments come from.
+    pub fn sources(&self) -> &'static [ArgSource] {
+        self.sources
+    }
+
+    /// The name of the script.
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// Whether the script is read-only.
+    pub fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// The key of the script in the cache if it's given.
+    pub fn cache_key(&self) -> Option<&'static str> {
+        self.cache_key
+    }
+
+    /// The file and the line of the body in Rust if it's known.
+    pub fn location(&self) -> Option<(&'static str, u32)> {
+        self.location
     }
 }
 
@@ -35,102 +312,2402 @@ pub trait Script {
         ScriptJoin(self, other)
     }
 
-    /// Invoke the script.
-    fn invoke<T>(self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<T>
+    /// Run another script only if this script returns a truthy value, e.g. for compare-and-swap.
+    ///
+    /// Returns the return value of `action`, or `nil` without running it otherwise. Same as `invoke_bool`,
+    /// `nil`, `false`, `0`, `"0"`, the empty string and the empty table are not truthy.
+    /// The error reply returned by this script is returned as is.
+    fn then<T: Script>(self, action: T) -> ScriptThen<Self, T>
     where
-        T: redis::FromRedisValue,
         Self: Sized,
     {
-        let mut info = vec![];
-        let mut args = vec![];
-        self.info(&mut info, &mut args);
-        let script = gen_script(&info, &args);
-        let mut invoke = script.prepare_invoke();
-        for wr in args {
-            invoke.arg(wr);
-        }
-        invoke.invoke(con)
+        ScriptThen(self, action)
     }
 
-    /// Invoke the script asynchronously.
-    fn invoke_async<'a, C, T>(self, con: &'a mut C) -> redis::RedisFuture<'a, T>
+    /// Run another script with the return value of this script as `@@prev`, e.g. `lua!(return @@prev + 1)`.
+    ///
+    /// `@@prev` is a Lua local holding the value, so it's passed without a round trip nor `ARGV`.
+    /// It's `nil` in the scripts which don't follow `and_then`. The error reply returned by this script
+    /// is returned as is without running `next`.
+    fn and_then<T: Script>(self, next: T) -> ScriptAndThen<Self, T>
     where
-        C: redis::aio::ConnectionLike + Send,
-        T: redis::FromRedisValue + Send,
-        Self: Sized + Send + 'a,
+        Self: Sized,
     {
-        async move {
-            let mut info = vec![];
-            let mut args = vec![];
-            self.info(&mut info, &mut args);
-            let script = gen_script(&info, &args);
-            let mut invoke = script.prepare_invoke();
-            for wr in args {
-                invoke.arg(wr);
-            }
-            invoke.invoke_async(con).await
+        ScriptAndThen(self, next)
+    }
+
+    /// Return the return values of all the joined scripts as a table instead of only the last one.
+    ///
+    /// Same as Lua tables, the values after the first `nil` are discarded.
+    fn collect(self) -> ScriptCollect<Self>
+    where
+        Self: Sized,
+    {
+        ScriptCollect(self)
+    }
+
+    /// Run the script `n` times in a loop on the server, returning the return values as a table.
+    ///
+    /// Each run reads its own window of `KEYS` and `ARGV`, whose values are the ones of this script
+    /// until others are bound by `ScriptRepeat::apply`. The body is generated once however large `n` is.
+    fn repeat(self, n: usize) -> ScriptRepeat<Self>
+    where
+        Self: Sized,
+    {
+        ScriptRepeat {
+            n,
+            windows: vec![self],
         }
-        .boxed()
     }
-}
 
-impl<S: Script + ?Sized> Script for Box<S> {
-    fn info(&self, infos: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
-        (**self).info(infos, args);
+    /// Mark the script as read-only, so it's invoked by `EVALSHA_RO` and `EVAL_RO`, which can run on replicas.
+    ///
+    /// Redis rejects the writes from the read-only scripts. Requires Redis 7.
+    fn readonly(self) -> ScriptReadonly<Self>
+    where
+        Self: Sized,
+    {
+        ScriptReadonly(self)
     }
-}
 
-impl Script for () {
-    fn info(&self, _: &mut Vec<Info>, _: &mut Vec<ScriptArg>) {}
-}
+    /// Put the hash tag in front of all the keys, e.g. `{user123}:balance` for `balance`,
+    /// so that Redis Cluster puts them in the same hash slot instead of failing with `CROSSSLOT`.
+    ///
+    /// The tag decides the slot even if the keys have their own tags, because Redis hashes the first one.
+    /// The invocation fails with an error reply if the tag is empty or has braces, which Redis doesn't take as a tag,
+    /// or any of the keys is a table.
+    fn with_hashtag(self, tag: &str) -> ScriptHashtag<Self>
+    where
+        Self: Sized,
+    {
+        let invalid = if tag.is_empty() || tag.contains(['{', '}']) {
+            Some("the hash tag can't be empty or have braces")
+        } else {
+            None
+        };
+        ScriptHashtag {
+            tag: tag.into(),
+            invalid,
+            script: self,
+        }
+    }
 
-/// Represents the set of two scripts which are joined.
-pub struct ScriptJoin<S, T>(S, T);
+    /// Pass the additional keys to `KEYS` ahead of the keys of the script.
+    ///
+    /// The keys are not visible to the script; they let Redis Cluster route the script
+    /// when the keys are only known at runtime.
+    fn with_keys<K, I>(self, keys: I) -> ScriptWithKeys<Self>
+    where
+        K: serde::Serialize,
+        I: IntoIterator<Item = K>,
+        Self: Sized,
+    {
+        ScriptWithKeys {
+            keys: keys.into_iter().map(|key| script_key(&key)).collect(),
+            script: self,
+        }
+    }
 
-impl<S, T> Script for ScriptJoin<S, T>
-where
-    S: Script,
-    T: Script,
-{
-    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
-        self.0.info(info, args);
-        self.1.info(info, args);
+    /// Let the script read the keys passed by `with_keys` from `KEYS` by hand, e.g. `KEYS[1]`.
+    ///
+    /// The script fails with an error reply unless exactly `numkeys` keys are passed by `with_keys`,
+    /// which can be called either before or after this. The keys of the script, e.g. `#$key`, follow them in `KEYS`.
+    fn with_numkeys(self, numkeys: usize) -> ScriptNumKeys<Self>
+    where
+        Self: Sized,
+    {
+        ScriptNumKeys {
+            numkeys,
+            script: self,
+        }
     }
-}
 
-/// Take another script as the inner of the script.
-pub trait TakeScript<I> {
-    type Item;
+    /// Run the script against the database `db` by `SELECT` inside the script.
+    ///
+    /// Redis restores the database of the connection when the script returns, so the connection and
+    /// the other users of it are not affected. The scripts joined to it are run against the database
+    /// of the connection, which the script can't tell, so they have to select their own by `on_db` too;
+    /// otherwise the script fails with an error reply before running any of them.
+    /// Redis Cluster only has the database 0, where `SELECT` of the others fails.
+    fn on_db(self, db: i64) -> ScriptOnDb<Self>
+    where
+        Self: Sized,
+    {
+        ScriptOnDb { db, script: self }
+    }
 
-    /// Take the inner script.
-    fn take(self, inner: I) -> Self::Item;
-}
+    /// Put the Lua helpers defined by `lua_prelude!` at the beginning of the generated script.
+    ///
+    /// The prelude is put only once however many of the joined scripts use it. The script has to declare
+    /// the helpers it calls, e.g. `lua!(prelude = "uuid", ...)`, since `lua!` can't see the prelude.
+    fn use_prelude(self, prelude: Prelude) -> ScriptPrelude<Self>
+    where
+        Self: Sized,
+    {
+        ScriptPrelude {
+            prelude,
+            script: self,
+        }
+    }
 
-/// Generate a script from a list of script information.
-pub fn gen_script(info: &[Info], args: &[ScriptArg]) -> redis::Script {
-    assert!(info.len() > 0, "No script information");
+    /// Generate the `redis::Script` without invoking it.
+    fn prepare(&self) -> redis::Script {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        gen_script(&info, &args)
+    }
 
-    // Generate the joined script.
-    let mut arg_index = 0;
-    let mut script = String::new();
-    let last = info.len() - 1;
-    for (index, info) in info.iter().enumerate() {
-        let prefix = if index == last { "return " } else { "" };
-        let mut init = String::new();
+    /// The generated script which is sent to Redis.
+    fn source(&self) -> String {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        gen_source(&info, &args)
+    }
 
-        for arg in info.args {
-            let pack = args[arg_index].pack();
+    /// The file and the line in Rust of the line of the generated script, e.g. in the error of Redis.
+    ///
+    /// Returns `None` if the line is generated by redis-lua or the source is minified.
+    fn locate(&self, line: usize) -> Option<(&'static str, u32)> {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        source_map(&info, &args)
+            .iter()
+            .find_map(|mapping| mapping.locate(line))
+    }
 
-            arg_index += 1;
+    /// Generate the script and its arguments without invoking it.
+    fn explain(&self) -> ScriptPlan {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
 
-            if pack {
-                init += &format!("local {} = cmsgpack.unpack(ARGV[{}]) ", arg, arg_index);
+        let mut plan = ScriptPlan {
+            source: gen_source(&info, &args),
+            keys: vec![],
+            args: vec![],
+        };
+        for arg in args {
+            if arg.is_key() {
+                plan.keys.extend(arg.to_redis_args());
             } else {
-                init += &format!("local {} = ARGV[{}] ", arg, arg_index);
+                plan.args.extend(arg.to_redis_args());
+            }
+        }
+        plan
+    }
+
+    /// The metadata of the script at once, e.g. for a catalog of the scripts.
+    fn describe(&self) -> ScriptDescriptor {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        let (script, source_len) = gen_script_len(&info, &args);
+
+        ScriptDescriptor {
+            names: self.names(),
+            args: self
+                .arg_layout()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+            keys_count: self.keys_count(),
+            source_len,
+            sha1: script.get_hash().into(),
+        }
+    }
+
+    /// The keys and the arguments passed to the script with their names, e.g. for logging.
+    ///
+    /// The names are the ones in Rust, e.g. `x` for `@x`. The keys passed by `with_keys` are named `KEYS`.
+    fn arg_pairs(&self) -> Vec<(&'static str, Vec<Vec<u8>>)> {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+
+        pairs_by_info(&info, &args)
+            .into_iter()
+            .flat_map(|(_, pairs)| pairs)
+            .collect()
+    }
+
+    /// The joined scripts in the order they run, each with its keys and arguments named same as `arg_pairs`.
+    ///
+    /// The keys passed by `with_keys` don't belong to any of the scripts, so they are not included.
+    fn components(&self) -> Vec<ScriptComponent> {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+
+        pairs_by_info(&info, &args)
+            .into_iter()
+            .filter(|(info, _)| info.kind == Kind::Body)
+            .map(|(info, args)| ScriptComponent {
+                info: info.clone(),
+                args,
+            })
+            .collect()
+    }
+
+    /// The names of the keys followed by the arguments in the order of `KEYS` and `ARGV`, with where they come from.
+    ///
+    /// The `n`-th entry is `KEYS[n]` if `n` is less than `keys_count()`, and `ARGV[n - keys_count()]` otherwise,
+    /// counting from 1. The order follows the order in which the values first appear in each script, and then the
+    /// order of the joined scripts, regardless of the order of the builder methods. The keys passed by
+    /// `with_keys` are named `KEYS`.
+    fn arg_layout(&self) -> Vec<(&'static str, ArgSource)> {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+
+        let mut keys = Vec::new();
+        let mut argv = Vec::new();
+
+        for i in &info {
+            match i.kind {
+                Kind::Body => {
+                    let names = i.keys.iter().chain(i.args.iter());
+                    for (n, lua) in names.enumerate() {
+                        let name = i.names.get(n).unwrap_or(lua);
+                        let source = i.sources.get(n).copied().unwrap_or(ArgSource::Argument);
+                        if n < i.keys.len() {
+                            keys.push((*name, source));
+                        } else {
+                            argv.push((*name, source));
+                        }
+                    }
+                }
+                Kind::Keys(len) => {
+                    keys.extend((0..len).map(|_| ("KEYS", ArgSource::Keys)));
+                }
+                Kind::Begin(_) | Kind::End | Kind::Prelude => {}
             }
         }
 
-        script += &format!("{}(function() {} {} end)();\n", prefix, init, info.body);
+        keys.extend(argv);
+        keys
+    }
+
+    /// The names of the scripts, e.g. `lua!(name = "transfer", ...)`, in the order they are joined.
+    ///
+    /// The scripts without names are skipped.
+    fn names(&self) -> Vec<&'static str> {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        info.iter().filter_map(|i| i.name).collect()
+    }
+
+    /// The number of `KEYS` passed to the script, including the keys passed by `with_keys`.
+    fn keys_count(&self) -> usize {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        info.iter()
+            .map(|i| match i.kind {
+                Kind::Body => i.keys.len(),
+                Kind::Keys(len) => len,
+                Kind::Begin(_) | Kind::End | Kind::Prelude => 0,
+            })
+            .sum()
+    }
+
+    /// The identity of the script in the cache of the generated scripts.
+    ///
+    /// Derived from the pointers of the script information, or the keys given by `lua!(cache_key = "...", ...)`.
+    fn cache_key(&self) -> u64 {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        let mut hasher = DefaultHasher::new();
+        CacheKey::new(&info, &args).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The SHA1 digest of the generated script.
+    fn sha1(&self) -> String {
+        self.prepare().get_hash().into()
+    }
+
+    /// Load the script into the script cache of the server without executing it.
+    ///
+    /// Returns the SHA1 digest of the script.
+    fn load(&self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<String> {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        load_cmd(&info, &args).query(con)
+    }
+
+    /// Load the script into the script cache of the server asynchronously.
+    fn load_async<'a, C>(&self, con: &'a mut C) -> redis::RedisFuture<'a, String>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        let cmd = load_cmd(&info, &args);
+        async move { cmd.query_async(con).await }.boxed()
+    }
+
+    /// Register the script as the function `name` of the library `library` by `FUNCTION LOAD`.
+    ///
+    /// The library is replaced if it already exists. The function is called by `fcall`
+    /// with the same shape of the keys and the arguments. Requires Redis 7.
+    fn register(
+        &self,
+        con: &mut dyn redis::ConnectionLike,
+        library: &str,
+        name: &str,
+    ) -> redis::RedisResult<()> {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        function_load_cmd(&info, &args, library, name).query(con)
+    }
+
+    /// Register the script as a function asynchronously.
+    fn register_async<'a, C>(
+        &self,
+        con: &'a mut C,
+        library: &str,
+        name: &str,
+    ) -> redis::RedisFuture<'a, ()>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        let cmd = function_load_cmd(&info, &args, library, name);
+        async move { cmd.query_async(con).await }.boxed()
+    }
+
+    /// Invoke the function `name` registered by `register` by `FCALL` instead of `EVAL`.
+    fn fcall<T>(self, con: &mut dyn redis::ConnectionLike, name: &str) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        fcall_cmd(&args, name).query(con)
+    }
+
+    /// Invoke the function registered by `register` asynchronously.
+    fn fcall_async<'a, C, T>(self, con: &'a mut C, name: &str) -> redis::RedisFuture<'a, T>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        T: redis::FromRedisValue + Send,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        let cmd = fcall_cmd(&args, name);
+        async move { cmd.query_async(con).await }.boxed()
+    }
+
+    /// Invoke the script.
+    fn invoke<T>(self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+
+        if is_readonly(&info) {
+            return invoke_cmd(con, &info, &args, &ScriptOptions::default())
+                .and_then(|value| T::from_redis_value(&value));
+        }
+
+        #[cfg(feature = "log")]
+        let start = std::time::Instant::now();
+
+        let script = gen_script(&info, &args);
+        let mut invoke = script.prepare_invoke();
+        for wr in &args {
+            if wr.is_key() {
+                invoke.key(wr);
+            } else {
+                invoke.arg(wr);
+            }
+        }
+        let res = invoke
+            .invoke(con)
+            .map_err(|e| locate_error(&info, &args, e));
+
+        #[cfg(feature = "log")]
+        log_invoke(&info, &args, start, &res);
+
+        res
+    }
+
+    /// Invoke the script by `EVALSHA` with the digest given, e.g. by `load`, falling back to `EVAL` only on `NOSCRIPT`.
+    ///
+    /// The script is neither generated nor hashed unless it's not cached by the server.
+    /// The digest has to be of this script; the server runs whatever script has the digest.
+    fn invoke_sha<T>(self, con: &mut dyn redis::ConnectionLike, sha: &str) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+
+        #[cfg(feature = "log")]
+        let start = std::time::Instant::now();
+
+        let res = match con.req_command(&evalsha_cmd_with(&info, sha, &args)) {
+            Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+                con.req_command(&eval_cmd(&info, &args))
+            }
+            res => res,
+        }
+        .map_err(|e| locate_error(&info, &args, e))
+        .and_then(|value| T::from_redis_value(&value));
+
+        #[cfg(feature = "log")]
+        log_invoke(&info, &args, start, &res);
+
+        res
+    }
+
+    /// Invoke the script on a connection of the concrete type without dynamic dispatch.
+    ///
+    /// Same as `invoke`, the script is sent by `EVALSHA` and then by `EVAL` if it's not cached yet.
+    fn invoke_with<C, T>(self, con: &mut C) -> redis::RedisResult<T>
+    where
+        C: redis::ConnectionLike,
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        let value = invoke_cmd(con, &info, &args, &ScriptOptions::default())?;
+        T::from_redis_value(&value)
+    }
+
+    /// Invoke the script with the options, e.g. the hook on the `NOSCRIPT` fallback.
+    fn invoke_with_options<T>(
+        self,
+        con: &mut dyn redis::ConnectionLike,
+        options: &ScriptOptions,
+    ) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        let value = invoke_cmd(con, &info, &args, options)?;
+        T::from_redis_value(&value)
+    }
+
+    /// Invoke the script retrying on transient errors, e.g. I/O errors, with backoff.
+    ///
+    /// The errors of the script are returned without retries. See `RetryPolicy` for the errors retried.
+    fn invoke_retry<T>(
+        self,
+        con: &mut dyn redis::ConnectionLike,
+        policy: RetryPolicy,
+    ) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized + Clone,
+    {
+        let mut attempt = 0;
+        loop {
+            match self.clone().invoke(con) {
+                Err(e) => match policy.retry(attempt, &e) {
+                    Some(wait) => std::thread::sleep(wait),
+                    None => return Err(e),
+                },
+                res => return res,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Invoke the script returning the reply as is, e.g. to branch on the shape of the reply.
+    fn invoke_value(self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<redis::Value>
+    where
+        Self: Sized,
+    {
+        self.invoke(con)
+    }
+
+    /// Invoke the script returning whether the reply is truthy, e.g. for `return 1` or `return true`.
+    ///
+    /// Unlike `invoke::<bool>`, which rejects the other strings and tables, `nil`, `false`, `0`, `"0"`,
+    /// the empty string and the empty table are `false`, and any other values are `true`.
+    fn invoke_bool(self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<bool>
+    where
+        Self: Sized,
+    {
+        self.invoke_value(con).map(|value| is_truthy(&value))
+    }
+
+    /// Invoke the script collecting the returned table into a map.
+    ///
+    /// The table is either flat key-value pairs, e.g. `{k1, v1, k2, v2}`, or an array of pairs,
+    /// e.g. `{{k1, v1}, {k2, v2}}`. Redis drops the non-array part of tables, so a table like `{k1 = v1}`
+    /// has to be converted into either of them, or encoded by `cjson.encode` for `invoke_json`.
+    fn invoke_map<K, V>(
+        self,
+        con: &mut dyn redis::ConnectionLike,
+    ) -> redis::RedisResult<HashMap<K, V>>
+    where
+        K: redis::FromRedisValue + Eq + std::hash::Hash,
+        V: redis::FromRedisValue,
+        Self: Sized,
+    {
+        match self.invoke_value(con)? {
+            redis::Value::Bulk(items) if is_pairs(&items) => items
+                .iter()
+                .map(redis::FromRedisValue::from_redis_value)
+                .collect(),
+            value => redis::FromRedisValue::from_redis_value(&value),
+        }
+    }
+
+    /// Invoke the script decoding the returned table of tables, e.g. `{{a, b}, {c}}`, into rows.
+    ///
+    /// Unlike `invoke::<Vec<Vec<T>>>`, which decodes a row which isn't a table as a row of one value
+    /// and `false` as an empty row, it fails with the index of the first row which isn't a table.
+    /// The rows may differ in length.
+    fn invoke_nested<T>(
+        self,
+        con: &mut dyn redis::ConnectionLike,
+    ) -> redis::RedisResult<Vec<Vec<T>>>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let rows = match self.invoke_value(con)? {
+            redis::Value::Bulk(rows) => rows,
+            value => {
+                return Err(redis::RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "expected a table of tables",
+                    format!("the script returned {:?}", value),
+                )))
+            }
+        };
+
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| match row {
+                redis::Value::Bulk(_) => redis::FromRedisValue::from_redis_value(row),
+                row => Err(redis::RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "expected a table of tables",
+                    format!("row {} is not a table but {:?}", index + 1, row),
+                ))),
+            })
+            .collect()
+    }
+
+    /// Invoke the script returning the time it took on the server with the return value.
+    ///
+    /// The time is measured by `TIME` before and after the script, so it excludes the network and the queueing.
+    /// Redis before 5 refuses writes after `TIME` unless the script replicates the effects
+    /// by `redis.replicate_commands()`.
+    fn invoke_timed<T>(
+        self,
+        con: &mut dyn redis::ConnectionLike,
+    ) -> redis::RedisResult<(T, std::time::Duration)>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        from_timed(ScriptTimed(self).invoke_value(con)?)
+    }
+
+    /// Invoke the script returning `None` if the script returns `nil` or `false`.
+    ///
+    /// Redis converts both Lua `nil` and `false` to a nil reply, so they can't be distinguished.
+    /// Other values including `0` and the empty string are `Some`.
+    fn invoke_optional<T>(
+        self,
+        con: &mut dyn redis::ConnectionLike,
+    ) -> redis::RedisResult<Option<T>>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        self.invoke(con)
+    }
+
+    /// Invoke the script asynchronously failing with `ScriptError::Timeout` if it doesn't finish in time.
+    ///
+    /// Only the wait on the client side is cancelled; the script keeps running on the server.
+    /// Use `script_kill` to abort it.
+    #[cfg(feature = "tokio")]
+    fn invoke_async_timeout<'a, C, T>(
+        self,
+        con: &'a mut C,
+        timeout: std::time::Duration,
+    ) -> futures::future::BoxFuture<'a, Result<T, ScriptError>>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        T: redis::FromRedisValue + Send,
+        Self: Sized + Send + 'a,
+    {
+        async move {
+            match tokio1::time::timeout(timeout, self.invoke_async(con)).await {
+                Ok(res) => Ok(res?),
+                Err(_) => Err(ScriptError::Timeout),
+            }
+        }
+        .boxed()
+    }
+
+    /// Invoke the script asynchronously on a clone of the multiplexed connection.
+    ///
+    /// The clone shares the underlying connection, so the future doesn't borrow the connection,
+    /// e.g. to spawn it as a task or to invoke scripts concurrently.
+    fn invoke_async_multiplexed<T>(
+        self,
+        con: &redis::aio::MultiplexedConnection,
+    ) -> redis::RedisFuture<'static, T>
+    where
+        T: redis::FromRedisValue + Send,
+        Self: Sized + Send + 'static,
+    {
+        let mut con = con.clone();
+        async move { self.invoke_async(&mut con).await }.boxed()
+    }
+
+    /// Invoke the script parsing the error reply of the script as the user error.
+    fn invoke_typed<E, T>(self, con: &mut dyn redis::ConnectionLike) -> Result<T, ScriptError<E>>
+    where
+        E: std::str::FromStr,
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        self.invoke(con).map_err(ScriptError::from_redis)
+    }
+
+    /// Invoke the script separating the errors of the script from the other errors.
+    ///
+    /// The error replies including the Lua runtime errors fail with `ScriptError::Unmatched`,
+    /// the returned value which can't be decoded as `T` with `ScriptError::Decode`,
+    /// and the others, e.g. connection errors, with `ScriptError::Redis`.
+    fn invoke_err<T>(self, con: &mut dyn redis::ConnectionLike) -> Result<T, ScriptError>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let value: redis::Value = self.invoke(con).map_err(ScriptError::from_reply)?;
+        T::from_redis_value(&value).map_err(|e| ScriptError::Decode(e.to_string()))
+    }
+
+    /// Invoke the script with the keys and the arguments given positionally instead of the bound ones.
+    ///
+    /// `keys` are passed as `KEYS` and `args` as `ARGV` in the order of `arg_layout`, as is by `ToRedisArgs`,
+    /// so the values are never packed as tables. Fails with `ClientError` without sending anything
+    /// if the numbers of the values don't match the keys and the arguments of the script.
+    fn invoke_raw<T, K, A>(
+        self,
+        con: &mut dyn redis::ConnectionLike,
+        keys: &[K],
+        args: &[A],
+    ) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+        K: ToRedisArgs,
+        A: ToRedisArgs,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut bound = vec![];
+        self.info(&mut info, &mut bound);
+        invoke_raw(con, info, keys, args)
+    }
+
+    /// Invoke the script and deserialize the returned JSON string, e.g. `cjson.encode(result)`.
+    ///
+    /// Fails with `ScriptError::Decode` if the returned value is not valid JSON of `T`.
+    /// An empty object is decoded as an empty sequence as well, because `cjson` encodes an empty table as `{}`.
+    #[cfg(feature = "serde")]
+    fn invoke_json<T>(self, con: &mut dyn redis::ConnectionLike) -> Result<T, ScriptError>
+    where
+        T: serde::de::DeserializeOwned,
+        Self: Sized,
+    {
+        let res: Vec<u8> = self.invoke(con)?;
+        crate::json::from_slice(&res).map_err(|e| ScriptError::Decode(e.to_string()))
+    }
+
+    /// Invoke the script converting the returned value by `TryFrom`, e.g. a status string into an enum.
+    ///
+    /// Fails with `ScriptError::Convert` carrying the returned value if the conversion fails.
+    fn invoke_into<R>(self, con: &mut dyn redis::ConnectionLike) -> Result<R, ScriptError>
+    where
+        R: std::convert::TryFrom<redis::Value>,
+        R::Error: std::fmt::Display,
+        Self: Sized,
+    {
+        let value: redis::Value = self.invoke(con)?;
+        R::try_from(value.clone()).map_err(|e| ScriptError::Convert(value, e.to_string()))
+    }
+
+    /// Invoke the script on Redis Cluster.
+    ///
+    /// Redis Cluster routes the script by its keys, so it fails without sending the script
+    /// if no keys are given. All the keys have to be in the same hash slot, otherwise
+    /// Redis replies a `CROSSSLOT` error; use hash tags, e.g. `{user1}:a`, to put them together.
+    /// Same as `invoke`, read-only scripts are sent by `EVALSHA_RO` and `EVAL_RO`.
+    #[cfg(feature = "cluster")]
+    fn invoke_cluster<T>(self, con: &mut redis::cluster::ClusterConnection) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+        Self: Sized,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+        check_cluster_keys(&args)?;
+
+        let value = invoke_cmd(con, &info, &args, &ScriptOptions::default())?;
+        T::from_redis_value(&value)
+    }
+
+    /// Invoke the script asynchronously on Redis Cluster, e.g. by a client which implements
+    /// `redis::aio::ConnectionLike` on the cluster.
+    ///
+    /// Same as `invoke_cluster`, it fails without sending the script if no keys are given.
+    #[cfg(feature = "cluster")]
+    fn invoke_cluster_async<'a, C, T>(self, con: &'a mut C) -> redis::RedisFuture<'a, T>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        T: redis::FromRedisValue + Send,
+        Self: Sized + Send + 'a,
+    {
+        let mut info = vec![];
+        let mut args = vec![];
+        self.info(&mut info, &mut args);
+
+        match check_cluster_keys(&args) {
+            Ok(()) => self.invoke_async(con),
+            Err(e) => async move { Err(e) }.boxed(),
+        }
+    }
+
+    /// Invoke the script asynchronously.
+    ///
+    /// The connection is borrowed mutably rather than moved, so pooled connections can be used as is,
+    /// e.g. `invoke_async(&mut *guard)` for a connection guard which dereferences to a connection.
+    fn invoke_async<'a, C, T>(self, con: &'a mut C) -> redis::RedisFuture<'a, T>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        T: redis::FromRedisValue + Send,
+        Self: Sized + Send + 'a,
+    {
+        async move {
+            let mut info = vec![];
+            let mut args = vec![];
+            self.info(&mut info, &mut args);
+
+            #[cfg(feature = "log")]
+            let start = std::time::Instant::now();
+
+            let script = gen_script(&info, &args);
+            let res = if is_readonly(&info) {
+                match con
+                    .req_packed_command(&evalsha_cmd(&info, &script, &args))
+                    .await
+                {
+                    Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+                        con.req_packed_command(&eval_cmd(&info, &args)).await
+                    }
+                    res => res,
+                }
+                .map_err(|e| locate_error(&info, &args, e))
+                .and_then(|value| T::from_redis_value(&value))
+            } else {
+                let mut invoke = script.prepare_invoke();
+                for wr in &args {
+                    if wr.is_key() {
+                        invoke.key(wr);
+                    } else {
+                        invoke.arg(wr);
+                    }
+                }
+                invoke
+                    .invoke_async(con)
+                    .await
+                    .map_err(|e| locate_error(&info, &args, e))
+            };
+
+            #[cfg(feature = "log")]
+            log_invoke(&info, &args, start, &res);
+
+            res
+        }
+        .boxed()
+    }
+}
+
+impl<S: Script + ?Sized> Script for Box<S> {
+    fn info(&self, infos: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        (**self).info(infos, args);
+    }
+}
+
+impl Script for () {
+    fn info(&self, _: &mut Vec<Info>, _: &mut Vec<ScriptArg>) {}
+}
+
+/// The scripts with the values given positionally by `invoke_raw`.
+struct RawScript {
+    info: Vec<Info>,
+    args: Vec<ScriptArg>,
+}
+
+impl Script for RawScript {
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.extend(self.info.iter().cloned());
+        args.extend(self.args.iter().cloned());
+    }
+}
+
+/// Invoke the scripts of `info` with the keys and the arguments given positionally,
+/// e.g. by `invoke_raw` of `lua!` before the arguments are bound.
+#[doc(hidden)]
+pub fn invoke_raw<T, K, A>(
+    con: &mut dyn redis::ConnectionLike,
+    info: Vec<Info>,
+    keys: &[K],
+    args: &[A],
+) -> redis::RedisResult<T>
+where
+    T: redis::FromRedisValue,
+    K: ToRedisArgs,
+    A: ToRedisArgs,
+{
+    let (keys_len, argv_len) = info.iter().fold((0, 0), |(k, a), i| match i.kind {
+        Kind::Body => (k + i.keys.len(), a + i.args.len()),
+        Kind::Keys(len) => (k + len, a),
+        Kind::Begin(_) | Kind::End | Kind::Prelude => (k, a),
+    });
+
+    let keys: Vec<_> = keys.iter().flat_map(|key| key.to_redis_args()).collect();
+    let argv: Vec<_> = args.iter().flat_map(|arg| arg.to_redis_args()).collect();
+    if keys.len() != keys_len || argv.len() != argv_len {
+        return Err(redis::RedisError::from((
+            redis::ErrorKind::ClientError,
+            "arguments mismatch",
+            format!(
+                "the script takes {} keys and {} arguments but {} keys and {} arguments are given",
+                keys_len,
+                argv_len,
+                keys.len(),
+                argv.len()
+            ),
+        )));
+    }
+
+    let args = keys
+        .into_iter()
+        .map(|key| raw_arg(key, true))
+        .chain(argv.into_iter().map(|arg| raw_arg(arg, false)))
+        .collect();
+    RawScript { info, args }.invoke(con)
+}
+
+/// Represents the set of two scripts which are joined.
+pub struct ScriptJoin<S, T>(S, T);
+
+impl<S, T> Script for ScriptJoin<S, T>
+where
+    S: Script,
+    T: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        self.0.info(info, args);
+        self.1.info(info, args);
+    }
+}
+
+/// Represents the script which runs only if the guard script returns a truthy value.
+#[derive(Clone, Debug)]
+pub struct ScriptThen<S, T>(S, T);
+
+impl<S, T> Script for ScriptThen<S, T>
+where
+    S: Script,
+    T: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::begin(Group::Then));
+        info.push(Info::begin(Group::Guard));
+        self.0.info(info, args);
+        info.push(Info::end());
+        self.1.info(info, args);
+        info.push(Info::end());
+    }
+}
+
+/// Represents the script which runs with the return value of the previous script as `@@prev`.
+#[derive(Clone, Debug)]
+pub struct ScriptAndThen<S, T>(S, T);
+
+impl<S, T> Script for ScriptAndThen<S, T>
+where
+    S: Script,
+    T: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::begin(Group::AndThen));
+        info.push(Info::begin(Group::Guard));
+        self.0.info(info, args);
+        info.push(Info::end());
+        self.1.info(info, args);
+        info.push(Info::end());
+    }
+}
+
+/// Represents the script which runs the given number of times with the windows of the values.
+#[derive(Clone, Debug)]
+pub struct ScriptRepeat<S> {
+    n: usize,
+    windows: Vec<S>,
+}
+
+impl<S> ScriptRepeat<S> {
+    /// Bind the values of the next run, e.g. `add.x(1).repeat(3).apply(add.x(2)).apply(add.x(3))`.
+    ///
+    /// The runs without their own values take the ones bound last. Panics if all the runs already have values.
+    pub fn apply(mut self, script: S) -> Self {
+        if self.windows.len() >= self.n {
+            panic!(
+                "Couldn't apply more than {} sets of values to the repeated script",
+                self.n
+            );
+        }
+        self.windows.push(script);
+        self
+    }
+}
+
+impl<S> Script for ScriptRepeat<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        // Each window is a `Guard` group, which returns the return value of the last script.
+        info.push(Info::begin(Group::Repeat(self.n)));
+        for i in 0..self.n {
+            let last = self.windows.len() - 1;
+            info.push(Info::begin(Group::Guard));
+            self.windows[i.min(last)].info(info, args);
+            info.push(Info::end());
+        }
+        info.push(Info::end());
+    }
+}
+
+/// Represents the scripts which return all the return values.
+#[derive(Clone, Debug)]
+pub struct ScriptCollect<S>(S);
+
+impl<S> Script for ScriptCollect<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::begin(Group::Collect));
+        self.0.info(info, args);
+        info.push(Info::end());
+    }
+}
+
+/// Represents the read-only script.
+#[derive(Clone, Debug)]
+pub struct ScriptReadonly<S>(S);
+
+impl<S> Script for ScriptReadonly<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        let begin = info.len();
+        self.0.info(info, args);
+        for i in &mut info[begin..] {
+            i.readonly = true;
+        }
+    }
+}
+
+/// Represents the script with the additional keys.
+#[derive(Clone, Debug)]
+pub struct ScriptWithKeys<S> {
+    keys: Vec<ScriptArg>,
+    script: S,
+}
+
+impl<S> Script for ScriptWithKeys<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::extra_keys(self.keys.len()));
+        args.extend(self.keys.iter().cloned());
+        self.script.info(info, args);
+    }
+}
+
+/// Represents the script whose keys share the hash tag.
+#[derive(Clone, Debug)]
+pub struct ScriptHashtag<S> {
+    tag: String,
+    invalid: Option<&'static str>,
+    script: S,
+}
+
+impl<S> Script for ScriptHashtag<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        let (info_begin, begin) = (info.len(), args.len());
+        self.script.info(info, args);
+
+        // Tables are packed by MessagePack, so the tag can't be put in front of them.
+        let invalid = self.invalid.or_else(|| {
+            args[begin..]
+                .iter()
+                .any(|arg| arg.is_key() && arg.pack())
+                .then_some("the hash tag can't be put on a table key")
+        });
+
+        match invalid {
+            Some(reason) => {
+                info.insert(info_begin, Info::begin(Group::Invalid(reason)));
+                info.push(Info::end());
+            }
+            None => {
+                for key in args[begin..].iter_mut().filter(|arg| arg.is_key()) {
+                    key_hashtag(key, &self.tag);
+                }
+            }
+        }
+    }
+}
+
+/// Represents the script which reads the keys passed by `with_keys` by hand.
+#[derive(Clone, Debug)]
+pub struct ScriptNumKeys<S> {
+    numkeys: usize,
+    script: S,
+}
+
+impl<S> Script for ScriptNumKeys<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        let mut inner = vec![];
+        self.script.info(&mut inner, args);
+
+        // The keys passed by `with_keys` come in front of the group, wherever `with_keys` is called.
+        let mut inner = inner.into_iter().peekable();
+        if let Some(keys) = inner.next_if(|i| matches!(i.kind, Kind::Keys(_))) {
+            info.push(keys);
+        }
+        info.push(Info::begin(Group::NumKeys(self.numkeys)));
+        info.extend(inner);
+        info.push(Info::end());
+    }
+}
+
+/// Represents the script which runs against another database.
+#[derive(Clone, Debug)]
+pub struct ScriptOnDb<S> {
+    db: i64,
+    script: S,
+}
+
+impl<S> Script for ScriptOnDb<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::begin(Group::Db(self.db)));
+        self.script.info(info, args);
+        info.push(Info::end());
+    }
+}
+
+/// Lua helpers shared by the scripts, defined by `lua_prelude!`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Prelude {
+    source: &'static str,
+}
+
+impl Prelude {
+    /// Create the prelude from the Lua source, which can't take arguments.
+    pub fn new(source: &'static str) -> Self {
+        Self { source }
+    }
+
+    /// The Lua source of the prelude.
+    pub fn source(&self) -> &'static str {
+        self.source
+    }
+}
+
+/// Represents the script which uses the Lua helpers of the prelude.
+#[derive(Clone, Debug)]
+pub struct ScriptPrelude<S> {
+    prelude: Prelude,
+    script: S,
+}
+
+impl<S> Script for ScriptPrelude<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::prelude(self.prelude.source));
+        self.script.info(info, args);
+    }
+}
+
+/// Represents the script which returns the elapsed time with the return value, for `Script::invoke_timed`.
+#[derive(Clone, Debug)]
+struct ScriptTimed<S>(S);
+
+impl<S> Script for ScriptTimed<S>
+where
+    S: Script,
+{
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::begin(Group::Timed));
+        self.0.info(info, args);
+        info.push(Info::end());
+    }
+}
+
+/// The name of a key or an argument with its value, e.g. for `Script::arg_pairs`.
+type ArgPair = (&'static str, Vec<Vec<u8>>);
+
+/// The keys and the arguments with their names grouped by the script information they belong to.
+fn pairs_by_info<'a>(info: &'a [Info], args: &[ScriptArg]) -> Vec<(&'a Info, Vec<ArgPair>)> {
+    let mut keys = args.iter().filter(|arg| arg.is_key());
+    let mut argv = args.iter().filter(|arg| !arg.is_key());
+    let mut groups = Vec::new();
+
+    for i in info {
+        let mut pairs = Vec::new();
+        match i.kind {
+            Kind::Body => {
+                // Falls back to the names in Lua if the names in Rust are not given.
+                let mut names = i.names.iter();
+                let vars = i.keys.iter().map(|key| (key, keys.next()));
+                let vars = vars.chain(i.args.iter().map(|arg| (arg, argv.next())));
+                for (lua, arg) in vars.collect::<Vec<_>>() {
+                    let name = names.next().unwrap_or(lua);
+                    pairs.push((
+                        *name,
+                        arg.map(|arg| arg.to_redis_args()).unwrap_or_default(),
+                    ));
+                }
+            }
+            Kind::Keys(len) => {
+                for key in keys.by_ref().take(len) {
+                    pairs.push(("KEYS", key.to_redis_args()));
+                }
+            }
+            Kind::Begin(_) | Kind::End | Kind::Prelude => {}
+        }
+        groups.push((i, pairs));
+    }
+
+    groups
+}
+
+/// A script of the joined scripts with the keys and the arguments passed to it.
+#[derive(Clone, Debug)]
+pub struct ScriptComponent {
+    info: Info,
+    args: Vec<ArgPair>,
+}
+
+impl ScriptComponent {
+    /// The script information, e.g. the body of the script.
+    pub fn info(&self) -> &Info {
+        &self.info
+    }
+
+    /// The name of the script, e.g. `lua!(name = "transfer", ...)`.
+    pub fn name(&self) -> Option<&'static str> {
+        self.info.name
+    }
+
+    /// The keys followed by the arguments passed to the script with their names in Rust.
+    pub fn args(&self) -> &[(&'static str, Vec<Vec<u8>>)] {
+        &self.args
+    }
+}
+
+/// The script and its arguments which would be sent to Redis.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptPlan {
+    source: String,
+    keys: Vec<Vec<u8>>,
+    args: Vec<Vec<u8>>,
+}
+
+impl ScriptPlan {
+    /// The generated script.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The values of `KEYS` in order.
+    pub fn keys(&self) -> &[Vec<u8>] {
+        &self.keys
+    }
+
+    /// The values of `ARGV` in order.
+    pub fn args(&self) -> &[Vec<u8>] {
+        &self.args
+    }
+
+    /// The number of keys.
+    pub fn num_keys(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+/// The metadata of a script returned by `Script::describe`.
+///
+/// Implements `Serialize` with the `serde` feature, e.g. to dump the catalog of the scripts as JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptDescriptor {
+    names: Vec<&'static str>,
+    args: Vec<&'static str>,
+    keys_count: usize,
+    source_len: usize,
+    sha1: String,
+}
+
+impl ScriptDescriptor {
+    /// The names of the scripts, same as `Script::names`.
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+
+    /// The names of the keys followed by the arguments, same as `Script::arg_layout`.
+    pub fn args(&self) -> &[&'static str] {
+        &self.args
+    }
+
+    /// The number of `KEYS`, same as `Script::keys_count`.
+    pub fn keys_count(&self) -> usize {
+        self.keys_count
+    }
+
+    /// The length of the generated script in bytes.
+    pub fn source_len(&self) -> usize {
+        self.source_len
+    }
+
+    /// The SHA1 digest of the generated script, same as `Script::sha1`.
+    pub fn sha1(&self) -> &str {
+        &self.sha1
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ScriptDescriptor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("ScriptDescriptor", 5)?;
+        s.serialize_field("names", &self.names)?;
+        s.serialize_field("args", &self.args)?;
+        s.serialize_field("keys_count", &self.keys_count)?;
+        s.serialize_field("source_len", &self.source_len)?;
+        s.serialize_field("sha1", &self.sha1)?;
+        s.end()
+    }
+}
+
+/// Take another script as the inner of the script.
+pub trait TakeScript<I> {
+    type Item;
+
+    /// Take the inner script.
+    fn take(self, inner: I) -> Self::Item;
+}
+
+/// Identity of a script information.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum InfoKey {
+    /// The kind, the pointers and the lengths of the strings.
+    Ptr(
+        Kind,
+        usize,
+        usize,
+        usize,
+        usize,
+        usize,
+        Option<(usize, usize)>,
+    ),
+    /// The key given by the user.
+    Custom(Kind, &'static str),
+    /// The text completed at runtime with the pointers of the other strings.
+    Text(Kind, String, String, usize, usize, Option<(usize, usize)>),
+}
+
+/// Identity of a generated script.
+///
+/// The script information generated by proc-macro mostly consists of `&'static str`,
+/// so the pointers identify the script, except for the text completed at runtime, e.g. by `lua_fmt!`. Packed arguments change the generated script
+/// so they are part of the identity too.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    info: Vec<InfoKey>,
+    pack: Vec<(bool, bool)>,
+}
+
+impl CacheKey {
+    fn new(info: &[Info], args: &[ScriptArg]) -> Self {
+        Self {
+            info: info
+                .iter()
+                .map(|info| match (info.cache_key, &info.script, &info.body) {
+                    (Some(key), _, _) => InfoKey::Custom(info.kind, key),
+                    (None, Text::Owned(script), Text::Owned(body)) => InfoKey::Text(
+                        info.kind,
+                        script.to_string(),
+                        body.to_string(),
+                        info.args.as_ptr() as usize,
+                        info.keys.as_ptr() as usize,
+                        info.name.map(|name| (name.as_ptr() as usize, name.len())),
+                    ),
+                    (None, _, _) => InfoKey::Ptr(
+                        info.kind,
+                        info.script.as_ptr() as usize,
+                        info.body.as_ptr() as usize,
+                        info.body.len(),
+                        info.args.as_ptr() as usize,
+                        info.keys.as_ptr() as usize,
+                        info.name.map(|name| (name.as_ptr() as usize, name.len())),
+                    ),
+                })
+                .collect(),
+            pack: args.iter().map(|arg| (arg.is_key(), arg.pack())).collect(),
+        }
+    }
+}
+
+/// Generated scripts, with the length of the source, which are reused across invocations.
+fn cache() -> &'static Mutex<HashMap<CacheKey, (redis::Script, usize)>> {
+    static CACHE: OnceLock<Mutex<HashMap<CacheKey, (redis::Script, usize)>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// The default limit of the length of the generated script for `try_gen_script`, 64 KiB.
+pub const DEFAULT_MAX_SCRIPT_LEN: usize = 64 * 1024;
+
+/// Generate a script from a list of script information.
+///
+/// The generated script is cached, so invoking the same script repeatedly
+/// doesn't regenerate the script nor recompute its hash.
+pub fn gen_script(info: &[Info], args: &[ScriptArg]) -> redis::Script {
+    gen_script_len(info, args).0
+}
+
+/// Generate a script from a list of script information, failing if the source is longer than `max_len` bytes,
+/// e.g. `DEFAULT_MAX_SCRIPT_LEN`.
+///
+/// It guards against sending a huge script by `EVAL`, e.g. by joining scripts in a loop by mistake.
+pub fn try_gen_script(
+    info: &[Info],
+    args: &[ScriptArg],
+    max_len: usize,
+) -> redis::RedisResult<redis::Script> {
+    let (script, len) = gen_script_len(info, args);
+    if len > max_len {
+        return Err(redis::RedisError::from((
+            redis::ErrorKind::ClientError,
+            "script too large",
+            format!("{} bytes exceeds the limit of {} bytes", len, max_len),
+        )));
+    }
+    Ok(script)
+}
+
+/// Generate a script with the length of the source.
+fn gen_script_len(info: &[Info], args: &[ScriptArg]) -> (redis::Script, usize) {
+    let key = CacheKey::new(info, args);
+
+    if let Some(script) = cache().lock().unwrap().get(&key) {
+        return script.clone();
+    }
+
+    let source = gen_source(info, args);
+    let script = (redis::Script::new(&source), source.len());
+    // The scripts completed at runtime may be made from any values, so they would grow the cache without limit.
+    if !info.iter().any(|i| matches!(i.body, Text::Owned(_))) {
+        cache().lock().unwrap().insert(key, script.clone());
+    }
+    script
+}
+
+/// Abort the script running on the server by `SCRIPT KILL`.
+///
+/// Redis refuses to kill the script once it has performed a write.
+pub fn script_kill(con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<()> {
+    redis::cmd("SCRIPT").arg("KILL").query(con)
+}
+
+/// Abort the script running on the server by `SCRIPT KILL` asynchronously.
+///
+/// Redis refuses to kill the script once it has performed a write.
+pub fn script_kill_async<C>(con: &mut C) -> redis::RedisFuture<'_, ()>
+where
+    C: redis::aio::ConnectionLike + Send,
+{
+    async move { redis::cmd("SCRIPT").arg("KILL").query_async(con).await }.boxed()
+}
+
+/// Load the scripts into the script cache of the server at once, e.g. to warm the cache on startup.
+///
+/// The `SCRIPT LOAD` commands are sent in a single pipeline. Returns the SHA1 digests by the names of the scripts,
+/// which are joined by `+` for joined scripts. The scripts without names are loaded but not returned.
+pub fn load_all_async<'a, 's, I, S, C>(
+    scripts: I,
+    con: &'a mut C,
+) -> redis::RedisFuture<'a, HashMap<String, String>>
+where
+    I: IntoIterator<Item = &'s S>,
+    S: Script + ?Sized + 's,
+    C: redis::aio::ConnectionLike + Send,
+{
+    let mut names = vec![];
+    let mut pipe = redis::pipe();
+    for script in scripts {
+        let mut info = vec![];
+        let mut args = vec![];
+        script.info(&mut info, &mut args);
+        let name: Vec<_> = info.iter().filter_map(|i| i.name).collect();
+        names.push(name.join("+"));
+        pipe.add_command(load_cmd(&info, &args));
+    }
+
+    async move {
+        if names.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let shas: Vec<String> = pipe.query_async(con).await?;
+        Ok(names
+            .into_iter()
+            .zip(shas)
+            .filter(|(name, _)| !name.is_empty())
+            .collect())
+    }
+    .boxed()
+}
+
+/// The return value and the elapsed time of the script of `ScriptTimed`, e.g. `{12, value}`.
+fn from_timed<T>(value: redis::Value) -> redis::RedisResult<(T, std::time::Duration)>
+where
+    T: redis::FromRedisValue,
+{
+    match value {
+        redis::Value::Bulk(items) => match items.as_slice() {
+            [redis::Value::Int(micros), rest @ ..] if rest.len() <= 1 => Ok((
+                redis::FromRedisValue::from_redis_value(
+                    rest.first().unwrap_or(&redis::Value::Nil),
+                )?,
+                std::time::Duration::from_micros(*micros as u64),
+            )),
+            _ => Err((redis::ErrorKind::TypeError, "expected the elapsed time").into()),
+        },
+        _ => Err((redis::ErrorKind::TypeError, "expected the elapsed time").into()),
+    }
+}
+
+/// Whether the reply is an array of pairs, e.g. `{{k1, v1}, {k2, v2}}`, for `invoke_map`.
+fn is_pairs(items: &[redis::Value]) -> bool {
+    !items.is_empty()
+        && items
+            .iter()
+            .all(|item| matches!(item, redis::Value::Bulk(pair) if pair.len() == 2))
+}
+
+/// Whether the reply of the script is truthy for `invoke_bool`.
+fn is_truthy(value: &redis::Value) -> bool {
+    match value {
+        redis::Value::Nil => false,
+        redis::Value::Int(n) => *n != 0,
+        redis::Value::Data(data) => !data.is_empty() && data != b"0",
+        redis::Value::Bulk(values) => !values.is_empty(),
+        redis::Value::Status(_) | redis::Value::Okay => true,
+    }
+}
+
+/// Generate the `SCRIPT LOAD` command for a list of script information.
+pub(crate) fn load_cmd(info: &[Info], args: &[ScriptArg]) -> redis::Cmd {
+    let mut cmd = redis::cmd("SCRIPT");
+    cmd.arg("LOAD").arg(gen_source(info, args));
+    cmd
+}
+
+/// Generate the library code which registers the script as a function for `FUNCTION LOAD`.
+///
+/// The body takes `KEYS` and `ARGV` as the parameters, so the generated script runs unchanged.
+pub fn gen_function(info: &[Info], args: &[ScriptArg], library: &str, name: &str) -> String {
+    format!(
+        "#!lua name={}\nredis.register_function('{}', function(KEYS, ARGV)\n{}end)\n",
+        library,
+        name,
+        gen_source(info, args)
+    )
+}
+
+/// Generate the `FUNCTION LOAD` command for a list of script information.
+fn function_load_cmd(info: &[Info], args: &[ScriptArg], library: &str, name: &str) -> redis::Cmd {
+    let mut cmd = redis::cmd("FUNCTION");
+    cmd.arg("LOAD")
+        .arg("REPLACE")
+        .arg(gen_function(info, args, library, name));
+    cmd
+}
+
+/// Generate the `FCALL` command passing the keys and the arguments same as `EVAL`.
+fn fcall_cmd(args: &[ScriptArg], name: &str) -> redis::Cmd {
+    let mut cmd = redis::cmd("FCALL");
+    cmd.arg(name);
+    push_args(&mut cmd, args);
+    cmd
+}
+
+/// Generate the `EVAL` command sending the entire script.
+///
+/// Unlike `EVALSHA`, it doesn't rely on the script cache, e.g. inside `MULTI`
+/// where the `NOSCRIPT` error can't be retried.
+pub(crate) fn eval_cmd(info: &[Info], args: &[ScriptArg]) -> redis::Cmd {
+    let mut cmd = redis::cmd(if is_readonly(info) { "EVAL_RO" } else { "EVAL" });
+    cmd.arg(gen_source(info, args));
+    push_args(&mut cmd, args);
+    cmd
+}
+
+/// Invoke the script by `EVALSHA` falling back to `EVAL` if the script is not cached.
+fn invoke_cmd<C>(
+    con: &mut C,
+    info: &[Info],
+    args: &[ScriptArg],
+    options: &ScriptOptions,
+) -> redis::RedisResult<redis::Value>
+where
+    C: redis::ConnectionLike + ?Sized,
+{
+    let script = match options.max_len() {
+        Some(max_len) => try_gen_script(info, args, max_len)?,
+        None => gen_script(info, args),
+    };
+
+    #[cfg(feature = "log")]
+    let start = std::time::Instant::now();
+
+    let res = match con.req_command(&evalsha_cmd(info, &script, args)) {
+        Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+            let names: Vec<_> = info.iter().filter_map(|i| i.name).collect();
+            options.noscript(&names, script.get_hash());
+            con.req_command(&eval_cmd(info, args))
+        }
+        res => res,
+    }
+    .map_err(|e| locate_error(info, args, e));
+
+    #[cfg(feature = "log")]
+    log_invoke(info, args, start, &res);
+
+    if options.recording() {
+        options.record(ScriptRecord::new(info, args, &res));
+    }
+    res
+}
+
+/// Generate the `EVALSHA` command which invokes the cached script.
+pub(crate) fn evalsha_cmd(info: &[Info], script: &redis::Script, args: &[ScriptArg]) -> redis::Cmd {
+    evalsha_cmd_with(info, script.get_hash(), args)
+}
+
+/// Generate the `EVALSHA` command which invokes the cached script by the digest.
+fn evalsha_cmd_with(info: &[Info], sha: &str, args: &[ScriptArg]) -> redis::Cmd {
+    let mut cmd = redis::cmd(if is_readonly(info) {
+        "EVALSHA_RO"
+    } else {
+        "EVALSHA"
+    });
+    cmd.arg(sha);
+    push_args(&mut cmd, args);
+    cmd
+}
+
+/// Log the invocation of the script, and the error if it fails.
+#[cfg(feature = "log")]
+fn log_invoke<T>(
+    info: &[Info],
+    args: &[ScriptArg],
+    start: std::time::Instant,
+    res: &redis::RedisResult<T>,
+) {
+    let elapsed = start.elapsed();
+    let names: Vec<_> = info.iter().filter_map(|i| i.name).collect();
+    let script = gen_script(info, args);
+    let keys = args.iter().filter(|arg| arg.is_key()).count();
+    let argv = args.len() - keys;
+
+    match res {
+        Ok(_) => log::debug!(
+            target: "redis_lua",
+            "invoked script: names={:?} sha={} keys={} args={} elapsed={:?}",
+            names,
+            script.get_hash(),
+            keys,
+            argv,
+            elapsed
+        ),
+        Err(e) => log::warn!(
+            target: "redis_lua",
+            "script failed: names={:?} sha={} keys={} args={} elapsed={:?} error={}",
+            names,
+            script.get_hash(),
+            keys,
+            argv,
+            elapsed,
+            e
+        ),
+    }
+}
+
+/// Fail if no keys are given, by which Redis Cluster routes the script.
+#[cfg(feature = "cluster")]
+fn check_cluster_keys(args: &[ScriptArg]) -> redis::RedisResult<()> {
+    if args.iter().any(|arg| arg.is_key()) {
+        return Ok(());
+    }
+
+    Err(redis::RedisError::from((
+        redis::ErrorKind::ClientError,
+        "script has no keys",
+        "Redis Cluster routes the script by its keys".to_string(),
+    )))
+}
+
+/// Whether all the joined scripts are read-only.
+fn is_readonly(info: &[Info]) -> bool {
+    let mut bodies = info.iter().filter(|i| i.kind == Kind::Body).peekable();
+    bodies.peek().is_some() && bodies.all(|i| i.readonly)
+}
+
+/// Push the number of keys, the keys and the arguments to the command.
+fn push_args(cmd: &mut redis::Cmd, args: &[ScriptArg]) {
+    let (keys, argv): (Vec<_>, Vec<_>) = args.iter().partition(|arg| arg.is_key());
+    let keys: Vec<_> = keys.iter().flat_map(|key| key.to_redis_args()).collect();
+    cmd.arg(keys.len()).arg(keys).arg(argv);
+}
+
+/// Generate a script from a list of script information with its source minified.
+///
+/// The generated script is not cached.
+pub fn gen_script_minified(info: &[Info], args: &[ScriptArg]) -> redis::Script {
+    redis::Script::new(&minify(&gen_source(info, args)))
+}
+
+/// Generate the source of a script from a list of script information.
+///
+/// The source is minified with the `minify` feature.
+pub(crate) fn gen_source(info: &[Info], args: &[ScriptArg]) -> String {
+    let source = gen_source_raw(info, args);
+
+    if cfg!(feature = "minify") {
+        minify(&source)
+    } else {
+        source
+    }
+}
+
+fn gen_source_raw(info: &[Info], args: &[ScriptArg]) -> String {
+    gen_source_mapped(info, args).0
+}
+
+/// Generate the source of a script with the lines which come from the script bodies.
+fn gen_source_mapped(info: &[Info], args: &[ScriptArg]) -> (String, Vec<Mapping>) {
+    if cfg!(debug_assertions) {
+        assert_args(info, args);
+    }
+
+    if let Some(reason) = invalid(info) {
+        return (
+            format!("return redis.error_reply(\"{}\");\n", reason),
+            vec![],
+        );
+    }
+
+    let mut gen = Gen::new(info, args);
+    let exprs = gen.exprs(&mut info.iter());
+
+    if runs_after_db(info) {
+        return (
+            "return redis.error_reply(\"the scripts joined after on_db have to select the database by on_db\");\n"
+                .into(),
+            vec![],
+        );
+    }
+
+    // The unit script, e.g. `()`, does nothing.
+    if exprs.is_empty() {
+        return ("return nil;\n".into(), vec![]);
+    }
+
+    // Generate the joined script preceded by the preludes and the shared functions.
+    // `@@prev` is `nil` outside of `and_then`.
+    let mut script = String::new();
+    if info
+        .iter()
+        .any(|i| i.kind == Kind::Body && i.body.contains(PREV))
+    {
+        script += &format!("local {}\n", PREV);
+    }
+    for prelude in &gen.preludes {
+        script += &format!("{}\n", prelude);
+    }
+    for def in &gen.defs {
+        script += &format!("{}\n", def);
+    }
+    let last = exprs.len() - 1;
+    for (index, expr) in exprs.iter().enumerate() {
+        let prefix = if index == last { "return " } else { "" };
+
+        script += &format!("{}{};\n", prefix, expr);
+    }
+
+    // The bodies appear in the order of the shared functions followed by the expressions.
+    let mut mappings = Vec::new();
+    let mut offset = 0;
+    for (body, location) in gen.def_bodies.iter().chain(&gen.expr_bodies) {
+        let begin = match script[offset..].find(body.as_str()) {
+            Some(begin) => offset + begin,
+            None => continue,
+        };
+        offset = begin + body.len();
+
+        if let Some((file, line)) = location {
+            mappings.push(Mapping {
+                begin: script[..begin].matches('\n').count() + 1,
+                len: body.matches('\n').count() + 1,
+                file,
+                line: *line,
+            });
+        }
+    }
+
+    (script, mappings)
+}
+
+/// Panic if the number of the keys and the arguments differs from the ones declared by the script information,
+/// e.g. by `Script::info` implemented by hand, which would let the scripts read the wrong `KEYS` and `ARGV`.
+fn assert_args(info: &[Info], args: &[ScriptArg]) {
+    let declared_keys: usize = info
+        .iter()
+        .map(|i| match i.kind {
+            Kind::Keys(len) => len,
+            _ => i.keys.len(),
+        })
+        .sum();
+    let declared_args: usize = info.iter().map(|i| i.args.len()).sum();
+    let bound_keys = args.iter().filter(|arg| arg.is_key()).count();
+    let bound_args = args.len() - bound_keys;
+
+    if (declared_keys, declared_args) == (bound_keys, bound_args) {
+        return;
+    }
+
+    let declared: Vec<_> = info
+        .iter()
+        .filter(|i| i.kind == Kind::Body)
+        .map(|i| {
+            format!(
+                "{}(keys: {:?}, args: {:?})",
+                i.name.unwrap_or("script"),
+                i.keys,
+                i.args
+            )
+        })
+        .collect();
+    panic!(
+        "the scripts declare {} keys and {} arguments but {} keys and {} arguments are bound: {}",
+        declared_keys,
+        declared_args,
+        bound_keys,
+        bound_args,
+        declared.join(", ")
+    );
+}
+
+/// The reason why the script can't be run, e.g. a hash tag on a table key.
+fn invalid(info: &[Info]) -> Option<&'static str> {
+    info.iter().find_map(|i| match i.kind {
+        Kind::Begin(Group::Invalid(reason)) => Some(reason),
+        _ => None,
+    })
+}
+
+/// Whether any script runs after `on_db` outside of `on_db`, i.e. against the database of the connection
+/// which can't be selected back.
+fn runs_after_db(info: &[Info]) -> bool {
+    let mut groups = Vec::new();
+    let mut selected = false;
+
+    for i in info {
+        let on_db = groups.iter().any(|g| matches!(g, Group::Db(_)));
+        match i.kind {
+            Kind::Body if selected && !on_db => return true,
+            Kind::Begin(group) => groups.push(group),
+            Kind::End => {
+                if let Some(Group::Db(_)) = groups.pop() {
+                    selected |= !groups.iter().any(|g| matches!(g, Group::Db(_)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// The lines of the generated script which come from a script body in Rust.
+#[derive(Clone, Copy, Debug)]
+struct Mapping {
+    /// The first line in the generated script.
+    begin: usize,
+    /// The number of lines.
+    len: usize,
+    /// The file of the body in Rust.
+    file: &'static str,
+    /// The line of the first line of the body in Rust.
+    line: u32,
+}
+
+impl Mapping {
+    fn locate(&self, line: usize) -> Option<(&'static str, u32)> {
+        if (self.begin..self.begin + self.len).contains(&line) {
+            Some((self.file, self.line + (line - self.begin) as u32))
+        } else {
+            None
+        }
+    }
+}
+
+/// The lines of the script sent to Redis which come from the script bodies.
+///
+/// Minifying joins the lines, so nothing is mapped with the `minify` feature.
+fn source_map(info: &[Info], args: &[ScriptArg]) -> Vec<Mapping> {
+    if cfg!(feature = "minify") {
+        return vec![];
+    }
+    gen_source_mapped(info, args).1
+}
+
+/// Append the file and the line in Rust to the lines of the script in the error, e.g. `user_script:3`.
+pub(crate) fn locate_error(
+    info: &[Info],
+    args: &[ScriptArg],
+    e: redis::RedisError,
+) -> redis::RedisError {
+    const MARKER: &str = "user_script:";
+
+    let detail = match (e.kind(), e.detail()) {
+        (redis::ErrorKind::ResponseError, Some(detail)) if detail.contains(MARKER) => {
+            detail.to_string()
+        }
+        _ => return e,
+    };
+    let mappings = source_map(info, args);
+
+    let mut located = String::with_capacity(detail.len());
+    let mut rest = detail.as_str();
+    while let Some(begin) = rest.find(MARKER) {
+        let (head, tail) = rest.split_at(begin + MARKER.len());
+        let end = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (digits, tail) = tail.split_at(end);
+
+        located += head;
+        located += digits;
+        let location = digits
+            .parse()
+            .ok()
+            .and_then(|line| mappings.iter().find_map(|m| m.locate(line)));
+        if let Some((file, line)) = location {
+            located += &format!(" ({}:{})", file, line);
+        }
+        rest = tail;
+    }
+    located += rest;
+
+    if located == detail {
+        return e;
+    }
+    redis::RedisError::from((
+        redis::ErrorKind::ResponseError,
+        "An error was signalled by the server",
+        located,
+    ))
+}
+
+/// The distinct indices of `ARGV[n]` read by the script.
+fn argv_indices(script: &str) -> std::collections::BTreeSet<usize> {
+    script
+        .match_indices("ARGV[")
+        .filter_map(|(begin, m)| {
+            let rest = &script[begin + m.len()..];
+            rest[..rest.find(']')?].parse().ok()
+        })
+        .collect()
+}
+
+/// The prefix of the Lua locals generated by the crate, which is reserved.
+///
+/// The scripts must not use names with the prefix, so that they never collide with the generated ones.
+const LOCAL_PREFIX: &str = "__redis_lua_";
+
+/// The Lua local of `@@prev`, the return value of the previous script of `and_then`.
+const PREV: &str = "__redis_lua_prev";
+
+/// Identity of a script body, same as `CacheKey`.
+type BodyId = (usize, usize, usize, usize);
+
+fn body_id(info: &Info) -> BodyId {
+    (
+        info.body.as_ptr() as usize,
+        info.body.len(),
+        info.args.as_ptr() as usize,
+        info.keys.as_ptr() as usize,
+    )
+}
+
+/// Generator of the joined script.
+struct Gen<'a> {
+    keys: Vec<&'a ScriptArg>,
+    argv: Vec<&'a ScriptArg>,
+    key_index: usize,
+    arg_index: usize,
+    script_index: usize,
+    /// Number of occurrences of each script body.
+    counts: HashMap<BodyId, usize>,
+    /// Indices of the shared functions of the repeated script bodies.
+    funcs: HashMap<BodyId, usize>,
+    /// Definitions of the shared functions.
+    defs: Vec<String>,
+    /// The range of the last keys passed by `with_keys`.
+    extra_keys: Option<(usize, usize)>,
+    /// The keys which the scripts read by hand, e.g. `KEYS[1]`.
+    raw_keys: Vec<String>,
+    /// The database selected by the enclosing `on_db`.
+    db: Option<i64>,
+    /// The preludes used by the scripts, without duplicates.
+    preludes: Vec<String>,
+    /// Bodies of the shared functions with their locations in Rust.
+    def_bodies: Vec<(String, Option<(&'static str, u32)>)>,
+    /// Bodies of the other scripts in the order of appearance with their locations in Rust.
+    expr_bodies: Vec<(String, Option<(&'static str, u32)>)>,
+    /// The loop variables of the enclosing `repeat` with the numbers of the keys and the arguments of a window.
+    windows: Vec<(String, usize, usize)>,
+}
+
+impl<'a> Gen<'a> {
+    fn new(info: &[Info], args: &'a [ScriptArg]) -> Self {
+        let mut counts = HashMap::new();
+        for i in info.iter().filter(|i| i.kind == Kind::Body) {
+            *counts.entry(body_id(i)).or_default() += 1;
+        }
+
+        // Keys and arguments are partitioned into `KEYS` and `ARGV` respectively.
+        Self {
+            keys: args.iter().filter(|arg| arg.is_key()).collect(),
+            argv: args.iter().filter(|arg| !arg.is_key()).collect(),
+            key_index: 0,
+            arg_index: 0,
+            script_index: 0,
+            counts,
+            funcs: HashMap::new(),
+            defs: Vec::new(),
+            extra_keys: None,
+            raw_keys: Vec::new(),
+            db: None,
+            preludes: Vec::new(),
+            def_bodies: Vec::new(),
+            expr_bodies: Vec::new(),
+            windows: Vec::new(),
+        }
+    }
+
+    /// Generate the expressions of the scripts until the end of the group.
+    fn exprs<'b>(&mut self, info: &mut impl Iterator<Item = &'b Info>) -> Vec<String> {
+        let mut exprs = Vec::new();
+
+        while let Some(i) = info.next() {
+            match i.kind {
+                Kind::Body => exprs.push(self.body(i)),
+                Kind::Begin(Group::NumKeys(numkeys)) => exprs.push(self.numkeys(numkeys, info)),
+                Kind::Begin(Group::Db(db)) => exprs.push(self.db(db, info)),
+                Kind::Begin(Group::Repeat(n)) => exprs.push(self.repeat(n, info)),
+                Kind::Begin(group) => {
+                    let inner = self.exprs(info);
+                    exprs.push(self.group(group, inner));
+                }
+                Kind::End => break,
+                Kind::Keys(len) => {
+                    self.extra_keys = Some((self.key_index, len));
+                    self.key_index += len;
+                }
+                Kind::Prelude => {
+                    if !self.preludes.iter().any(|prelude| **prelude == *i.body) {
+                        self.preludes.push(i.body.to_string());
+                    }
+                }
+            }
+        }
+
+        exprs
+    }
+
+    /// Generate the scripts which read the keys passed by `with_keys` by hand.
+    fn numkeys<'b>(&mut self, numkeys: usize, info: &mut impl Iterator<Item = &'b Info>) -> String {
+        let (begin, len) = self.extra_keys.take().unwrap_or_default();
+        let raw = (begin..begin + len)
+            .map(|i| format!("KEYS[{}]", i + 1))
+            .collect();
+        let outer = std::mem::replace(&mut self.raw_keys, raw);
+        let mut exprs = self.exprs(info);
+        self.raw_keys = outer;
+
+        if len != numkeys {
+            return format!(
+                "(function() return redis.error_reply(\"expected {} keys by with_keys but got {}\") end)()",
+                numkeys, len
+            );
+        }
+
+        let last = exprs.pop().unwrap_or_else(|| "nil".into());
+        let init: String = exprs.iter().map(|expr| format!("{}; ", expr)).collect();
+        format!("(function() {}return {} end)()", init, last)
+    }
+
+    /// Generate the scripts which run against the database, selecting back the database of the enclosing group.
+    fn db<'b>(&mut self, db: i64, info: &mut impl Iterator<Item = &'b Info>) -> String {
+        let outer = self.db.replace(db);
+        let mut exprs = self.exprs(info);
+        self.db = outer;
+
+        let last = exprs.pop().unwrap_or_else(|| "nil".into());
+        let init: String = exprs.iter().map(|expr| format!("{}; ", expr)).collect();
+        match outer {
+            Some(outer) => format!(
+                "(function() redis.call(\"SELECT\", {}); {}local {p}r = {}; redis.call(\"SELECT\", {}); return {p}r end)()",
+                db, init, last, outer, p = LOCAL_PREFIX
+            ),
+            None => format!(
+                "(function() redis.call(\"SELECT\", {}); {}return {} end)()",
+                db, init, last
+            ),
+        }
+    }
+
+    /// Generate the loop running the first window of the scripts with the windowed indices of `KEYS` and `ARGV`.
+    ///
+    /// Falls back to the windows one by one if they differ, e.g. in the packing of the values,
+    /// or read the keys passed by `with_keys` by hand.
+    fn repeat<'b>(&mut self, n: usize, info: &mut impl Iterator<Item = &'b Info>) -> String {
+        // Split the windows, each of which is a `Guard` group.
+        let mut windows: Vec<Vec<&Info>> = Vec::new();
+        let mut depth = 0;
+        for i in info {
+            match i.kind {
+                Kind::Begin(_) if depth == 0 => windows.push(Vec::new()),
+                Kind::End if depth == 0 => break,
+                _ => {}
+            }
+            match i.kind {
+                Kind::Begin(_) => depth += 1,
+                Kind::End => depth -= 1,
+                _ => {}
+            }
+            if let Some(window) = windows.last_mut() {
+                window.push(i);
+            }
+        }
+
+        if windows.is_empty() {
+            return "(function() return {} end)()".into();
+        }
+
+        let (keys, argv) = windows[0].iter().fold((0, 0), |(k, a), i| match i.kind {
+            Kind::Body => (k + i.keys.len(), a + i.args.len()),
+            Kind::Keys(len) => (k + len, a),
+            Kind::Begin(_) | Kind::End | Kind::Prelude => (k, a),
+        });
+        let packs = |values: &[&ScriptArg], begin: usize, len: usize| -> Option<Vec<bool>> {
+            let values = values.get(begin..begin + len)?;
+            Some(values.iter().map(|v| v.pack()).collect())
+        };
+        let uniform = windows.len() == n
+            && !windows
+                .iter()
+                .flatten()
+                .any(|i| matches!(i.kind, Kind::Keys(_)))
+            && (1..n).all(|w| {
+                windows[w] == windows[0]
+                    && packs(&self.keys, self.key_index + w * keys, keys)
+                        == packs(&self.keys, self.key_index, keys)
+                    && packs(&self.argv, self.arg_index + w * argv, argv)
+                        == packs(&self.argv, self.arg_index, argv)
+            });
+
+        if !uniform {
+            let exprs = self.exprs(&mut windows.iter().flatten().copied());
+            return format!("(function() return {{{}}} end)()", exprs.join(", "));
+        }
+
+        let var = format!("{}i{}", LOCAL_PREFIX, self.windows.len());
+        self.windows.push((var.clone(), keys, argv));
+        let expr = self.exprs(&mut windows[0].iter().copied()).join("; ");
+        self.windows.pop();
+        self.key_index += (n - 1) * keys;
+        self.arg_index += (n - 1) * argv;
+
+        format!(
+            "(function() local {p}rs = {{}}; for {v} = 0, {} do {p}rs[{v} + 1] = {} end return {p}rs end)()",
+            n - 1,
+            expr,
+            p = LOCAL_PREFIX,
+            v = var
+        )
+    }
+
+    /// The index of `KEYS` or `ARGV` counting from 1, offset by the windows of the enclosing `repeat`.
+    fn index(&self, index: usize, key: bool) -> String {
+        let mut index = index.to_string();
+        for (var, keys, argv) in &self.windows {
+            let len = if key { keys } else { argv };
+            if *len > 0 {
+                index += &format!(" + {} * {}", var, len);
+            }
+        }
+        index
+    }
+
+    fn body(&mut self, info: &Info) -> String {
+        // The values passed to the script, e.g. `ARGV[2]`.
+        let mut values = Vec::new();
+
+        for _ in info.keys {
+            let pack = self.keys.get(self.key_index).is_some_and(|key| key.pack());
+            self.key_index += 1;
+            values.push(gen_value("KEYS", &self.index(self.key_index, true), pack));
+        }
+
+        for _ in info.args {
+            let pack = self.argv.get(self.arg_index).is_some_and(|arg| arg.pack());
+            self.arg_index += 1;
+            values.push(gen_value("ARGV", &self.index(self.arg_index, false), pack));
+        }
+
+        // The name is put in front of the script as a comment.
+        let name = match info.name {
+            Some(name) => format!("--[[{}]] ", name.replace("]]", "] ]")),
+            None => String::new(),
+        };
+
+        let id = body_id(info);
+
+        // The shared function can't read the keys by hand nor `@@prev`, which may differ by the call.
+        if self.raw_keys.is_empty()
+            && !info.body.contains(PREV)
+            && self.counts.get(&id).is_some_and(|count| *count > 1)
+        {
+            // The repeated script is defined once as a function and called with its own values.
+            let index = match self.funcs.get(&id) {
+                Some(index) => *index,
+                None => {
+                    let index = self.defs.len();
+                    let (params, body) =
+                        gen_body(info, &format!("{}f{}", LOCAL_PREFIX, index), &[]);
+                    self.defs.push(format!(
+                        "local {}f{} = function({}) {} end",
+                        LOCAL_PREFIX,
+                        index,
+                        params.join(", "),
+                        body
+                    ));
+                    self.def_bodies.push((body, info.location));
+                    self.funcs.insert(id, index);
+                    index
+                }
+            };
+
+            format!("{}{}f{}({})", name, LOCAL_PREFIX, index, values.join(", "))
+        } else {
+            let (params, body) = gen_body(
+                info,
+                &format!("{}s{}", LOCAL_PREFIX, self.script_index),
+                &self.raw_keys,
+            );
+            self.script_index += 1;
+
+            let init: String = params
+                .iter()
+                .zip(&values)
+                .map(|(param, value)| format!("local {} = {} ", param, value))
+                .collect();
+
+            let expr = format!("{}(function() {} {} end)()", name, init, body);
+            self.expr_bodies.push((body, info.location));
+            expr
+        }
+    }
+
+    fn group(&self, group: Group, mut exprs: Vec<String>) -> String {
+        match group {
+            Group::Collect => format!("(function() return {{{}}} end)()", exprs.join(", ")),
+            Group::NumKeys(_) => unreachable!("generated by `numkeys`"),
+            Group::Db(_) => unreachable!("generated by `db`"),
+            Group::Repeat(_) => unreachable!("generated by `repeat`"),
+            Group::Invalid(_) => unreachable!("generated by `gen_source_mapped`"),
+            Group::Guard => {
+                let last = exprs.pop().unwrap_or_else(|| "nil".into());
+                let init: String = exprs.iter().map(|expr| format!("{}; ", expr)).collect();
+                format!("(function() {}return {} end)()", init, last)
+            }
+            Group::Then => {
+                // The first one is the expression of the `Guard` group.
+                let guard = exprs.remove(0);
+                let last = exprs.pop().unwrap_or_else(|| "nil".into());
+                let init: String = exprs.iter().map(|expr| format!("{}; ", expr)).collect();
+                format!(
+                    "(function() local {p}g = {}; if type({p}g) == \"table\" and {p}g.err then return {p}g end; \
+                     if {p}g and {p}g ~= 0 and {p}g ~= \"0\" and {p}g ~= \"\" \
+                     and not (type({p}g) == \"table\" and next({p}g) == nil) then \
+                     {}return {} end return nil end)()",
+                    guard, init, last, p = LOCAL_PREFIX
+                )
+            }
+            Group::AndThen => {
+                // The first one is the expression of the `Guard` group.
+                let prev = exprs.remove(0);
+                let last = exprs.pop().unwrap_or_else(|| "nil".into());
+                let init: String = exprs.iter().map(|expr| format!("{}; ", expr)).collect();
+                format!(
+                    "(function() local {} = {}; if type({}) == \"table\" and {}.err then return {} end; {}return {} end)()",
+                    PREV, prev, PREV, PREV, PREV, init, last
+                )
+            }
+            Group::Timed => {
+                let last = exprs.pop().unwrap_or_else(|| "nil".into());
+                let init: String = exprs.iter().map(|expr| format!("{}; ", expr)).collect();
+                format!(
+                    "(function() local {p}t0 = redis.call(\"TIME\"); {}local {p}r = {}; \
+                     local {p}t1 = redis.call(\"TIME\"); \
+                     return {{({p}t1[1] - {p}t0[1]) * 1000000 + ({p}t1[2] - {p}t0[2]), {p}r}} end)()",
+                    init, last, p = LOCAL_PREFIX
+                )
+            }
+        }
+    }
+}
+
+/// Generate the locals and the body of a script.
+///
+/// Locals are renamed with the prefix, e.g. `__redis_lua_s0_a1`,
+/// so that the locals of the joined scripts never overlap.
+fn gen_body(info: &Info, prefix: &str, raw_keys: &[String]) -> (Vec<String>, String) {
+    let mut locals = Vec::new();
+
+    for (i, key) in info.keys.iter().enumerate() {
+        locals.push((*key, format!("{}_k{}", prefix, i)));
+    }
+
+    // `KEYS` is shadowed by the keys of this script only, so that `#KEYS` counts them.
+    // The keys read by hand come first so that `KEYS[1]` is the first of them.
+    let keys: Vec<_> = raw_keys
+        .iter()
+        .map(|key| key.as_str())
+        .chain(locals.iter().map(|(_, local)| local.as_str()))
+        .collect();
+    let keys = format!("local KEYS = {{{}}}", keys.join(", "));
+
+    for (i, arg) in info.args.iter().enumerate() {
+        locals.push((*arg, format!("{}_a{}", prefix, i)));
+    }
+
+    let body = format!("{} {}", keys, rename_locals(&info.body, &locals));
+    let params = locals.into_iter().map(|(_, local)| local).collect();
+
+    (params, body)
+}
+
+/// Replace the identifiers in the script body with the new names.
+fn rename_locals(body: &str, locals: &[(&str, String)]) -> String {
+    if locals.is_empty() {
+        return body.into();
+    }
+
+    let mut renamed = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(begin) = rest.find(is_ident_char) {
+        let (head, tail) = rest.split_at(begin);
+        let end = tail.find(|c| !is_ident_char(c)).unwrap_or(tail.len());
+        let (ident, tail) = tail.split_at(end);
+
+        renamed += head;
+        match locals.iter().find(|(name, _)| *name == ident) {
+            Some((_, local)) => renamed += local,
+            None => renamed += ident,
+        }
+        rest = tail;
+    }
+    renamed += rest;
+
+    renamed
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn gen_value(table: &str, index: &str, pack: bool) -> String {
+    if pack {
+        format!("cmsgpack.unpack({}[{}])", table, index)
+    } else {
+        format!("{}[{}]", table, index)
     }
-    redis::Script::new(&script)
 }
@@ -0,0 +1,29 @@
+/// Escape the value to be put in a Lua string literal.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_ascii_control() => out.push_str(&format!("\\{:03}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Substitute the values into the placeholders of the script generated by `lua_fmt!`.
+///
+/// The completed script is owned by the script information, so it's dropped with the script.
+#[doc(hidden)]
+pub fn fmt_script(script: &'static str, values: &[String]) -> String {
+    values
+        .iter()
+        .enumerate()
+        .fold(script.to_string(), |text, (index, value)| {
+            text.replace(&format!("{{redis_lua_fmt_{}}}", index), &escape(value))
+        })
+}
@@ -0,0 +1,73 @@
+use crate::script::{eval_cmd, Script};
+use futures::prelude::*;
+
+/// The scripts and the commands which are executed in a `MULTI`/`EXEC` transaction.
+///
+/// Each script is sent by `EVAL` as is, not joined with the others, so the scripts
+/// and the ordinary commands are executed atomically in the order they are added.
+/// The reply of `EXEC` is decoded into a tuple of the results.
+#[derive(Clone)]
+pub struct ScriptTransaction {
+    pipe: redis::Pipeline,
+}
+
+impl Default for ScriptTransaction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptTransaction {
+    /// Create an empty transaction.
+    pub fn new() -> Self {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        Self { pipe }
+    }
+
+    /// Add a script to the transaction.
+    pub fn script<S: Script>(mut self, script: S) -> Self {
+        let mut info = vec![];
+        let mut args = vec![];
+        script.info(&mut info, &mut args);
+        self.pipe.add_command(eval_cmd(&info, &args));
+        self
+    }
+
+    /// Add an ordinary command to the transaction.
+    pub fn cmd(mut self, cmd: redis::Cmd) -> Self {
+        self.pipe.add_command(cmd);
+        self
+    }
+
+    /// Add a script to the transaction discarding its result.
+    pub fn script_ignore<S: Script>(self, script: S) -> Self {
+        let mut tx = self.script(script);
+        tx.pipe.ignore();
+        tx
+    }
+
+    /// Add an ordinary command to the transaction discarding its result.
+    pub fn cmd_ignore(self, cmd: redis::Cmd) -> Self {
+        let mut tx = self.cmd(cmd);
+        tx.pipe.ignore();
+        tx
+    }
+
+    /// Execute the transaction.
+    pub fn invoke<T>(&self, con: &mut dyn redis::ConnectionLike) -> redis::RedisResult<T>
+    where
+        T: redis::FromRedisValue,
+    {
+        self.pipe.query(con)
+    }
+
+    /// Execute the transaction asynchronously.
+    pub fn invoke_async<'a, C, T>(&'a self, con: &'a mut C) -> redis::RedisFuture<'a, T>
+    where
+        C: redis::aio::ConnectionLike + Send,
+        T: redis::FromRedisValue + Send,
+    {
+        async move { self.pipe.query_async(con).await }.boxed()
+    }
+}
@@ -11,9 +11,11 @@ trait RedisArgWrite: RedisWrite {
 }
 
 #[doc(hidden)]
+#[derive(Clone, Debug)]
 pub struct ScriptArg {
     buf: Vec<u8>,
     pack: bool,
+    key: bool,
 }
 
 impl ScriptArg {
@@ -21,12 +23,18 @@ impl ScriptArg {
         Self {
             buf: Vec::with_capacity(128),
             pack: false,
+            key: false,
         }
     }
 
     pub fn pack(&self) -> bool {
         self.pack
     }
+
+    /// Whether the argument is passed as `KEYS` instead of `ARGV`.
+    pub fn is_key(&self) -> bool {
+        self.key
+    }
 }
 
 impl RedisWrite for ScriptArg {
@@ -48,10 +56,171 @@ pub fn script_arg<T: Serialize + ?Sized>(value: &T) -> ScriptArg {
     arg
 }
 
+/// The value passed to the script as is, e.g. by `Script::invoke_raw`, which is never packed as a table.
+pub(crate) fn raw_arg(buf: Vec<u8>, key: bool) -> ScriptArg {
+    ScriptArg {
+        buf,
+        pack: false,
+        key,
+    }
+}
+
+pub fn script_key<T: Serialize + ?Sized>(value: &T) -> ScriptArg {
+    let mut arg = script_arg(value);
+    arg.key = true;
+    arg
+}
+
+/// The bound of the type annotation of `$`, e.g. `$x: i64`, which is satisfied only by the type itself.
+#[doc(hidden)]
+pub trait TypedArg<T> {}
+
+impl<T> TypedArg<T> for T {}
+
+/// The bound of the value captured by `@`, checked where it's captured, e.g. `@x`.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "captured value `{Self}` must implement `Serialize`",
+    label = "captured by `@` here",
+    note = "`@` captures a value, not a function; call the function, e.g. `@(f())`, or capture a variable holding the value"
+)]
+pub trait Capture {}
+
+impl<T: Serialize + ?Sized> Capture for T {}
+
+#[doc(hidden)]
+pub fn capture<T: Capture>(value: T) -> T {
+    value
+}
+
+/// The bytes passed to the script as a single binary-safe string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BytesArg(Vec<u8>);
+
+impl BytesArg {
+    /// The bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Serialize for BytesArg {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+/// Pass the bytes to the script as a string as is, e.g. a protobuf message.
+///
+/// Serde serializes `&[u8]` and `Vec<u8>` as sequences, which are passed as tables of numbers.
+pub fn bytes_arg<T: AsRef<[u8]> + ?Sized>(value: &T) -> BytesArg {
+    BytesArg(value.as_ref().to_vec())
+}
+
+/// The float passed to the script as a plain decimal string, e.g. `0.0000001` rather than `1e-7`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FloatArg(String);
+
+impl FloatArg {
+    /// The decimal string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Serialize for FloatArg {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Pass the float to the script in the decimal form without an exponent, which `tonumber` reads back exactly.
+///
+/// The digits are the shortest ones which round-trip, so `tonumber` in Lua returns the same `f64`.
+/// `f32` is widened to `f64` first, so Lua gets its exact value, e.g. `0.10000000149011612` for `0.1f32`.
+/// Panics if the float is NaN or infinite, which `tonumber` doesn't read portably.
+pub fn float_arg<T: Into<f64>>(value: T) -> FloatArg {
+    let value = value.into();
+    if !value.is_finite() {
+        panic!("Couldn't pass a non-finite float `{}`", value);
+    }
+    FloatArg(value.to_string())
+}
+
+/// The key concatenating the prefix and the value, e.g. `user:42`, for `#"user:"@id` and `#user:@id`.
+///
+/// The value is converted same as the other arguments, e.g. integers in decimal and strings as is.
+/// Tables, which are packed by MessagePack, can't be concatenated.
+pub fn key_concat<T: Serialize + ?Sized>(prefix: &str, value: &T) -> BytesArg {
+    let arg = script_arg(value);
+    if arg.pack() {
+        panic!(
+            "Couldn't concatenate a table to the key prefix `{}`",
+            prefix
+        );
+    }
+    let mut key = prefix.as_bytes().to_vec();
+    key.extend(arg.buf);
+    BytesArg(key)
+}
+
+/// Put the hash tag in front of the key, e.g. `{user123}:balance` for `balance`.
+///
+/// The key must not be a table, which is packed by MessagePack.
+pub(crate) fn key_hashtag(key: &mut ScriptArg, tag: &str) {
+    debug_assert!(!key.pack, "the hash tag is put on a table key");
+    let mut buf = format!("{{{}}}:", tag).into_bytes();
+    buf.append(&mut key.buf);
+    key.buf = buf;
+}
+
+/// The status or string reply of the script, e.g. `redis.status_reply("OK")`, for `Script::invoke_into`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Status(pub String);
+
+impl Status {
+    /// Whether the reply is `OK`.
+    pub fn is_ok(&self) -> bool {
+        self.0 == "OK"
+    }
+}
+
+impl std::convert::TryFrom<redis::Value> for Status {
+    type Error = String;
+
+    fn try_from(value: redis::Value) -> std::result::Result<Self, String> {
+        match value {
+            redis::Value::Okay => Ok(Self("OK".into())),
+            redis::Value::Status(s) => Ok(Self(s)),
+            redis::Value::Data(d) => String::from_utf8(d)
+                .map(Self)
+                .map_err(|_| "the reply is not valid UTF-8".into()),
+            v => Err(format!("{:?} is not a status reply", v)),
+        }
+    }
+}
+
+/// The value as passed to the script, or the bytes of the table packed by MessagePack.
+impl Display for ScriptArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pack {
+            write!(f, "{:?}", self.buf)
+        } else {
+            write!(f, "{}", String::from_utf8_lossy(&self.buf))
+        }
+    }
+}
+
 impl ToRedisArgs for ScriptArg {
-    fn write_redis_args<W: ?Sized>(&self, out: &mut W)
+    fn write_redis_args<W>(&self, out: &mut W)
     where
-        W: RedisWrite,
+        W: RedisWrite + ?Sized,
     {
         self.buf.write_redis_args(out);
     }
@@ -82,8 +251,8 @@ impl Display for Error {
 
 impl std::error::Error for Error {}
 
-impl From<rmp::encode::Error> for Error {
-    fn from(e: rmp::encode::Error) -> Self {
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
         Self(e.to_string())
     }
 }
@@ -125,65 +294,80 @@ where
     type SerializeStructVariant = Compound<Arg<'a, W>, Map>;
 
     fn serialize_bool(self, v: bool) -> Result<()> {
-        Ok((v as usize).write_redis_args(self.0))
+        (v as usize).write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_i8(self, v: i8) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_i16(self, v: i16) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_i32(self, v: i32) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_u16(self, v: u16) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_u32(self, v: u32) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_char(self, v: char) -> Result<()> {
         let mut buf = [0; 4];
         let len = v.encode_utf8(&mut buf).len();
-        Ok((&buf[..len]).write_redis_args(self.0))
+        (&buf[..len]).write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        Ok(v.write_redis_args(self.0))
+        v.write_redis_args(self.0);
+        Ok(())
     }
 
     fn serialize_none(self) -> Result<()> {
-        Ok(self.write_null())
+        self.write_null();
+        Ok(())
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<()>
@@ -194,11 +378,13 @@ where
     }
 
     fn serialize_unit(self) -> Result<()> {
-        Ok(self.write_null())
+        self.write_null();
+        Ok(())
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
-        Ok(self.write_null())
+        self.write_null();
+        Ok(())
     }
 
     fn serialize_unit_variant(
@@ -600,9 +786,9 @@ where
         }
     }
 
-    fn add<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn add<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.len += 1;
 
@@ -655,9 +841,9 @@ where
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.add(value)
     }
@@ -0,0 +1,63 @@
+use redis_lua::Script;
+
+#[test]
+fn add_arity_1_3() {
+    let script1 = redis_lua::lua!(return $a;);
+    let script2 = redis_lua::lua!(return $b + $c + $d;);
+    let script = (script1 + script2).a(1).b(2).c(3).d(4);
+
+    let plan = script.explain();
+    let args: Vec<_> = ["1", "2", "3", "4"]
+        .iter()
+        .map(|arg| arg.as_bytes().to_vec())
+        .collect();
+    assert_eq!(plan.args(), &args[..]);
+}
+
+#[test]
+fn add_arity_2_3() {
+    let script1 = redis_lua::lua!(return $a + $b;);
+    let script2 = redis_lua::lua!(return $c + $d + $e;);
+    let script = (script1 + script2).a(1).b(2).c(3).d(4).e(5);
+
+    assert_eq!(script.explain().args().len(), 5);
+}
+
+#[test]
+fn add_arity_3_1_partial() {
+    let script1 = redis_lua::lua!(return $a + $b + $c;);
+    let script2 = redis_lua::lua!(return $d;);
+
+    // The setters of the first script may be partially applied before joining.
+    let script = (script1.a(1).b(2) + script2).c(3).d(4);
+
+    assert_eq!(script.explain().args().len(), 4);
+}
+
+#[test]
+fn add_arity_applied() {
+    let script1 = redis_lua::lua!(return $a;);
+    let script2 = redis_lua::lua!(return $b + $c + $d;);
+    let script3 = redis_lua::lua!(return $e + $f;);
+
+    let script = (script1.a(1) + script2.b(2) + script3).c(3).d(4).e(5).f(6);
+
+    let plan = script.explain();
+    let args: Vec<_> = ["1", "2", "3", "4", "5", "6"]
+        .iter()
+        .map(|arg| arg.as_bytes().to_vec())
+        .collect();
+    assert_eq!(plan.args(), &args[..]);
+}
+
+#[tokio::test]
+async fn add_arity() {
+    let script1 = redis_lua::lua!(return $a;);
+    let script2 = redis_lua::lua!(return $b + $c + $d;);
+    let script = (script1 + script2).a(1).b(2).c(3).d(4);
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+    let res: usize = script.invoke_async(&mut con).await.unwrap();
+    assert_eq!(res, 9);
+}
@@ -0,0 +1,51 @@
+use redis_lua::Script;
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn and_then_source() {
+    let script = redis_lua::lua!(return $x + 1;).x(1);
+    let next = redis_lua::lua!(return @@prev * $y;).y(2);
+
+    let source = script.and_then(next).source();
+    assert!(source.contains("local __redis_lua_prev = "));
+    assert!(source.contains("__redis_lua_prev * "));
+}
+
+#[tokio::test]
+async fn and_then() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let add = redis_lua::lua!(return $x + 1;);
+    let mul = redis_lua::lua!(return @@prev * $y;);
+
+    // (1 + 1) * 3
+    let res: usize = add
+        .clone()
+        .x(1)
+        .and_then(mul.clone().y(3))
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, 6);
+
+    // ((1 + 1) * 3) * 4, where the same script is chained twice.
+    let res: usize = add
+        .x(1)
+        .and_then(mul.clone().y(3))
+        .and_then(mul.clone().y(4))
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, 24);
+
+    // `@@prev` is `nil` outside of `and_then`.
+    let res: bool = redis_lua::lua!(return @@prev == nil;)
+        .invoke(&mut cli)
+        .unwrap();
+    assert!(res);
+
+    // The error reply stops the chain.
+    let err = redis_lua::lua!(return redis.error_reply("stop");)
+        .and_then(mul.y(3))
+        .invoke::<usize>(&mut cli)
+        .unwrap_err();
+    assert!(err.to_string().contains("stop"));
+}
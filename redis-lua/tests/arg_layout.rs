@@ -0,0 +1,57 @@
+use redis_lua::{ArgSource, Script};
+
+#[test]
+fn arg_layout() {
+    let x = 1;
+    let script = redis_lua::lua!(
+        return redis.call("set", #$key, #@x, $y + @x + $z);
+    );
+
+    let script = script.key("k").y(2).z(3);
+
+    // The values are in the order they first appear in the script, with the keys first.
+    assert_eq!(
+        script.arg_layout(),
+        vec![
+            ("key", ArgSource::Argument),
+            ("x", ArgSource::Capture),
+            ("y", ArgSource::Argument),
+            ("x", ArgSource::Capture),
+            ("z", ArgSource::Argument),
+        ]
+    );
+
+    let plan = script.explain();
+    assert_eq!(plan.keys(), &[b"k".to_vec(), b"1".to_vec()]);
+    assert_eq!(plan.args(), &[b"2".to_vec(), b"1".to_vec(), b"3".to_vec()]);
+}
+
+#[test]
+fn arg_layout_join() {
+    let x = 1;
+    let script1 = redis_lua::lua!(return $a + @x;);
+    let script2 = redis_lua::lua!(return redis.call("get", #$key) + $b;);
+    let script = (script1 + script2)
+        .a(1)
+        .key("k")
+        .b(2)
+        .with_keys(vec!["extra"]);
+
+    let layout = script.arg_layout();
+    assert_eq!(
+        layout,
+        vec![
+            ("KEYS", ArgSource::Keys),
+            ("key", ArgSource::Argument),
+            ("a", ArgSource::Argument),
+            ("x", ArgSource::Capture),
+            ("b", ArgSource::Argument),
+        ]
+    );
+
+    // The layout matches the values passed to the script.
+    let plan = script.explain();
+    assert_eq!(script.keys_count(), 2);
+    assert_eq!(plan.keys(), &[b"extra".to_vec(), b"k".to_vec()]);
+    assert_eq!(plan.args(), &[b"1".to_vec(), b"1".to_vec(), b"2".to_vec()]);
+}
@@ -0,0 +1,38 @@
+use redis_lua::Script;
+
+struct Order {
+    total: usize,
+}
+
+#[test]
+fn arg_pairs() {
+    let x = 1;
+    let order = Order { total: 5 };
+    let script1 = redis_lua::lua!(
+        return redis.call("set", #$key, @x + $y + @order.total);
+    );
+    let script2 = redis_lua::lua!(
+        return $z;
+    );
+    let script = (script1 + script2)
+        .key("arg_pairs:a")
+        .y(2)
+        .z("c")
+        .with_keys(vec!["arg_pairs:b"]);
+
+    let pairs = script.arg_pairs();
+    assert_eq!(
+        pairs,
+        vec![
+            ("KEYS", vec![b"arg_pairs:b".to_vec()]),
+            ("key", vec![b"arg_pairs:a".to_vec()]),
+            ("x", vec![b"1".to_vec()]),
+            ("y", vec![b"2".to_vec()]),
+            ("order.total", vec![b"5".to_vec()]),
+            ("z", vec![b"c".to_vec()]),
+        ]
+    );
+
+    // Doesn't consume the script.
+    assert_eq!(script.arg_pairs(), pairs);
+}
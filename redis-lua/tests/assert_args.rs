@@ -0,0 +1,48 @@
+use redis_lua::{Info, Script, ScriptArg};
+
+struct Mismatched;
+
+impl Script for Mismatched {
+    fn info(&self, info: &mut Vec<Info>, args: &mut Vec<ScriptArg>) {
+        info.push(Info::new("return a + b", "return a + b", &["a", "b"]));
+        args.push(redis_lua::script_arg(&1));
+    }
+}
+
+#[test]
+fn matched_args() {
+    let script = redis_lua::lua!(return $a + $b;).a(1).b(2);
+    assert!(script.source().contains("ARGV[2]"));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(
+    expected = "the scripts declare 0 keys and 2 arguments but 0 keys and 1 arguments are bound"
+)]
+fn mismatched_args() {
+    Mismatched.source();
+}
+
+#[test]
+fn new_checked() {
+    let info = Info::new_checked(
+        "local a = ARGV[1]; local b = ARGV[2]; return a + b",
+        "return a + b",
+        &["a", "b"],
+    )
+    .unwrap();
+    assert_eq!(info.args(), &["a", "b"]);
+
+    // `ARGV[2]` is read twice and `ARGV[1]` is never read.
+    let err = Info::new_checked(
+        "local a = ARGV[2]; local b = ARGV[2]; return a + b",
+        "return a + b",
+        &["a", "b"],
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::ClientError);
+
+    let err = Info::new_checked("return a + b", "return a + b", &["a", "b"]).unwrap_err();
+    assert!(err.to_string().contains("declares 2 arguments"));
+}
@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+
+#[tokio::test]
+async fn bind_named() {
+    let script = redis_lua::lua!(
+        return $x + $y * $z;
+    );
+
+    let args: HashMap<&str, usize> = vec![("x", 1), ("y", 2), ("z", 3)].into_iter().collect();
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.bind_named(&args).unwrap().invoke(&mut cli).unwrap();
+    assert_eq!(res, 7);
+}
+
+#[test]
+fn bind_named_missing() {
+    let script = redis_lua::lua!(
+        return $x + $y;
+    );
+
+    let args: HashMap<&str, usize> = vec![("x", 1)].into_iter().collect();
+
+    let err = script.bind_named(&args).err().unwrap();
+    assert_eq!(err.kind(), redis::ErrorKind::ClientError);
+    assert_eq!(err.detail(), Some("y"));
+}
@@ -0,0 +1,37 @@
+use redis_lua::Script;
+
+/// Bytes which are not valid UTF-8.
+const BLOB: &[u8] = &[0xff, 0x00, 0xfe, 0x80, b'a', 0xc3, 0x28];
+
+#[test]
+fn bytes_arg_plan() {
+    let blob = redis_lua::bytes_arg(BLOB);
+    let script = redis_lua::lua!(return @blob .. $x;).x(redis_lua::bytes_arg(BLOB));
+
+    let plan = script.explain();
+    assert_eq!(plan.args(), &[BLOB.to_vec(), BLOB.to_vec()]);
+}
+
+#[tokio::test]
+async fn bytes_arg_roundtrip() {
+    let blob = redis_lua::bytes_arg(BLOB);
+    let script = redis_lua::lua!(return @blob .. $x;);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: Vec<u8> = script
+        .x(redis_lua::bytes_arg(BLOB))
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, [BLOB, BLOB].concat());
+}
+
+#[tokio::test]
+async fn bytes_arg_packed() {
+    // Tables are packed by `cmsgpack`, and the bytes in them are kept as strings.
+    let blobs = vec![redis_lua::bytes_arg(BLOB), redis_lua::bytes_arg(&BLOB[..2])];
+    let script = redis_lua::lua!(return (@blobs)[1] .. (@blobs)[2];);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: Vec<u8> = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, [BLOB, &BLOB[..2]].concat());
+}
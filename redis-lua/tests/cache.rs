@@ -0,0 +1,29 @@
+use redis_lua::{lua, Script};
+
+#[test]
+fn cache_join_order() {
+    let script1 = lua!(return 1;);
+    let script2 = lua!(return 2;);
+
+    let s12 = script1.clone().join(script2.clone()).prepare();
+    let s21 = script2.clone().join(script1.clone()).prepare();
+    assert_ne!(s12.get_hash(), s21.get_hash());
+
+    // Cached scripts are identical to the first ones.
+    assert_eq!(
+        s12.get_hash(),
+        script1.clone().join(script2.clone()).prepare().get_hash()
+    );
+    assert_eq!(s21.get_hash(), script2.join(script1).prepare().get_hash());
+}
+
+#[test]
+fn cache_packed_args() {
+    let script = lua!(return $x;);
+
+    // Tables are packed and unpacked by `cmsgpack`, which changes the script.
+    let plain = script.clone().x(1).prepare();
+    let packed = script.clone().x(vec![1, 2]).prepare();
+    assert_ne!(plain.get_hash(), packed.get_hash());
+    assert_eq!(plain.get_hash(), script.x(2).prepare().get_hash());
+}
@@ -0,0 +1,30 @@
+use redis_lua::{lua, Script};
+
+#[test]
+fn cache_key_default() {
+    let script1 = lua!(return 1;);
+    let script2 = lua!(return 2;);
+
+    assert_eq!(script1.cache_key(), script1.clone().cache_key());
+    assert_ne!(script1.cache_key(), script2.cache_key());
+}
+
+#[test]
+fn cache_key_custom() {
+    let script1 = lua!(cache_key = "one", return 1;);
+    let script2 = lua!(cache_key = "one", {
+        return 1;
+    });
+    let script3 = lua!(cache_key = "other", return 1;);
+
+    // The scripts with the same key share the generated script.
+    assert_eq!(script1.cache_key(), script2.cache_key());
+    assert_eq!(script1.prepare().get_hash(), script2.prepare().get_hash());
+    assert_ne!(script1.cache_key(), script3.cache_key());
+
+    // Joining is still part of the identity.
+    assert_ne!(
+        script1.clone().join(script2.clone()).cache_key(),
+        script1.cache_key()
+    );
+}
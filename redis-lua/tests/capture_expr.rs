@@ -0,0 +1,21 @@
+struct Config {
+    ttl: u64,
+}
+
+impl Config {
+    fn ttl_seconds(&self) -> u64 {
+        self.ttl
+    }
+}
+
+#[tokio::test]
+async fn capture_expr() {
+    let config = Config { ttl: 30 };
+    let script = redis_lua::lua!(
+        return @(config.ttl_seconds()) + @(config.ttl * 2);
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, 90);
+}
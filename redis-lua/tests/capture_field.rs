@@ -0,0 +1,42 @@
+use redis_lua::Script;
+
+struct Order {
+    total: u64,
+    item: Item,
+}
+
+struct Item {
+    count: u64,
+}
+
+impl Order {
+    fn script(&self) -> impl Script {
+        redis_lua::lua!(return @self.total * @self.item.count;)
+    }
+}
+
+#[test]
+fn capture_field_args() {
+    let order = Order {
+        total: 3,
+        item: Item { count: 4 },
+    };
+
+    let plan = redis_lua::lua!(return @order.total .. "/" .. @order.item.count;).explain();
+    assert_eq!(plan.args(), &[b"3".to_vec(), b"4".to_vec()]);
+
+    let plan = order.script().explain();
+    assert_eq!(plan.args(), &[b"3".to_vec(), b"4".to_vec()]);
+}
+
+#[tokio::test]
+async fn capture_field() {
+    let order = Order {
+        total: 3,
+        item: Item { count: 4 },
+    };
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = order.script().invoke(&mut cli).unwrap();
+    assert_eq!(res, 12);
+}
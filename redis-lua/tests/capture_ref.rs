@@ -0,0 +1,25 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn capture_ref() {
+    let big = vec![1; 1000];
+    let script = redis_lua::lua!(return #(@(&big)) + $x;);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.x(1).invoke(&mut cli).unwrap();
+    assert_eq!(res, 1001);
+
+    // The value is borrowed, not moved.
+    assert_eq!(big.len(), 1000);
+}
+
+#[test]
+fn capture_ref_join() {
+    let big = vec![1, 2, 3];
+    let script1 = redis_lua::lua!(return @(&big)[1];);
+    let script2 = redis_lua::lua!(return @(&big)[2];);
+
+    let plan = script1.join(script2).explain();
+    assert_eq!(plan.args().len(), 2);
+    assert_eq!(big, vec![1, 2, 3]);
+}
@@ -0,0 +1,49 @@
+use redis_lua::Script;
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn cfg_script_source() {
+    let script = redis_lua::lua!(if cfg!(debug_assertions) {
+        return $x + 1;
+    } else {
+        return $x + 2;
+    });
+
+    let source = script.x(1).source();
+    if cfg!(debug_assertions) {
+        assert!(source.contains("+ 1"));
+        assert!(!source.contains("+ 2"));
+    } else {
+        assert!(source.contains("+ 2"));
+        assert!(!source.contains("+ 1"));
+    }
+}
+
+#[test]
+fn cfg_script_fragment() {
+    let trace = redis_lua::lua!(if cfg!(not(debug_assertions)) {
+        redis.log(redis.LOG_NOTICE, "trace");
+    });
+    let script = trace.join(redis_lua::lua!(return 1;));
+
+    assert_eq!(
+        script.source().contains("redis.log"),
+        !cfg!(debug_assertions)
+    );
+}
+
+#[tokio::test]
+async fn cfg_script_invoke() {
+    let script = redis_lua::lua!(
+        name = "cfg",
+        if cfg!(debug_assertions) {
+            return 1;
+        } else {
+            return 2;
+        }
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, if cfg!(debug_assertions) { 1 } else { 2 });
+}
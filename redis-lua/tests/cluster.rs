@@ -0,0 +1,53 @@
+#![cfg(feature = "cluster")]
+
+use redis_lua::Script;
+
+#[tokio::test]
+async fn cluster() {
+    let cli = redis::cluster::ClusterClient::open(vec!["redis://127.0.0.1:7000"]).unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let script = redis_lua::lua!(
+        redis.call("set", #$key, $value);
+        return redis.call("get", #$key);
+    );
+    let res: usize = script
+        .key("{cluster}:a")
+        .value(3)
+        .invoke_cluster(&mut con)
+        .unwrap();
+    assert_eq!(res, 3);
+
+    let res: usize = redis_lua::lua!(readonly, return redis.call("get", #$key);)
+        .key("{cluster}:a")
+        .invoke_cluster(&mut con)
+        .unwrap();
+    assert_eq!(res, 3);
+
+    let err = redis_lua::lua!(return 1;)
+        .invoke_cluster::<usize>(&mut con)
+        .unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::ClientError);
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn cluster_async() {
+    let mut con = redis_lua::MockConnection::new().reply(redis::Value::Int(3));
+
+    let res: usize = redis_lua::lua!(return redis.call("get", #$key);)
+        .key("{cluster}:a")
+        .invoke_cluster_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(res, 3);
+    assert_eq!(con.last_call().unwrap().keys, vec![b"{cluster}:a".to_vec()]);
+
+    // The script without keys isn't sent.
+    let err = redis_lua::lua!(return 1;)
+        .invoke_cluster_async::<_, usize>(&mut con)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::ClientError);
+    assert_eq!(con.calls().len(), 1);
+}
@@ -0,0 +1,25 @@
+use redis_lua::{lua, Script};
+
+#[tokio::test]
+async fn collect() {
+    let script1 = lua!(return $x + 1;);
+    let script2 = lua!(return $y + 2;);
+    let script3 = lua!(return "three";);
+    let script = (script1 + script2 + script3).x(10).y(20).collect();
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: (usize, usize, String) = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, (11, 22, "three".into()));
+}
+
+#[tokio::test]
+async fn collect_join() {
+    let script1 = lua!(return 1;);
+    let script2 = lua!(return 2;);
+    let script3 = lua!(return 3;);
+    let script = script1.join(script2).collect().join(script3.collect());
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: Vec<usize> = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, vec![3]);
+}
@@ -0,0 +1,12 @@
+// Calls which have the right number of arguments, or may have, compile.
+#[test]
+fn command_arity() {
+    let script = redis_lua::lua!(
+        redis.call("SET", #$key, $value, "EX", 10);
+        redis.pcall("hset", #$hash, "a", 1, "b", 2);
+        redis.call("del", unpack(KEYS));
+        redis.call("unknown_command");
+        return redis.call("get", #$key);
+    );
+    let _ = script.key("a").value(1).hash("b").signature();
+}
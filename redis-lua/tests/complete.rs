@@ -0,0 +1,76 @@
+//! `Script` is implemented only once all the `$` arguments are bound.
+
+use redis_lua::Script;
+use std::marker::PhantomData;
+
+struct Probe<T>(PhantomData<T>);
+
+fn probe<T>(_: &T) -> Probe<T> {
+    Probe(PhantomData)
+}
+
+// Autoref specialization: `IsScript` is preferred if it's implemented, otherwise `NotScript` is picked.
+trait IsScript {
+    fn is_script(&self) -> bool {
+        true
+    }
+}
+
+impl<T: Script> IsScript for Probe<T> {}
+
+trait NotScript {
+    fn is_script(&self) -> bool {
+        false
+    }
+}
+
+impl<T> NotScript for &Probe<T> {}
+
+macro_rules! is_script {
+    ($e:expr) => {
+        (&probe(&$e)).is_script()
+    };
+}
+
+#[test]
+fn complete_zero() {
+    let x = 1;
+    assert!(is_script!(redis_lua::lua!(return 1)));
+    assert!(is_script!(redis_lua::lua!(return @x)));
+    assert!(is_script!(redis_lua::lua!(return $x = 1)));
+}
+
+#[test]
+fn complete_one() {
+    let script = redis_lua::lua!(return $a);
+    assert!(!is_script!(script));
+    assert!(is_script!(script.a(1)));
+}
+
+#[test]
+fn complete_three() {
+    let script = redis_lua::lua!(return $a + $b + #$c);
+    assert!(!is_script!(script));
+    assert!(!is_script!(script.clone().a(1)));
+    assert!(!is_script!(script.clone().a(1).b(2)));
+    assert!(is_script!(script.a(1).b(2).c("k")));
+}
+
+#[test]
+fn complete_defaults() {
+    let script = redis_lua::lua!(return $a + ($b = 2));
+    assert!(!is_script!(script));
+    assert!(!is_script!(script.clone().b(3)));
+    assert!(is_script!(script.a(1)));
+}
+
+#[test]
+fn complete_join() {
+    let script1 = redis_lua::lua!(return $a);
+    let script2 = redis_lua::lua!(return $b + $c);
+    let script = script1 + script2;
+    assert!(!is_script!(script));
+    assert!(!is_script!(script.clone().a(1)));
+    assert!(!is_script!(script.clone().a(1).b(2)));
+    assert!(is_script!(script.a(1).b(2).c(3)));
+}
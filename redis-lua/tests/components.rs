@@ -0,0 +1,34 @@
+use redis_lua::Script;
+
+#[test]
+fn components() {
+    let x = 1;
+    let script1 = redis_lua::lua!(name = "first", return redis.call("get", #$key) + @x;);
+    let script2 = redis_lua::lua!(return $y;);
+    let script3 = redis_lua::lua!(name = "third", return $z;);
+    let script = (script1 + script2 + script3)
+        .key("a")
+        .y(2)
+        .z(3)
+        .with_keys(vec!["b"]);
+
+    let components = script.components();
+    assert_eq!(components.len(), 3);
+
+    let names: Vec<_> = components.iter().map(|c| c.name()).collect();
+    assert_eq!(names, vec![Some("first"), None, Some("third")]);
+
+    assert_eq!(
+        components[0].args(),
+        &[("key", vec![b"a".to_vec()]), ("x", vec![b"1".to_vec()])]
+    );
+    assert_eq!(components[1].args(), &[("y", vec![b"2".to_vec()])]);
+    assert_eq!(components[2].args(), &[("z", vec![b"3".to_vec()])]);
+    assert!(components[1].info().body().contains("return"));
+}
+
+#[test]
+fn components_collect() {
+    let script = (redis_lua::lua!(return 1;) + redis_lua::lua!(return 2;)).collect();
+    assert_eq!(script.components().len(), 2);
+}
@@ -0,0 +1,57 @@
+use redis_lua::ScriptCursor;
+
+#[tokio::test]
+async fn cursor() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    // Returns the numbers from 0 to 9 in the batches of 3 starting with the cursor.
+    let script = redis_lua::lua!(
+        local from = tonumber($cursor)
+        local batch = {}
+        for i = from, math.min(from + 2, 9) do
+            table.insert(batch, i)
+        end
+        local cursor = from + 3
+        if cursor > 9 then
+            cursor = 0
+        end
+        return {tostring(cursor), batch}
+    );
+
+    let batches: Vec<Vec<usize>> =
+        ScriptCursor::new(&mut cli, |cursor| script.clone().cursor(cursor))
+            .collect::<Result<_, _>>()
+            .unwrap();
+    assert_eq!(
+        batches,
+        vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8], vec![9]]
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn cursor_mock() {
+    use redis::Value;
+
+    let batch = |cursor: &str, items: &[&str]| {
+        Value::Bulk(vec![
+            Value::Data(cursor.into()),
+            Value::Bulk(
+                items
+                    .iter()
+                    .map(|i| Value::Data(i.as_bytes().to_vec()))
+                    .collect(),
+            ),
+        ])
+    };
+    let mut con = redis_lua::MockConnection::new()
+        .reply(batch("5", &["a", "b"]))
+        .reply(batch("0", &["c"]));
+
+    let script = redis_lua::lua!(return {$cursor, {}};);
+    let batches: Vec<Vec<String>> =
+        ScriptCursor::new(&mut con, |cursor| script.clone().cursor(cursor))
+            .collect::<Result<_, _>>()
+            .unwrap();
+    assert_eq!(batches, vec![vec!["a", "b"], vec!["c"]]);
+}
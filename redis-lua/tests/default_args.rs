@@ -0,0 +1,63 @@
+use redis_lua::Script;
+
+fn args(values: &[&str]) -> Vec<Vec<u8>> {
+    values.iter().map(|v| v.as_bytes().to_vec()).collect()
+}
+
+#[test]
+fn default_omitted() {
+    let script = redis_lua::lua!(return $x + ($step = 1););
+
+    let plan = script.x(10).explain();
+    assert_eq!(plan.args(), &args(&["10", "1"])[..]);
+}
+
+#[test]
+fn default_overridden() {
+    let script = redis_lua::lua!(return $x + ($step = 1););
+
+    // The argument with the default value can be set at any point of the chain.
+    let plan = script.clone().step(5).x(10).explain();
+    assert_eq!(plan.args(), &args(&["10", "5"])[..]);
+
+    let plan = script.x(10).step(5).explain();
+    assert_eq!(plan.args(), &args(&["10", "5"])[..]);
+}
+
+#[test]
+fn default_only() {
+    let script = redis_lua::lua!(
+        local limit = $limit = 10
+        return redis.call("lrange", #$key = "queue", 0, limit - 1)
+    );
+
+    let plan = script.clone().explain();
+    assert_eq!(plan.keys(), &args(&["queue"])[..]);
+    assert_eq!(plan.args(), &args(&["10"])[..]);
+
+    let plan = script.key("jobs").limit(-1).explain();
+    assert_eq!(plan.keys(), &args(&["jobs"])[..]);
+    assert_eq!(plan.args(), &args(&["-1"])[..]);
+}
+
+#[test]
+fn default_comparison() {
+    // `==` is a comparison rather than the default value.
+    let script = redis_lua::lua!(return $x == 1;);
+
+    assert_eq!(script.x(2).explain().args(), &args(&["2"])[..]);
+}
+
+#[tokio::test]
+async fn default_invoke() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let script = redis_lua::lua!(return $x + ($step = 1););
+
+    let res: usize = script.clone().x(10).invoke_async(&mut con).await.unwrap();
+    assert_eq!(res, 11);
+
+    let res: usize = script.x(10).step(5).invoke_async(&mut con).await.unwrap();
+    assert_eq!(res, 15);
+}
@@ -0,0 +1,29 @@
+use redis_lua::Script;
+
+#[test]
+fn describe() {
+    let y = 2;
+    let script = redis_lua::lua!(name = "incr", return redis.call("incrby", #$key, $x + @y);)
+        .key("a")
+        .x(1);
+
+    let desc = script.describe();
+    assert_eq!(desc.names(), &["incr"]);
+    assert_eq!(desc.args(), &["key", "x", "y"]);
+    assert_eq!(desc.keys_count(), 1);
+    assert_eq!(desc.source_len(), script.source().len());
+    assert_eq!(desc.sha1(), script.sha1());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn describe_json() {
+    let desc = redis_lua::lua!(name = "get", return redis.call("get", #$key);)
+        .key("a")
+        .describe();
+    let json = redis_lua::json_arg(&desc).unwrap();
+    let json = json.as_str();
+
+    assert!(json.starts_with(r#"{"names":["get"],"args":["key"],"keys_count":1,"source_len":"#));
+    assert!(json.ends_with(&format!(r#""sha1":"{}"}}"#, desc.sha1())));
+}
@@ -0,0 +1,18 @@
+#![cfg(feature = "deterministic")]
+
+use redis_lua::Script;
+
+#[test]
+fn deterministic_opt_out() {
+    let script = redis_lua::lua!(nondeterministic, {
+        local now = redis.call("time")
+        return now[1]
+    });
+    assert!(script.source().contains("redis.call(\"time\")"));
+}
+
+#[test]
+fn deterministic_allowed() {
+    let script = redis_lua::lua!(return redis.call("get", #$key););
+    assert!(script.key("a").source().contains("redis.call(\"get\""));
+}
@@ -0,0 +1,42 @@
+#[test]
+fn display() {
+    let script = redis_lua::lua!(name = "transfer", {
+        redis.call("decrby", #$from, $amount);
+        return redis.call("incrby", #$to, $amount);
+    });
+
+    let script = script.from("acct1").amount(100).to("acct2");
+    assert_eq!(
+        script.to_string(),
+        "transfer(from=acct1, amount=100, to=acct2)"
+    );
+}
+
+#[test]
+fn display_partial() {
+    let script = redis_lua::lua!(return redis.call("get", #$key) + $x;);
+    assert_eq!(script.to_string(), "script()");
+    assert_eq!(script.key("a").to_string(), "script(key=a)");
+}
+
+#[test]
+fn display_captures_and_defaults() {
+    let y = 2;
+    let script = redis_lua::lua!(return $x + @y + $z = 3;);
+    assert_eq!(script.clone().x(1).to_string(), "script(y=2, x=1, z=3)");
+    assert_eq!(script.x(1).z(4).to_string(), "script(y=2, x=1, z=4)");
+}
+
+#[test]
+fn display_non_display_args() {
+    // The script is usable without `Display` of the arguments.
+    let script = redis_lua::lua!(return #$x;).x(vec![1, 2]);
+    let _ = script.signature();
+}
+
+#[test]
+fn display_braces_in_expr() {
+    let id = 3;
+    let script = redis_lua::lua!(return #@(format!("user:{}", id)););
+    assert!(script.to_string().ends_with("=user:3)"));
+}
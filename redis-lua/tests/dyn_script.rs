@@ -0,0 +1,30 @@
+use redis_lua::Script;
+
+fn scripts() -> Vec<Box<dyn Script>> {
+    let x = 1;
+    vec![
+        Box::new(redis_lua::lua!(return @x;)),
+        Box::new(redis_lua::lua!(return $y + 1;).y(2)),
+        Box::new(redis_lua::lua!(return redis.call("get", #$key);).key("a")),
+    ]
+}
+
+#[test]
+fn dyn_script_iter() {
+    let keys: Vec<_> = scripts().iter().map(|s| s.keys_count()).collect();
+    assert_eq!(keys, vec![0, 0, 1]);
+}
+
+#[test]
+fn dyn_script_fold() {
+    let joined = scripts()
+        .into_iter()
+        .fold(Box::new(()) as Box<dyn Script>, |acc, s| {
+            Box::new(acc.join(s))
+        });
+    assert_eq!(joined.keys_count(), 1);
+
+    let plan = joined.explain();
+    assert_eq!(plan.keys(), &[b"a".to_vec()]);
+    assert_eq!(plan.args(), &[b"1".to_vec(), b"2".to_vec()]);
+}
@@ -0,0 +1,45 @@
+use redis_lua::Script;
+
+#[derive(redis_lua::ScriptArg)]
+enum Color {
+    Red,
+    Green,
+    Blue = 10,
+    Black,
+}
+
+#[derive(Clone, Copy, redis_lua::ScriptArg)]
+#[allow(dead_code)]
+pub enum Priority {
+    #[doc = "The lowest."]
+    Low = -1,
+    High = 1,
+}
+
+#[test]
+fn enum_arg_discriminant() {
+    let (red, green, blue, black) = (Color::Red, Color::Green, Color::Blue, Color::Black);
+    let script = redis_lua::lua!(return {@red, @green, @blue, @black, $p};);
+    let plan = script.p(Priority::Low).explain();
+    assert_eq!(
+        plan.args(),
+        &[
+            b"0".to_vec(),
+            b"1".to_vec(),
+            b"10".to_vec(),
+            b"11".to_vec(),
+            b"-1".to_vec()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn enum_arg() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let color = Color::Blue;
+    let res: i64 = redis_lua::lua!(return @color + 1;)
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, 11);
+}
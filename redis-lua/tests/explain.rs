@@ -0,0 +1,41 @@
+use redis_lua::Script;
+
+#[test]
+fn explain() {
+    let x = 1;
+    let script = redis_lua::lua!(
+        redis.call("set", #$key, $value);
+        return redis.call("get", #$key) + @x;
+    );
+
+    let plan = script.key("explain:a").value(3).explain();
+    assert_eq!(plan.num_keys(), 1);
+    assert_eq!(plan.keys(), &[b"explain:a".to_vec()]);
+    assert_eq!(plan.args(), &[b"3".to_vec(), b"1".to_vec()]);
+    assert!(plan.source().contains("KEYS[1]"));
+}
+
+#[test]
+fn explain_join_keys() {
+    let script1 = redis_lua::lua!(
+        return redis.call("set", #$k1, $a1);
+    );
+    let script2 = redis_lua::lua!(
+        return redis.call("set", #$k2, $a2);
+    );
+
+    let plan = (script1 + script2)
+        .k1("explain:k1")
+        .a1(1)
+        .k2("explain:k2")
+        .a2(2)
+        .explain();
+    assert_eq!(plan.num_keys(), 2);
+    assert_eq!(
+        plan.keys(),
+        &[b"explain:k1".to_vec(), b"explain:k2".to_vec()]
+    );
+    assert_eq!(plan.args(), &[b"1".to_vec(), b"2".to_vec()]);
+    assert!(plan.source().contains("KEYS[2]"));
+    assert!(plan.source().contains("ARGV[2]"));
+}
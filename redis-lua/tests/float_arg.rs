@@ -0,0 +1,47 @@
+use redis_lua::{float_arg, lua, Script};
+
+#[test]
+fn large_magnitudes() {
+    assert_eq!(float_arg(1e21).as_str(), "1000000000000000000000");
+    assert_eq!(float_arg(-1.5e20).as_str(), "-150000000000000000000");
+    assert_eq!(
+        float_arg(f64::MAX).as_str().parse::<f64>().unwrap(),
+        f64::MAX
+    );
+    assert!(!float_arg(f64::MAX).as_str().contains('e'));
+}
+
+#[test]
+fn small_fractions() {
+    assert_eq!(float_arg(1e-7).as_str(), "0.0000001");
+    assert_eq!(float_arg(0.1).as_str(), "0.1");
+    assert_eq!(float_arg(0.1f32).as_str(), "0.10000000149011612");
+    let tiny = float_arg(f64::MIN_POSITIVE);
+    assert!(!tiny.as_str().contains('e'));
+    assert_eq!(tiny.as_str().parse::<f64>().unwrap(), f64::MIN_POSITIVE);
+}
+
+#[test]
+#[should_panic(expected = "non-finite")]
+fn non_finite() {
+    float_arg(f64::NAN);
+}
+
+#[test]
+fn args() {
+    let v = float_arg(1e-7);
+    let script = lua!(return @v;);
+    assert_eq!(script.explain().args(), &[b"0.0000001".to_vec()]);
+}
+
+#[test]
+fn round_trip() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let v = float_arg(1e-7);
+    let res: String = lua!(return tostring(tonumber(@v) == 0.0000001);)
+        .invoke(&mut con)
+        .unwrap();
+    assert_eq!(res, "true");
+}
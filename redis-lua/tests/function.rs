@@ -0,0 +1,49 @@
+use redis_lua::Script;
+
+#[test]
+fn function_source() {
+    let script = redis_lua::lua!(return redis.call("get", #$key) .. $x;)
+        .key("function:a")
+        .x(1);
+
+    let mut info = vec![];
+    let mut args = vec![];
+    script.info(&mut info, &mut args);
+    let source = redis_lua::gen_function(&info, &args, "mylib", "myfunc");
+
+    assert!(source
+        .starts_with("#!lua name=mylib\nredis.register_function('myfunc', function(KEYS, ARGV)\n"));
+    assert!(source.contains(&script.source()));
+    assert!(source.ends_with("end)\n"));
+}
+
+#[tokio::test]
+async fn function_fcall() {
+    let script = redis_lua::lua!(
+        redis.call("set", #$key, $x);
+        return redis.call("get", #$key) + $y;
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let script = script.key("function:b").x(2).y(3);
+    script.register(&mut cli, "redis_lua_test", "add").unwrap();
+
+    let res: usize = script.fcall(&mut cli, "add").unwrap();
+    assert_eq!(res, 5);
+}
+
+#[tokio::test]
+async fn function_fcall_async() {
+    let script = redis_lua::lua!(return $x + $y;);
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+    let script = script.x(2).y(3);
+    script
+        .register_async(&mut con, "redis_lua_test_async", "add")
+        .await
+        .unwrap();
+
+    let res: usize = script.fcall_async(&mut con, "add").await.unwrap();
+    assert_eq!(res, 5);
+}
@@ -0,0 +1,67 @@
+use redis_lua::Script;
+
+#[test]
+fn hashtag_keys() {
+    let script = redis_lua::lua!(
+        redis.call("set", #$from, $amount)
+        return redis.call("get", #$to);
+    )
+    .from("balance:a")
+    .amount(3)
+    .to("balance:b")
+    .with_keys(vec!["log"])
+    .with_hashtag("user123");
+
+    let plan = script.explain();
+    assert_eq!(
+        plan.keys(),
+        &[
+            b"{user123}:log".to_vec(),
+            b"{user123}:balance:a".to_vec(),
+            b"{user123}:balance:b".to_vec(),
+        ]
+    );
+    assert_eq!(plan.args(), &[b"3".to_vec()]);
+}
+
+#[test]
+fn hashtag_joined() {
+    let a = redis_lua::lua!(return redis.call("get", #$key);).key("a");
+    let b = redis_lua::lua!(return redis.call("get", #$key);).key("{other}:b");
+
+    let plan = a.join(b.with_hashtag("x")).explain();
+    assert_eq!(plan.keys(), &[b"a".to_vec(), b"{x}:{other}:b".to_vec()]);
+}
+
+#[test]
+fn hashtag_invalid_tag() {
+    for tag in ["", "{x}", "a}"] {
+        let source = redis_lua::lua!(return 1;).with_hashtag(tag).source();
+        assert!(source.contains("the hash tag can't be empty or have braces"));
+    }
+}
+
+#[test]
+fn hashtag_table_key() {
+    let key = vec![1, 2];
+    let script = redis_lua::lua!(return #@key;).with_hashtag("t");
+    assert!(script
+        .source()
+        .contains("the hash tag can't be put on a table key"));
+    assert_eq!(script.explain().keys().len(), 1);
+}
+
+#[test]
+fn hashtag_invoke() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let res: String = redis_lua::lua!(
+        redis.call("set", #$key, "v")
+        return redis.call("get", "{hashtag}:k");
+    )
+    .key("k")
+    .with_hashtag("hashtag")
+    .invoke(&mut cli)
+    .unwrap();
+    assert_eq!(res, "v");
+}
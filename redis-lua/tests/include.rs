@@ -0,0 +1,23 @@
+#[tokio::test]
+async fn include() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let _: () = redis::cmd("SET")
+        .arg("include:from")
+        .arg(10)
+        .query(&mut cli)
+        .unwrap();
+    let _: () = redis::cmd("SET")
+        .arg("include:to")
+        .arg(0)
+        .query(&mut cli)
+        .unwrap();
+
+    let amount = 3;
+    let script = redis_lua::include_lua!("scripts/transfer.lua");
+    let res: usize = script
+        .from("include:from")
+        .to("include:to")
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, 3);
+}
@@ -0,0 +1,76 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn invoke_bool() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    assert!(redis_lua::lua!(return true;).invoke_bool(&mut cli).unwrap());
+    assert!(!redis_lua::lua!(return false;)
+        .invoke_bool(&mut cli)
+        .unwrap());
+    assert!(redis_lua::lua!(return 1;).invoke_bool(&mut cli).unwrap());
+    assert!(redis_lua::lua!(return 2;).invoke_bool(&mut cli).unwrap());
+    assert!(!redis_lua::lua!(return 0;).invoke_bool(&mut cli).unwrap());
+    assert!(!redis_lua::lua!(return nil;).invoke_bool(&mut cli).unwrap());
+    assert!(!redis_lua::lua!(return "";).invoke_bool(&mut cli).unwrap());
+    assert!(!redis_lua::lua!(return "0";).invoke_bool(&mut cli).unwrap());
+    assert!(redis_lua::lua!(return "yes";)
+        .invoke_bool(&mut cli)
+        .unwrap());
+    assert!(!redis_lua::lua!(return {};).invoke_bool(&mut cli).unwrap());
+    assert!(redis_lua::lua!(return {1};).invoke_bool(&mut cli).unwrap());
+    assert!(redis_lua::lua!(return redis.status_reply("OK");)
+        .invoke_bool(&mut cli)
+        .unwrap());
+}
+
+#[tokio::test]
+async fn invoke_bool_from_redis_value() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    // `invoke::<bool>` accepts the integers, `true`, `false` and `nil`.
+    assert!(redis_lua::lua!(return true;)
+        .invoke::<bool>(&mut cli)
+        .unwrap());
+    assert!(!redis_lua::lua!(return false;)
+        .invoke::<bool>(&mut cli)
+        .unwrap());
+    assert!(redis_lua::lua!(return 3;).invoke::<bool>(&mut cli).unwrap());
+    assert!(!redis_lua::lua!(return 0;).invoke::<bool>(&mut cli).unwrap());
+    assert!(!redis_lua::lua!(return nil;)
+        .invoke::<bool>(&mut cli)
+        .unwrap());
+
+    // but not the empty string nor tables.
+    assert!(redis_lua::lua!(return "";)
+        .invoke::<bool>(&mut cli)
+        .is_err());
+    assert!(redis_lua::lua!(return {};)
+        .invoke::<bool>(&mut cli)
+        .is_err());
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn invoke_bool_mock() {
+    use redis::Value;
+
+    let cases = vec![
+        (Value::Int(1), true),
+        (Value::Int(0), false),
+        (Value::Int(-1), true),
+        (Value::Nil, false),
+        (Value::Data(vec![]), false),
+        (Value::Data(b"0".to_vec()), false),
+        (Value::Data(b"1".to_vec()), true),
+        (Value::Bulk(vec![]), false),
+        (Value::Bulk(vec![Value::Nil]), true),
+        (Value::Okay, true),
+    ];
+
+    for (value, expected) in cases {
+        let mut con = redis_lua::MockConnection::new().reply(value.clone());
+        let res = redis_lua::lua!(return 1;).invoke_bool(&mut con).unwrap();
+        assert_eq!(res, expected, "{:?}", value);
+    }
+}
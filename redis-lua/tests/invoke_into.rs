@@ -0,0 +1,66 @@
+use redis_lua::{Script, ScriptError, Status};
+use std::convert::TryFrom;
+
+#[derive(Debug, PartialEq)]
+enum State {
+    Queued,
+    Done,
+}
+
+impl TryFrom<redis::Value> for State {
+    type Error = String;
+
+    fn try_from(value: redis::Value) -> Result<Self, String> {
+        match Status::try_from(value)?.0.as_str() {
+            "QUEUED" => Ok(State::Queued),
+            "DONE" => Ok(State::Done),
+            s => Err(format!("unknown state {}", s)),
+        }
+    }
+}
+
+#[test]
+fn status() {
+    assert_eq!(
+        Status::try_from(redis::Value::Okay).unwrap(),
+        Status("OK".into())
+    );
+    assert!(Status::try_from(redis::Value::Status("OK".into()))
+        .unwrap()
+        .is_ok());
+    assert_eq!(
+        Status::try_from(redis::Value::Data(b"DONE".to_vec())).unwrap(),
+        Status("DONE".into())
+    );
+    assert!(Status::try_from(redis::Value::Int(1)).is_err());
+}
+
+#[test]
+fn invoke_into() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let state: State = redis_lua::lua!(return redis.status_reply("QUEUED"))
+        .invoke_into(&mut con)
+        .unwrap();
+    assert_eq!(state, State::Queued);
+
+    let status: Status = redis_lua::lua!(return "DONE")
+        .invoke_into(&mut con)
+        .unwrap();
+    assert_eq!(status, Status("DONE".into()));
+}
+
+#[test]
+fn invoke_into_error() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    match redis_lua::lua!(return redis.status_reply("LOST")).invoke_into::<State>(&mut con) {
+        Err(ScriptError::Convert(v, e)) => {
+            assert_eq!(v, redis::Value::Status("LOST".into()));
+            assert_eq!(e, "unknown state LOST");
+        }
+        res => panic!("unexpected {:?}", res),
+    }
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "serde")]
+
+use redis_lua::{json_arg, Script, ScriptError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+    name: String,
+    qty: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Order {
+    id: u64,
+    note: Option<String>,
+    items: Vec<Item>,
+}
+
+#[test]
+fn invoke_json() {
+    let order = Order {
+        id: 7,
+        note: None,
+        items: vec![Item {
+            name: "pear \u{1f350}".into(),
+            qty: 3,
+        }],
+    };
+
+    let arg = json_arg(&order).unwrap();
+    let script = redis_lua::lua!(
+        local order = cjson.decode(@arg)
+        order.items[1].qty = order.items[1].qty + 1
+        return cjson.encode(order)
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+    let res: Order = script.invoke_json(&mut con).unwrap();
+    assert_eq!(
+        res,
+        Order {
+            items: vec![Item {
+                name: "pear \u{1f350}".into(),
+                qty: 4,
+            }],
+            ..order
+        }
+    );
+
+    let script = redis_lua::lua!(return "not json");
+    let res = script.invoke_json::<Order>(&mut cli);
+    assert!(matches!(res, Err(ScriptError::Decode(_))));
+
+    let script = redis_lua::lua!(return redis.error_reply("failed"));
+    let res = script.invoke_json::<Order>(&mut cli);
+    assert!(matches!(res, Err(ScriptError::Redis(_))));
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn invoke_json_mock() {
+    use std::collections::HashMap;
+
+    let reply = |json: &str| {
+        redis_lua::MockConnection::new().reply(redis::Value::Data(json.as_bytes().to_vec()))
+    };
+
+    // `cjson` encodes the empty table as an empty object.
+    let mut con = reply(r#"{"id":1,"note":null,"items":{}}"#);
+    let res: Order = redis_lua::lua!(return 1).invoke_json(&mut con).unwrap();
+    assert_eq!(
+        res,
+        Order {
+            id: 1,
+            note: None,
+            items: vec![],
+        }
+    );
+
+    let mut con = reply(r#"{"1":[],"2":{}}"#);
+    let res: HashMap<u32, Vec<u32>> = redis_lua::lua!(return 1).invoke_json(&mut con).unwrap();
+    assert_eq!(res, vec![(1, vec![]), (2, vec![])].into_iter().collect());
+
+    let mut con = reply(r#"{}"#);
+    let res: HashMap<String, u32> = redis_lua::lua!(return 1).invoke_json(&mut con).unwrap();
+    assert!(res.is_empty());
+}
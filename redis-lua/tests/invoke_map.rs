@@ -0,0 +1,95 @@
+use redis_lua::Script;
+use std::collections::HashMap;
+
+fn expected() -> HashMap<String, String> {
+    vec![("a".into(), "1".into()), ("b".into(), "2".into())]
+        .into_iter()
+        .collect()
+}
+
+#[tokio::test]
+async fn invoke_map_flat() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    // Flat pairs work with `invoke` too.
+    let res: HashMap<String, String> = redis_lua::lua!(return {"a", "1", "b", "2"};)
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, expected());
+
+    let res: HashMap<String, String> = redis_lua::lua!(return {"a", "1", "b", "2"};)
+        .invoke_map(&mut cli)
+        .unwrap();
+    assert_eq!(res, expected());
+}
+
+#[tokio::test]
+async fn invoke_map_pairs() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let res: HashMap<String, String> = redis_lua::lua!(
+        local t = {a = "1", b = "2"}
+        local out = {}
+        for k, v in pairs(t) do
+            out[#out + 1] = {k, v}
+        end
+        return out;
+    )
+    .invoke_map(&mut cli)
+    .unwrap();
+    assert_eq!(res, expected());
+}
+
+#[tokio::test]
+async fn invoke_map_hash_table() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    // Redis drops the non-array part of the table.
+    let res: HashMap<String, String> = redis_lua::lua!(return {a = "1", b = "2"};)
+        .invoke_map(&mut cli)
+        .unwrap();
+    assert!(res.is_empty());
+}
+
+#[tokio::test]
+async fn invoke_map_roundtrip() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let _: () = redis::cmd("DEL").arg("invoke_map").query(&mut cli).unwrap();
+    let res: HashMap<String, usize> = redis_lua::lua!(
+        redis.call("hset", #$key, "a", 1, "b", 2);
+        return redis.call("hgetall", #$key);
+    )
+    .key("invoke_map")
+    .invoke_map(&mut cli)
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![("a".into(), 1), ("b".into(), 2)].into_iter().collect()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn invoke_map_mock() {
+    use redis::Value;
+
+    let data = |s: &str| Value::Data(s.as_bytes().to_vec());
+    let cases = vec![
+        Value::Bulk(vec![data("a"), data("1"), data("b"), data("2")]),
+        Value::Bulk(vec![
+            Value::Bulk(vec![data("a"), data("1")]),
+            Value::Bulk(vec![data("b"), data("2")]),
+        ]),
+    ];
+
+    for value in cases {
+        let mut con = redis_lua::MockConnection::new().reply(value);
+        let res: HashMap<String, String> = redis_lua::lua!(return 1;).invoke_map(&mut con).unwrap();
+        assert_eq!(res, expected());
+    }
+
+    let mut con = redis_lua::MockConnection::new().reply(Value::Bulk(vec![]));
+    let res: HashMap<String, String> = redis_lua::lua!(return 1;).invoke_map(&mut con).unwrap();
+    assert!(res.is_empty());
+}
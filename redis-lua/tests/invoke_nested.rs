@@ -0,0 +1,48 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn invoke_nested() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let res: Vec<Vec<String>> = redis_lua::lua!(return {{"a", "b"}, {"c"}, {}};)
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, vec![vec!["a", "b"], vec!["c"], vec![]]);
+
+    let res: Vec<Vec<usize>> = redis_lua::lua!(return {{1, 2}, {3}, {}};)
+        .invoke_nested(&mut cli)
+        .unwrap();
+    assert_eq!(res, vec![vec![1, 2], vec![3], vec![]]);
+
+    // `invoke` takes the row which isn't a table as a row of one value.
+    let res: Vec<Vec<usize>> = redis_lua::lua!(return {{1, 2}, 3};)
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, vec![vec![1, 2], vec![3]]);
+
+    let err = redis_lua::lua!(return {{1, 2}, 3};)
+        .invoke_nested::<usize>(&mut cli)
+        .unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::TypeError);
+    assert!(err.to_string().contains("row 2 is not a table"));
+
+    let err = redis_lua::lua!(return 1;)
+        .invoke_nested::<usize>(&mut cli)
+        .unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::TypeError);
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn invoke_nested_mock() {
+    use redis::Value;
+
+    let mut con = redis_lua::MockConnection::new().reply(Value::Bulk(vec![
+        Value::Bulk(vec![Value::Int(1), Value::Int(2)]),
+        Value::Nil,
+    ]));
+    let err = redis_lua::lua!(return {{1, 2}};)
+        .invoke_nested::<usize>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("row 2 is not a table but nil"));
+}
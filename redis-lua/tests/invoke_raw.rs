@@ -0,0 +1,48 @@
+use redis_lua::Script;
+
+#[test]
+fn invoke_raw() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let script = redis_lua::lua!(
+        redis.call("set", #$key, $value);
+        return redis.call("get", #$key) .. $suffix;
+    );
+    let res: String = script
+        .invoke_raw(&mut cli, &["invoke_raw:a"], &["1", "!"])
+        .unwrap();
+    assert_eq!(res, "1!");
+}
+
+#[test]
+fn invoke_raw_bound() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let x = 1;
+    let script = redis_lua::lua!(return @x + $y;).y(2);
+    let res: usize = script
+        .invoke_raw(&mut cli, &[] as &[&str], &[10, 20])
+        .unwrap();
+    assert_eq!(res, 30);
+}
+
+#[test]
+fn invoke_raw_mismatch() {
+    let mut cli = redis::Client::open("redis://127.0.0.1:1").unwrap();
+
+    let script = redis_lua::lua!(return redis.call("get", #$key) .. $suffix;);
+    let err = script
+        .invoke_raw::<String, _, _>(&mut cli, &["invoke_raw:a"], &["!", "?"])
+        .unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::ClientError);
+    assert_eq!(
+        err.detail(),
+        Some("the script takes 1 keys and 1 arguments but 1 keys and 2 arguments are given")
+    );
+
+    let x = 1;
+    let err = redis_lua::lua!(return @x;)
+        .invoke_raw::<usize, _, _>(&mut cli, &["invoke_raw:a"], &[1])
+        .unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::ClientError);
+}
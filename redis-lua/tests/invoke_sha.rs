@@ -0,0 +1,32 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn invoke_sha() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let script = redis_lua::lua!(return $x + 1;);
+    let sha = script.clone().x(0).load(&mut cli).unwrap();
+
+    let res: usize = script.x(2).invoke_sha(&mut cli, &sha).unwrap();
+    assert_eq!(res, 3);
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn invoke_sha_mock() {
+    let mut con = redis_lua::MockConnection::new();
+
+    let script = redis_lua::lua!(return $x + 1;);
+    let sha = script.clone().x(0).load(&mut con).unwrap();
+
+    let _: () = script.clone().x(1).invoke_sha(&mut con, &sha).unwrap();
+    let call = con.last_call().unwrap();
+    assert_eq!(call.command, "EVALSHA");
+    assert_eq!(call.args, vec![b"1".to_vec()]);
+
+    // Unknown digests fall back to `EVAL`.
+    let _: () = script.x(2).invoke_sha(&mut con, "0000").unwrap();
+    let call = con.last_call().unwrap();
+    assert_eq!(call.command, "EVAL");
+    assert_eq!(call.args, vec![b"2".to_vec()]);
+}
@@ -0,0 +1,39 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn invoke_timed() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let script = redis_lua::lua!(
+        local n = 0
+        for i = 1, 100000 do
+            n = n + i
+        end
+        return n
+    );
+    let (n, elapsed): (u64, _) = script.invoke_timed(&mut cli).unwrap();
+    assert_eq!(n, 5000050000);
+    assert!(elapsed > std::time::Duration::ZERO);
+
+    let (none, _): (Option<u64>, _) = redis_lua::lua!(return nil;).invoke_timed(&mut cli).unwrap();
+    assert_eq!(none, None);
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn invoke_timed_mock() {
+    let mut con = redis_lua::MockConnection::new().reply(redis::Value::Bulk(vec![
+        redis::Value::Int(1500),
+        redis::Value::Data(b"ok".to_vec()),
+    ]));
+
+    let (res, elapsed): (String, _) = redis_lua::lua!(return $x;)
+        .x("ok")
+        .invoke_timed(&mut con)
+        .unwrap();
+    assert_eq!(res, "ok");
+    assert_eq!(elapsed, std::time::Duration::from_micros(1500));
+
+    let call = con.last_call().unwrap();
+    assert!(call.source.contains("redis.call(\"TIME\")"));
+}
@@ -0,0 +1,26 @@
+use redis_lua::Script;
+
+#[test]
+fn invoke_value() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let script = redis_lua::lua!(
+        if $table then
+            return {1, "two"}
+        end
+        return 3
+    );
+
+    let value = script.clone().table(true).invoke_value(&mut con).unwrap();
+    assert_eq!(
+        value,
+        redis::Value::Bulk(vec![
+            redis::Value::Int(1),
+            redis::Value::Data(b"two".to_vec())
+        ])
+    );
+
+    let value = script.table(false).invoke_value(&mut con).unwrap();
+    assert_eq!(value, redis::Value::Int(3));
+}
@@ -0,0 +1,47 @@
+use redis_lua::Script;
+
+/// Connection wrapper which only implements `ConnectionLike` by delegation.
+struct Wrapper(redis::Connection);
+
+impl redis::ConnectionLike for Wrapper {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> redis::RedisResult<redis::Value> {
+        self.0.req_packed_command(cmd)
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisResult<Vec<redis::Value>> {
+        self.0.req_packed_commands(cmd, offset, count)
+    }
+
+    fn get_db(&self) -> i64 {
+        self.0.get_db()
+    }
+
+    fn check_connection(&mut self) -> bool {
+        self.0.check_connection()
+    }
+
+    fn is_open(&self) -> bool {
+        self.0.is_open()
+    }
+}
+
+#[tokio::test]
+async fn invoke_with() {
+    let script = redis_lua::lua!(return $x + $y;);
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = Wrapper(cli.get_connection().unwrap());
+
+    // Flushing the script cache makes the first invocation fall back to `EVAL`.
+    let _: () = redis::cmd("SCRIPT").arg("FLUSH").query(&mut con).unwrap();
+    let res: usize = script.clone().x(1).y(2).invoke_with(&mut con).unwrap();
+    assert_eq!(res, 3);
+
+    let res: usize = script.x(3).y(4).invoke_with(&mut con).unwrap();
+    assert_eq!(res, 7);
+}
@@ -0,0 +1,25 @@
+use redis_lua::Script;
+
+#[test]
+fn join_dedup_source() {
+    let script = redis_lua::lua!(return $x + 1;);
+    let joined = (script.clone() + script.clone() + script).x(1).x(2).x(3);
+
+    let source = joined.source();
+    assert_eq!(source.matches("function(").count(), 1);
+    assert_eq!(source.matches("__redis_lua_f0(").count(), 3);
+}
+
+#[tokio::test]
+async fn join_dedup() {
+    let y = 1;
+    let script = redis_lua::lua!(return $x + @y;);
+    let v = vec![1, 2];
+    let other = redis_lua::lua!(return #(@v) + $x;);
+
+    let joined = (script.clone() + other + script).x(1).x(2).x(3).collect();
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: (usize, usize, usize) = joined.invoke(&mut cli).unwrap();
+    assert_eq!(res, (2, 4, 4));
+}
@@ -0,0 +1,22 @@
+use redis_lua::lua;
+
+#[tokio::test]
+async fn join_locals() {
+    let x = 10;
+    let script1 = lua!(
+        local v = @x * $y;
+        return v;
+    );
+
+    let x = 3;
+    let script2 = lua!(
+        local v = @x + $y;
+        return v;
+    );
+
+    let script = script1 + script2;
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.y(2).y(4).invoke(&mut cli).unwrap();
+    assert_eq!(res, 7);
+}
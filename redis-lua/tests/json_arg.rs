@@ -0,0 +1,79 @@
+#![cfg(feature = "serde")]
+
+use redis_lua::{json_arg, Script, ScriptError};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Item {
+    name: String,
+    qty: u32,
+}
+
+#[derive(Serialize)]
+struct Order {
+    id: u64,
+    note: Option<String>,
+    items: Vec<Item>,
+}
+
+fn order() -> Order {
+    Order {
+        id: 7,
+        note: Some("say \"hi\"\n".into()),
+        items: vec![
+            Item {
+                name: "apple".into(),
+                qty: 2,
+            },
+            Item {
+                name: "pear".into(),
+                qty: 3,
+            },
+        ],
+    }
+}
+
+#[test]
+fn json_arg_single_slot() {
+    let order = json_arg(&order()).unwrap();
+    let x = 1;
+    let script = redis_lua::lua!(
+        local order = cjson.decode(@order)
+        return order.id + @x + $y
+    );
+
+    let plan = script.y(2).explain();
+    assert_eq!(
+        plan.args(),
+        &[
+            br#"{"id":7,"note":"say \"hi\"\n","items":[{"name":"apple","qty":2},{"name":"pear","qty":3}]}"#
+                .to_vec(),
+            b"1".to_vec(),
+            b"2".to_vec(),
+        ]
+    );
+}
+
+#[test]
+fn json_arg_non_string_keys() {
+    let map: std::collections::BTreeMap<_, _> = vec![((1, 2), "a")].into_iter().collect();
+    assert!(matches!(json_arg(&map), Err(ScriptError::Encode(_))));
+}
+
+#[tokio::test]
+async fn json_arg_roundtrip() {
+    let order = json_arg(&order()).unwrap();
+    let script = redis_lua::lua!(
+        local order = cjson.decode(@order)
+        local total = 0
+        for _, item in ipairs(order.items) do
+            total = total + item.qty
+        end
+        return { order.id, order.note, order.items[2].name, total }
+    );
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+    let res: (u64, String, String, u32) = script.invoke_async(&mut con).await.unwrap();
+    assert_eq!(res, (7, "say \"hi\"\n".into(), "pear".into(), 5));
+}
@@ -0,0 +1,59 @@
+use redis_lua::{ArgSource, Script};
+
+#[test]
+fn key_prefix_literal() {
+    let id = 42;
+    let script = redis_lua::lua!(return redis.call("get", #"{user}:"@id););
+
+    let plan = script.explain();
+    assert_eq!(plan.keys(), &[b"{user}:42".to_vec()]);
+    assert_eq!(plan.num_keys(), 1);
+}
+
+#[test]
+fn key_prefix_ident() {
+    struct User {
+        id: u64,
+        name: &'static str,
+    }
+    let user = User { id: 7, name: "bob" };
+    let script = redis_lua::lua!(
+        redis.call("set", #app:user:@user.id, @user.name)
+        return redis.call("get", #app:user:@user.id)
+    );
+
+    // The same key is passed once.
+    let plan = script.explain();
+    assert_eq!(plan.keys(), &[b"app:user:7".to_vec()]);
+    assert_eq!(plan.args(), &[b"bob".to_vec()]);
+    assert_eq!(script.arg_layout()[0].1, ArgSource::Capture);
+}
+
+#[test]
+fn key_prefix_string_value() {
+    let name = String::from("alice");
+    let script = redis_lua::lua!(return #"user:"@name;);
+    assert_eq!(script.explain().keys(), &[b"user:alice".to_vec()]);
+}
+
+#[test]
+#[should_panic(expected = "Couldn't concatenate a table")]
+fn key_prefix_table() {
+    let ids = vec![1, 2];
+    let script = redis_lua::lua!(return #"user:"@ids;);
+    script.explain();
+}
+
+#[tokio::test]
+async fn key_prefix() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let id = 1;
+    let _: () = redis_lua::lua!(redis.call("set", #"key_prefix:"@id, 10))
+        .invoke(&mut cli)
+        .unwrap();
+    let res: usize = redis_lua::lua!(return redis.call("get", "key_prefix:1"))
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, 10);
+}
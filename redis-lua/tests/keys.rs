@@ -0,0 +1,60 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn keys() {
+    let script = redis_lua::lua!(
+        redis.call("set", #$key, $value);
+        return redis.call("get", #$key);
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.key("keys:a").value(3).invoke(&mut cli).unwrap();
+    assert_eq!(res, 3);
+}
+
+#[tokio::test]
+async fn keys_mixed() {
+    let x = "keys:x";
+    let script = redis_lua::lua!(
+        return {#@x, $a, #$y, @x};
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: Vec<String> = script.a(1).y("keys:y").invoke(&mut cli).unwrap();
+    assert_eq!(res, vec!["keys:x", "1", "keys:y", "keys:x"]);
+}
+
+#[tokio::test]
+async fn keys_len() {
+    let z = vec![1, 2];
+    let script = redis_lua::lua!(
+        local keys = {#$x, #$y};
+        return #keys + #(@z);
+    )
+    .x("keys:x")
+    .y("keys:y");
+
+    let plan = script.clone().explain();
+    assert_eq!(plan.keys(), &[b"keys:x".to_vec(), b"keys:y".to_vec()]);
+    assert_eq!(plan.args().len(), 1);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, 4);
+}
+
+#[tokio::test]
+async fn keys_join() {
+    let script1 = redis_lua::lua!(
+        redis.call("set", #$x, 1);
+        return #KEYS;
+    );
+    let script2 = redis_lua::lua!(
+        return {#KEYS, KEYS[1], redis.call("get", #$y)};
+    );
+    let script = (script1 + script2).x("keys:join:x").y("keys:join:x");
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: (usize, String, usize) = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, (1, "keys:join:x".into(), 1));
+}
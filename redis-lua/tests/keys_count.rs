@@ -0,0 +1,20 @@
+use redis_lua::Script;
+
+#[test]
+fn keys_count() {
+    let script1 = redis_lua::lua!(return redis.call("get", #$key) .. $x;);
+    let script2 = redis_lua::lua!(return redis.call("mget", #$a, #$b););
+    let script3 = redis_lua::lua!(return $x;);
+
+    assert_eq!(script1.clone().key("a").x(1).keys_count(), 1);
+    assert_eq!(script3.clone().x(1).keys_count(), 0);
+
+    let joined = script1
+        .key("a")
+        .x(1)
+        .join(script2.a("b").b("c"))
+        .join(script3.x(2))
+        .with_keys(vec!["d", "e"]);
+    assert_eq!(joined.keys_count(), 5);
+    assert_eq!(joined.keys_count(), joined.explain().num_keys());
+}
@@ -0,0 +1,8 @@
+#[tokio::test]
+async fn kill_not_busy() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let err = redis_lua::script_kill(&mut con).unwrap_err();
+    assert_eq!(err.code(), Some("NOTBUSY"));
+}
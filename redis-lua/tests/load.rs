@@ -0,0 +1,32 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn load() {
+    let script = redis_lua::lua!(return $x + 1;);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let script = script.x(2);
+    let hash = script.load(&mut cli).unwrap();
+    assert_eq!(hash, script.prepare().get_hash());
+
+    let exists: Vec<bool> = redis::cmd("SCRIPT")
+        .arg("EXISTS")
+        .arg(&hash)
+        .query(&mut cli)
+        .unwrap();
+    assert_eq!(exists, vec![true]);
+
+    let res: usize = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, 3);
+}
+
+#[tokio::test]
+async fn load_async() {
+    let script = redis_lua::lua!(return $x + 1;);
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+    let script = script.x(2);
+    let hash = script.load_async(&mut con).await.unwrap();
+    assert_eq!(hash, script.prepare().get_hash());
+}
@@ -0,0 +1,49 @@
+use redis_lua::Script;
+
+fn scripts() -> Vec<Box<dyn Script>> {
+    vec![
+        Box::new(redis_lua::lua!(name = "incr", return redis.call("incr", #$key);).key("a")),
+        Box::new(redis_lua::lua!(name = "double", return $x * 2;).x(1)),
+        Box::new(redis_lua::lua!(return 1;)),
+    ]
+}
+
+#[tokio::test]
+async fn load_all() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let scripts = scripts();
+    let shas = redis_lua::load_all_async(scripts.iter().map(|s| s.as_ref()), &mut con)
+        .await
+        .unwrap();
+    assert_eq!(shas.len(), 2);
+    assert_eq!(shas["incr"], scripts[0].sha1());
+    assert_eq!(shas["double"], scripts[1].sha1());
+
+    let exists: Vec<bool> = redis::cmd("SCRIPT")
+        .arg("EXISTS")
+        .arg(&shas["incr"])
+        .arg(scripts[2].sha1())
+        .query_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(exists, vec![true, true]);
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn load_all_mock() {
+    let mut con = redis_lua::MockConnection::new();
+
+    let joined = redis_lua::lua!(name = "a", return 1;) + redis_lua::lua!(name = "b", return 2;);
+    let shas = redis_lua::load_all_async(vec![&joined], &mut con)
+        .await
+        .unwrap();
+    assert_eq!(shas["a+b"], joined.sha1());
+
+    let shas = redis_lua::load_all_async(Vec::<&dyn Script>::new(), &mut con)
+        .await
+        .unwrap();
+    assert!(shas.is_empty());
+}
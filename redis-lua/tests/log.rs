@@ -0,0 +1,101 @@
+#![cfg(feature = "log")]
+
+use redis_lua::Script;
+use std::sync::Mutex;
+
+static RECORDS: Mutex<Vec<(log::Level, String)>> = Mutex::new(Vec::new());
+
+struct Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "redis_lua"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let message = record.args().to_string();
+            RECORDS.lock().unwrap().push((record.level(), message));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// A connection which replies the given value to any command.
+struct Reply(redis::RedisResult<redis::Value>);
+
+impl redis::ConnectionLike for Reply {
+    fn req_packed_command(&mut self, _: &[u8]) -> redis::RedisResult<redis::Value> {
+        match &self.0 {
+            Ok(value) => Ok(value.clone()),
+            Err(_) => Err((redis::ErrorKind::ResponseError, "script failed").into()),
+        }
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        _: &[u8],
+        _: usize,
+        _: usize,
+    ) -> redis::RedisResult<Vec<redis::Value>> {
+        unimplemented!()
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+
+    fn check_connection(&mut self) -> bool {
+        true
+    }
+
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn log() {
+    log::set_logger(&Logger).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let script = redis_lua::lua!(name = "incr", return redis.call("incrby", #$key, $x));
+    let sha = script.clone().key("a").x(1).sha1();
+
+    let mut con = Reply(Ok(redis::Value::Int(1)));
+    let res: usize = script.clone().key("a").x(1).invoke(&mut con).unwrap();
+    assert_eq!(res, 1);
+
+    let mut con = Reply(Err((redis::ErrorKind::ResponseError, "").into()));
+    assert!(script
+        .clone()
+        .key("a")
+        .x(1)
+        .invoke::<usize>(&mut con)
+        .is_err());
+
+    let mut con = Reply(Ok(redis::Value::Int(1)));
+    let res: usize = script.clone().key("a").x(1).invoke_with(&mut con).unwrap();
+    assert_eq!(res, 1);
+    let options = redis_lua::ScriptOptions::new();
+    let res: usize = script
+        .key("a")
+        .x(1)
+        .invoke_with_options(&mut con, &options)
+        .unwrap();
+    assert_eq!(res, 1);
+
+    let records = RECORDS.lock().unwrap();
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0].0, log::Level::Debug);
+    assert!(records[0].1.contains("names=[\"incr\"]"));
+    assert!(records[0].1.contains(&format!("sha={}", sha)));
+    assert!(records[0].1.contains("keys=1 args=1"));
+    assert_eq!(records[1].0, log::Level::Warn);
+    assert!(records[1].1.contains("error="));
+    assert_eq!(records[2].0, log::Level::Debug);
+    assert!(records[2].1.contains("names=[\"incr\"]"));
+    assert_eq!(records[3].0, log::Level::Debug);
+    assert!(records[3].1.contains(&format!("sha={}", sha)));
+}
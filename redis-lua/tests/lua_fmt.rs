@@ -0,0 +1,66 @@
+use redis_lua::Script;
+
+fn counter(prefix: &str) -> impl Script + Clone + '_ {
+    redis_lua::lua_fmt!(prefix = prefix, {
+        return redis.call("incrby", "{prefix}:counter", $n);
+    })
+    .n(1)
+}
+
+#[test]
+fn lua_fmt_source() {
+    let source = counter("app1").source();
+    assert!(source.contains(r#""app1:counter""#), "{}", source);
+
+    let source = counter("app2").source();
+    assert!(source.contains(r#""app2:counter""#), "{}", source);
+}
+
+#[test]
+fn lua_fmt_cache() {
+    // The same text is the same script.
+    assert_eq!(counter("a").cache_key(), counter("a").cache_key());
+    assert_ne!(counter("a").cache_key(), counter("b").cache_key());
+    assert_ne!(counter("a").sha1(), counter("b").sha1());
+}
+
+#[test]
+fn lua_fmt_owned() {
+    // The text is owned by the script information, and compared by the text.
+    let a = counter("owned").components();
+    let b = counter("owned").components();
+    assert!(a[0].info().body().contains(r#""owned:counter""#));
+    assert_eq!(a[0].info(), b[0].info());
+    assert_ne!(a[0].info(), counter("other").components()[0].info());
+}
+
+#[test]
+fn lua_fmt_escape() {
+    let name = "a\"b\\c\n";
+    let n = 2;
+    let script = redis_lua::lua_fmt!(name = name, n = n, {
+        local t = {1, 2}
+        return "{name} {{n}} {n}" .. t[1] .. @n;
+    });
+
+    let source = script.source();
+    assert!(source.contains(r#""a\"b\\c\n {n} 2""#), "{}", source);
+}
+
+#[tokio::test]
+async fn lua_fmt() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let _: () = redis::cmd("DEL")
+        .arg("app:counter")
+        .query(&mut cli)
+        .unwrap();
+    let res: usize = counter("app").invoke(&mut cli).unwrap();
+    assert_eq!(res, 1);
+
+    let res: usize = redis::cmd("GET")
+        .arg("app:counter")
+        .query(&mut cli)
+        .unwrap();
+    assert_eq!(res, 1);
+}
@@ -0,0 +1,65 @@
+use redis_lua::Script;
+
+#[test]
+fn lua_module_source() {
+    let scripts = redis_lua::lua_module! {
+        prelude {
+            local function clamp(x, lo, hi)
+                return math.max(lo, math.min(hi, x))
+            end
+            local function double(x)
+                return x * 2
+            end
+        }
+
+        fn add {
+            return clamp($x + $y, 0, 100)
+        }
+
+        fn sub() {
+            return clamp($x - $y, 0, 100)
+        }
+    };
+
+    let add = scripts.add.clone().x(1).y(2);
+    assert_eq!(add.names(), vec!["add"]);
+    assert!(add.source().contains("local function clamp"));
+
+    let sub = scripts.sub.x(1).y(2);
+    assert_eq!(sub.names(), vec!["sub"]);
+    assert!(sub.source().contains("local function clamp"));
+
+    assert_ne!(add.sha1(), sub.sha1());
+}
+
+#[test]
+fn lua_module_without_prelude() {
+    let y = 1;
+    let scripts = redis_lua::lua_module! {
+        fn get { return redis.call("get", #$key) }
+        fn incr { return redis.call("incrby", #$key, @y) }
+    };
+
+    assert_eq!(scripts.get.key("a").keys_count(), 1);
+    assert_eq!(scripts.incr.key("a").names(), vec!["incr"]);
+}
+
+#[tokio::test]
+async fn lua_module() {
+    let scripts = redis_lua::lua_module! {
+        prelude {
+            local function clamp(x, lo, hi)
+                return math.max(lo, math.min(hi, x))
+            end
+        }
+
+        fn add { return clamp($x + $y, 0, 100) }
+        fn sub { return clamp($x - $y, 0, 100) }
+    };
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = scripts.add.x(70).y(50).invoke(&mut cli).unwrap();
+    assert_eq!(res, 100);
+    let res: usize = scripts.sub.x(1).y(2).invoke(&mut cli).unwrap();
+    assert_eq!(res, 0);
+}
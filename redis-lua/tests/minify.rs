@@ -0,0 +1,52 @@
+use redis_lua::{minify, Script};
+
+#[test]
+fn minify_source() {
+    assert_eq!(
+        minify("local a = 1 -- comment\nreturn a .. \"  x  \" .. 1 .. [[ y  ]]"),
+        "local a=1 return a..\"  x  \"..1 .. [[ y  ]]"
+    );
+    assert_eq!(minify("--[[ long\ncomment ]] return - -1"), "return- -1");
+}
+
+#[test]
+#[cfg(not(feature = "minify"))]
+fn minify_join() {
+    let script1 = redis_lua::lua!(
+        return $x + 10;
+    );
+    let script2 = redis_lua::lua!(
+        return $y + 10;
+    );
+    let script3 = redis_lua::lua!(
+        return $z + 10;
+    );
+    let script = (script1 + script2 + script3).x(20).y(4).z(2);
+
+    let source = script.source();
+    let minified = minify(&source);
+    assert!(minified.len() < source.len());
+}
+
+#[tokio::test]
+async fn minify_invoke() {
+    let y = 1;
+    let script = redis_lua::lua!(
+        local a = $x .. "  " .. @y
+        return a;
+    );
+    let script = script.x(2);
+
+    let mut info = vec![];
+    let mut args = vec![];
+    script.info(&mut info, &mut args);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let minified = redis_lua::gen_script_minified(&info, &args);
+    let mut invoke = minified.prepare_invoke();
+    for arg in args {
+        invoke.arg(arg);
+    }
+    let res: String = invoke.invoke(&mut cli).unwrap();
+    assert_eq!(res, "2  1");
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "testing")]
+
+use redis_lua::{MockConnection, ScriptPipeline};
+
+#[test]
+fn mock_args() {
+    let mut con = MockConnection::new().reply(redis::Value::Int(3));
+
+    let script = redis_lua::lua!(return redis.call("hincrby", #$key, $field, $by););
+    let res: usize = script
+        .key("counters")
+        .field("hits")
+        .by(3)
+        .invoke(&mut con)
+        .unwrap();
+    assert_eq!(res, 3);
+
+    // The script is loaded after `EVALSHA` fails with `NOSCRIPT`, and then invoked by `EVALSHA` again.
+    assert_eq!(con.calls().len(), 1);
+    let call = con.last_call().unwrap();
+    assert_eq!(call.command, "EVALSHA");
+    assert!(call.source.contains("hincrby"));
+    assert_eq!(call.keys, vec![b"counters".to_vec()]);
+    assert_eq!(call.args, vec![b"hits".to_vec(), b"3".to_vec()]);
+}
+
+#[test]
+fn mock_cached() {
+    let mut con = MockConnection::new();
+
+    let script = redis_lua::lua!(return $x;);
+    let _: () = script.clone().x(1).invoke(&mut con).unwrap();
+    let _: () = script.x(2).invoke(&mut con).unwrap();
+
+    assert_eq!(con.calls().len(), 2);
+    assert_eq!(con.calls()[0].source, con.calls()[1].source);
+    assert_eq!(con.calls()[1].args, vec![b"2".to_vec()]);
+}
+
+#[test]
+fn mock_error() {
+    let mut con = MockConnection::new().fail(redis::RedisError::from((
+        redis::ErrorKind::ResponseError,
+        "An error was signalled by the server",
+        "insufficient funds".to_string(),
+    )));
+
+    let err = redis_lua::lua!(return redis.error_reply("insufficient funds"))
+        .invoke::<()>(&mut con)
+        .unwrap_err();
+    assert_eq!(err.detail(), Some("insufficient funds"));
+}
+
+#[test]
+fn mock_pipeline() {
+    let mut con = MockConnection::new()
+        .reply(redis::Value::Int(1))
+        .reply(redis::Value::Int(2));
+
+    let res: Vec<usize> = ScriptPipeline::new()
+        .script(redis_lua::lua!(return $x;).x(1))
+        .script(redis_lua::lua!(return $y;).y(2))
+        .invoke(&mut con)
+        .unwrap();
+    assert_eq!(res, vec![1, 2]);
+    assert_eq!(con.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn mock_async() {
+    let mut con = MockConnection::new().reply(redis::Value::Data(b"ok".to_vec()));
+
+    let res: String = redis_lua::lua!(return $x;)
+        .x("a")
+        .invoke_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(res, "ok");
+    assert_eq!(con.last_call().unwrap().args, vec![b"a".to_vec()]);
+}
+
+#[test]
+fn mock_sha1() {
+    use redis_lua::Script;
+
+    let mut con = MockConnection::new().reply(redis::Value::Int(2));
+    let script = redis_lua::lua!(return $x + 1;).x(1);
+
+    // The digest is the one `SCRIPT LOAD` returns, so the script can be invoked by `EVALSHA` directly.
+    let sha1 = script.sha1();
+    assert_eq!(script.load(&mut con).unwrap(), sha1);
+
+    let res: usize = redis::cmd("EVALSHA")
+        .arg(&sha1)
+        .arg(0)
+        .arg(1)
+        .query(&mut con)
+        .unwrap();
+    assert_eq!(res, 2);
+}
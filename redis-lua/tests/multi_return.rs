@@ -0,0 +1,30 @@
+#[cfg(not(feature = "minify"))]
+#[test]
+fn multi_return_source() {
+    use redis_lua::Script;
+
+    let script = redis_lua::lua!(
+        local f = function(a, b)
+            return a, b
+        end
+        if $x > 0 then
+            return f(1, 2), "a", {3, 4};
+        end
+        return $x
+    );
+
+    let source = script.x(1).source();
+    assert!(source.contains("return a, b"));
+    assert!(source.contains(r#"return {f(1, 2), "a", {3, 4}};"#));
+}
+
+#[tokio::test]
+async fn multi_return() {
+    let script = redis_lua::lua!(
+        return 1, "two", $x;
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: (usize, String, usize) = script.x(3).invoke(&mut cli).unwrap();
+    assert_eq!(res, (1, "two".into(), 3));
+}
@@ -0,0 +1,19 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn invoke_async_multiplexed() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let con = cli.get_multiplexed_tokio_connection().await.unwrap();
+
+    let script = redis_lua::lua!(return $x + 1;);
+    let a = script.clone().x(1).invoke_async_multiplexed::<usize>(&con);
+    let b = script.x(2).invoke_async_multiplexed::<usize>(&con);
+    let (a, b) = futures::try_join!(a, b).unwrap();
+    assert_eq!((a, b), (2, 3));
+
+    // The future doesn't borrow the connection.
+    let x = 10;
+    let task = tokio::spawn(redis_lua::lua!(return @x;).invoke_async_multiplexed::<usize>(&con));
+    drop(con);
+    assert_eq!(task.await.unwrap().unwrap(), 10);
+}
@@ -0,0 +1,31 @@
+use redis_lua::Script;
+
+#[test]
+fn named_source() {
+    let script1 = redis_lua::lua!(name = "set_a", {
+        return redis.call("set", "named:a", $x);
+    });
+    let script2 = redis_lua::lua!(name = "get_a", return redis.call("get", "named:a"););
+    let script3 = redis_lua::lua!(return 1;);
+
+    let script = (script1 + script3 + script2).x(1);
+    assert_eq!(script.names(), vec!["set_a", "get_a"]);
+
+    let source = script.source();
+    if !cfg!(feature = "minify") {
+        assert!(source.contains("--[[set_a]]"));
+        assert!(source.contains("--[[get_a]]"));
+    }
+}
+
+#[tokio::test]
+async fn named_invoke() {
+    let script = redis_lua::lua!(name = "add", {
+        local t = {1, 2}
+        return t[1] + $x
+    });
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.x(2).invoke(&mut cli).unwrap();
+    assert_eq!(res, 3);
+}
@@ -0,0 +1,61 @@
+use redis_lua::Script;
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn numkeys_source() {
+    let script = redis_lua::lua!(return redis.call("get", KEYS[1]) .. #$key;)
+        .key("b")
+        .with_numkeys(1)
+        .with_keys(vec!["a"]);
+
+    let plan = script.explain();
+    assert_eq!(plan.keys(), &[b"a".to_vec(), b"b".to_vec()]);
+    assert!(plan.source().contains("{KEYS[1], "));
+    assert!(!plan.source().contains("error_reply"));
+}
+
+#[test]
+fn numkeys_either_order() {
+    let script = redis_lua::lua!(return KEYS[2];);
+
+    let before = script.clone().with_numkeys(2).with_keys(vec!["a", "b"]);
+    let after = script.with_keys(vec!["a", "b"]).with_numkeys(2);
+    assert_eq!(before.source(), after.source());
+}
+
+#[test]
+fn numkeys_mismatch() {
+    let script = redis_lua::lua!(return KEYS[1];)
+        .with_numkeys(2)
+        .with_keys(vec!["a"]);
+
+    assert!(script
+        .source()
+        .contains("expected 2 keys by with_keys but got 1"));
+}
+
+#[tokio::test]
+async fn numkeys() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let script = redis_lua::lua!(
+        redis.call("set", KEYS[1], $x)
+        return redis.call("get", KEYS[1])
+    );
+    let res: usize = script
+        .x(3)
+        .with_numkeys(1)
+        .with_keys(vec!["numkeys:a"])
+        .invoke_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(res, 3);
+
+    let err = redis_lua::lua!(return KEYS[1];)
+        .with_numkeys(1)
+        .invoke_async::<_, ()>(&mut con)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("expected 1 keys"));
+}
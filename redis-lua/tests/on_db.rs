@@ -0,0 +1,75 @@
+use redis_lua::Script;
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn on_db_source() {
+    let script = redis_lua::lua!(return 1;).on_db(2);
+    assert!(script.source().contains("redis.call(\"SELECT\", 2)"));
+}
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn on_db_nested() {
+    let inner = redis_lua::lua!(return 1;).on_db(2);
+    let script = inner.join(redis_lua::lua!(return 2;)).on_db(1);
+
+    // The inner script selects back the database of the outer one.
+    let source = script.source();
+    assert!(source.contains("redis.call(\"SELECT\", 2)"));
+    assert!(source.contains("local __redis_lua_r = "));
+    assert!(source.contains("redis.call(\"SELECT\", 1); return __redis_lua_r"));
+    assert!(!source.contains("error_reply"));
+}
+
+#[test]
+fn on_db_joined_after() {
+    let script = redis_lua::lua!(return 1;)
+        .on_db(2)
+        .join(redis_lua::lua!(return 2;));
+    assert!(script.source().contains("error_reply"));
+
+    let script = redis_lua::lua!(return 1;)
+        .on_db(2)
+        .join(redis_lua::lua!(return 2;).on_db(0));
+    assert!(!script.source().contains("error_reply"));
+
+    let script = redis_lua::lua!(return 1;).join(redis_lua::lua!(return 2;).on_db(2));
+    assert!(!script.source().contains("error_reply"));
+}
+
+#[tokio::test]
+async fn on_db() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let script = redis_lua::lua!(
+        redis.call("set", #$key, $value)
+        return redis.call("get", #$key)
+    );
+    let res: usize = script
+        .key("on_db:a")
+        .value(3)
+        .on_db(1)
+        .invoke_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(res, 3);
+
+    // The database of the connection is not changed.
+    let script = redis_lua::lua!(return redis.call("get", #$key););
+    let res: Option<usize> = script
+        .clone()
+        .key("on_db:a")
+        .invoke_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(res, None);
+
+    let res: Option<usize> = script
+        .key("on_db:a")
+        .on_db(1)
+        .invoke_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(res, Some(3));
+}
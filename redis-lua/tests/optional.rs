@@ -0,0 +1,30 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn optional() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let res: Option<usize> = redis_lua::lua!(return nil;)
+        .invoke_optional(&mut con)
+        .unwrap();
+    assert_eq!(res, None);
+
+    let res: Option<usize> = redis_lua::lua!(return false;)
+        .invoke_optional(&mut con)
+        .unwrap();
+    assert_eq!(res, None);
+
+    let res: Option<usize> = redis_lua::lua!(return 0;)
+        .invoke_optional(&mut con)
+        .unwrap();
+    assert_eq!(res, Some(0));
+
+    let res: Option<String> = redis_lua::lua!(return "";)
+        .invoke_optional(&mut con)
+        .unwrap();
+    assert_eq!(res, Some("".into()));
+
+    let res: Option<usize> = redis_lua::lua!(return nil;).invoke(&mut con).unwrap();
+    assert_eq!(res, None);
+}
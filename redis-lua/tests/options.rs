@@ -0,0 +1,41 @@
+use redis_lua::{Script, ScriptOptions};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn options_noscript() {
+    let script = redis_lua::lua!(name = "double", return $x * 2;);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let fallbacks = Arc::new(Mutex::new(vec![]));
+    let options = {
+        let fallbacks = fallbacks.clone();
+        ScriptOptions::new().on_noscript(move |names, sha1| {
+            fallbacks
+                .lock()
+                .unwrap()
+                .push((names.to_vec(), sha1.to_string()));
+        })
+    };
+
+    // Flushing the script cache makes the first invocation fall back to `EVAL`.
+    let _: () = redis::cmd("SCRIPT").arg("FLUSH").query(&mut cli).unwrap();
+    let res: usize = script
+        .clone()
+        .x(1)
+        .invoke_with_options(&mut cli, &options)
+        .unwrap();
+    assert_eq!(res, 2);
+
+    let res: usize = script
+        .clone()
+        .x(2)
+        .invoke_with_options(&mut cli, &options)
+        .unwrap();
+    assert_eq!(res, 4);
+
+    assert_eq!(
+        *fallbacks.lock().unwrap(),
+        vec![(vec!["double"], script.x(1).sha1())]
+    );
+}
@@ -0,0 +1,39 @@
+use redis_lua::ScriptPipeline;
+
+#[tokio::test]
+async fn pipeline() {
+    let script = redis_lua::lua!(return $x * 2;);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    // Flushing the script cache makes the pipeline load the script.
+    let _: () = redis::cmd("SCRIPT").arg("FLUSH").query(&mut cli).unwrap();
+
+    let mut pipe = ScriptPipeline::new();
+    pipe.extend((1..=3).map(|x| script.clone().x(x)));
+    assert_eq!(pipe.len(), 3);
+
+    let res: Vec<usize> = pipe.invoke(&mut cli).unwrap();
+    assert_eq!(res, vec![2, 4, 6]);
+}
+
+#[tokio::test]
+async fn pipeline_mixed_async() {
+    let script1 = redis_lua::lua!(return $x + 1;);
+    let script2 = redis_lua::lua!(return $x + 2;);
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let pipe = ScriptPipeline::new()
+        .script(script1.clone().x(1))
+        .script(script2.x(1))
+        .script(script1.x(2));
+    let res: Vec<usize> = pipe.invoke_async(&mut con).await.unwrap();
+    assert_eq!(res, vec![2, 3, 3]);
+}
+
+#[test]
+fn pipeline_empty() {
+    assert!(ScriptPipeline::new().is_empty());
+}
@@ -0,0 +1,86 @@
+/// A connection guard of a pool, which only dereferences to the connection.
+struct Pooled<C>(C);
+
+impl<C> std::ops::Deref for Pooled<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.0
+    }
+}
+
+impl<C> std::ops::DerefMut for Pooled<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.0
+    }
+}
+
+#[tokio::test]
+async fn pooled() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = Pooled(cli.get_multiplexed_tokio_connection().await.unwrap());
+
+    let script = redis_lua::lua!(return $a + $b;);
+    let res: usize = script
+        .clone()
+        .a(1)
+        .b(2)
+        .invoke_async(&mut *con)
+        .await
+        .unwrap();
+    assert_eq!(res, 3);
+
+    let res: usize = script.a(3).b(4).invoke_async(&mut *con).await.unwrap();
+    assert_eq!(res, 7);
+}
+
+/// A connection guard which borrows the pool and is neither `Clone` nor `'static`, e.g. of `bb8`.
+struct Borrowed<'a, C>(&'a mut C);
+
+impl<C> std::ops::Deref for Borrowed<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        self.0
+    }
+}
+
+impl<C> std::ops::DerefMut for Borrowed<'_, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        self.0
+    }
+}
+
+#[tokio::test]
+async fn pooled_borrowed() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut pool = cli.get_async_connection().await.unwrap();
+    let mut con = Borrowed(&mut pool);
+
+    let res: usize = redis_lua::lua!(return $a * $b;)
+        .a(3)
+        .b(4)
+        .invoke_async(&mut *con)
+        .await
+        .unwrap();
+    assert_eq!(res, 12);
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn pooled_mock() {
+    let mut pool = redis_lua::MockConnection::new().reply(redis::Value::Int(12));
+    let mut con = Borrowed(&mut pool);
+
+    let res: usize = redis_lua::lua!(return $a * $b;)
+        .a(3)
+        .b(4)
+        .invoke_async(&mut *con)
+        .await
+        .unwrap();
+    assert_eq!(res, 12);
+    assert_eq!(
+        pool.last_call().unwrap().args,
+        vec![b"3".to_vec(), b"4".to_vec()]
+    );
+}
@@ -0,0 +1,72 @@
+use redis_lua::Script;
+
+#[test]
+fn prelude_source() {
+    let prelude = redis_lua::lua_prelude! {
+        local function clamp(x, lo, hi)
+            return math.max(lo, math.min(hi, x))
+        end
+        local function double(x)
+            return x * 2
+        end
+    };
+
+    let add = redis_lua::lua!(prelude = "clamp", { return clamp($x + $y, 0, 100) });
+    let sub = redis_lua::lua!(prelude = "clamp, double", { return double(clamp($x - $y, 0, 100)) });
+
+    let script = add.x(1).y(2).use_prelude(prelude);
+    let source = script.source();
+    assert!(source.starts_with("local function clamp"));
+
+    // The prelude is put once however many scripts use it.
+    let joined = script
+        .join(sub.x(3).y(4).use_prelude(prelude))
+        .use_prelude(prelude);
+    let source = joined.source();
+    assert_eq!(source.matches("local function clamp").count(), 1);
+    assert!(source.starts_with("local function clamp"));
+}
+
+#[test]
+fn prelude_distinct() {
+    let clamp = redis_lua::lua_prelude! {
+        local function clamp(x, lo, hi)
+            return math.max(lo, math.min(hi, x))
+        end
+    };
+    let double = redis_lua::lua_prelude! {
+        local function double(x)
+            return x * 2
+        end
+    };
+
+    let script = redis_lua::lua!(prelude = "clamp double", { return double(clamp(@1, 0, 100)) })
+        .use_prelude(clamp)
+        .use_prelude(double);
+    let source = script.source();
+    assert!(source.contains("local function clamp"));
+    assert!(source.contains("local function double"));
+}
+
+#[tokio::test]
+async fn prelude() {
+    let prelude = redis_lua::lua_prelude! {
+        local function clamp(x, lo, hi)
+            return math.max(lo, math.min(hi, x))
+        end
+    };
+
+    let add = redis_lua::lua!(prelude = "clamp", { return clamp($x + $y, 0, 100) });
+    let sub = redis_lua::lua!(prelude = "clamp", { return clamp($x - $y, 0, 100) });
+
+    let joined = add
+        .x(70)
+        .y(50)
+        .use_prelude(prelude)
+        .join(sub.x(10).y(50).use_prelude(prelude))
+        .collect();
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: (usize, usize) = joined.invoke(&mut cli).unwrap();
+    assert_eq!(res, (100, 0));
+}
@@ -0,0 +1,26 @@
+use redis_lua::{lua, Script};
+
+#[test]
+fn prepare() {
+    let script = lua!(return $x + 1;);
+
+    let s1 = script.clone().x(1).prepare();
+    let s2 = script.x(2).prepare();
+    assert_eq!(s1.get_hash(), s2.get_hash());
+}
+
+#[test]
+fn prepare_join() {
+    let script1 = lua!(return $x + 1;);
+    let script2 = lua!(return $y + 2;);
+
+    let single = script1.clone().x(1).prepare();
+    let joined = (script1 + script2).x(1).y(2).prepare();
+    assert_ne!(single.get_hash(), joined.get_hash());
+
+    let boxed = Box::new(lua!(return 1;)) as Box<dyn Script>;
+    assert_eq!(
+        boxed.prepare().get_hash(),
+        lua!(return 1;).prepare().get_hash()
+    );
+}
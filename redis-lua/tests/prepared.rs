@@ -0,0 +1,63 @@
+use redis_lua::{PreparedScript, Script};
+
+#[test]
+fn prepared_rebind() {
+    let script = redis_lua::lua!(return $x + 1;);
+    let mut prepared = PreparedScript::new(&script.clone().x(0));
+    let hash = prepared.script().get_hash().to_string();
+
+    // The script is kept while the same script is bound.
+    let _ = prepared.bind(script.clone().x(1));
+    assert_eq!(prepared.script().get_hash(), hash);
+
+    // Another script is generated again.
+    let _ = prepared.bind(redis_lua::lua!(return $y + 2;).y(1));
+    assert_ne!(prepared.script().get_hash(), hash);
+    assert_eq!(
+        prepared.script().get_hash(),
+        redis_lua::lua!(return $y + 2;).y(1).sha1()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn prepared_mock() {
+    let mut con = redis_lua::MockConnection::new();
+
+    let script = redis_lua::lua!(return redis.call("incrby", #$key, $by););
+    let mut prepared = PreparedScript::new(&script.clone().key("a").by(0));
+
+    for by in 1..=3 {
+        let _: () = prepared
+            .bind(script.clone().key("a").by(by))
+            .invoke(&mut con)
+            .unwrap();
+    }
+
+    let calls = con.calls();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[2].keys, vec![b"a".to_vec()]);
+    assert_eq!(calls[2].args, vec![b"3".to_vec()]);
+    assert_eq!(calls[0].source, calls[2].source);
+}
+
+#[tokio::test]
+async fn prepared() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let script = redis_lua::lua!(return $x + 1;);
+    let mut prepared = PreparedScript::new(&script.clone().x(0));
+
+    for x in 0..3 {
+        let res: usize = prepared.bind(script.clone().x(x)).invoke(&mut cli).unwrap();
+        assert_eq!(res, x + 1);
+
+        let res: usize = prepared
+            .bind(script.clone().x(x))
+            .invoke_async(&mut con)
+            .await
+            .unwrap();
+        assert_eq!(res, x + 1);
+    }
+}
@@ -0,0 +1,48 @@
+use redis_lua::{Script, ScriptError};
+
+#[test]
+fn protected_source() {
+    let script = redis_lua::lua!(protected, {
+        return $x + 1;
+    });
+
+    let source = script.x(1).source();
+    assert!(source.contains("pcall(function()"));
+    assert!(source.contains("redis.error_reply"));
+}
+
+#[tokio::test]
+async fn protected_ok() {
+    let script = redis_lua::lua!(name = "add", protected, {
+        local y = $x + 1
+        return y
+    });
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.x(1).invoke(&mut cli).unwrap();
+    assert_eq!(res, 2);
+}
+
+#[tokio::test]
+async fn protected_error() {
+    let script1 = redis_lua::lua!(protected, {
+        redis.call("set", #$key, 1);
+        error("INSUFFICIENT_BALANCE", 0)
+    });
+    let script2 = redis_lua::lua!(protected, {
+        return redis.call("hget", #$key, "field");
+    });
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res = script1
+        .key("protected:a")
+        .invoke_typed::<String, ()>(&mut cli);
+    match res {
+        Err(ScriptError::Script(e)) => assert_eq!(e, "INSUFFICIENT_BALANCE"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // The error of `redis.call`, i.e. `WRONGTYPE`, is returned as the error reply.
+    let res = script2.key("protected:a").invoke::<()>(&mut cli);
+    assert_eq!(res.unwrap_err().code(), Some("WRONGTYPE"));
+}
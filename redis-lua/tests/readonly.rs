@@ -0,0 +1,62 @@
+use redis_lua::Script;
+
+#[test]
+fn readonly_plan() {
+    let script1 = redis_lua::lua!(readonly, return redis.call("get", #$key););
+    let script2 = redis_lua::lua!(return 1;);
+
+    let mut info = vec![];
+    let mut args = vec![];
+    script1.clone().key("a").info(&mut info, &mut args);
+    assert!(info.iter().all(|i| i.readonly()));
+
+    // Joined scripts are read-only only if all of them are.
+    let mut info = vec![];
+    script1
+        .clone()
+        .key("a")
+        .join(script2.clone())
+        .info(&mut info, &mut args);
+    assert!(!info.iter().all(|i| i.readonly()));
+
+    let mut info = vec![];
+    script1
+        .key("a")
+        .join(script2)
+        .readonly()
+        .info(&mut info, &mut args);
+    assert!(info.iter().all(|i| i.readonly()));
+}
+
+#[tokio::test]
+async fn readonly_invoke() {
+    let script = redis_lua::lua!(readonly, return redis.call("get", #$key););
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let _: () = redis::cmd("SET")
+        .arg("readonly:a")
+        .arg(3)
+        .query(&mut cli)
+        .unwrap();
+
+    let res: usize = script.clone().key("readonly:a").invoke(&mut cli).unwrap();
+    assert_eq!(res, 3);
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+    let res: usize = script
+        .key("readonly:a")
+        .invoke_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(res, 3);
+}
+
+#[tokio::test]
+async fn readonly_rejects_writes() {
+    let script = redis_lua::lua!(return redis.call("set", #$key, 1););
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res = script.key("readonly:b").readonly().invoke::<()>(&mut cli);
+    assert!(res.is_err());
+}
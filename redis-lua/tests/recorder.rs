@@ -0,0 +1,83 @@
+#![cfg(feature = "testing")]
+
+use redis_lua::{MockConnection, Script, ScriptOptions, ScriptRecord};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn recorder() {
+    let log = Arc::new(Mutex::new(Vec::<ScriptRecord>::new()));
+    let options = ScriptOptions::new().recorder(log.clone());
+    let mut con = MockConnection::new()
+        .reply(redis::Value::Int(3))
+        .fail(redis::RedisError::from((
+            redis::ErrorKind::ResponseError,
+            "An error was signalled by the server",
+            "boom".to_string(),
+        )));
+
+    let script = redis_lua::lua!(name = "incr", return redis.call("incrby", #$key, $by););
+    let res: usize = script
+        .clone()
+        .key("a")
+        .by(3)
+        .invoke_with_options(&mut con, &options)
+        .unwrap();
+    assert_eq!(res, 3);
+    let res: redis::RedisResult<usize> = script
+        .key("b")
+        .by(1)
+        .invoke_with_options(&mut con, &options);
+    assert!(res.is_err());
+
+    let log = log.lock().unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].names(), &["incr"]);
+    assert!(log[0].source().contains("incrby"));
+    assert_eq!(log[0].keys(), &[b"a".to_vec()]);
+    assert_eq!(log[0].args(), &[b"3".to_vec()]);
+    assert_eq!(log[0].result(), Ok(&redis::Value::Int(3)));
+    assert_eq!(log[1].keys(), &[b"b".to_vec()]);
+    assert!(log[1].result().unwrap_err().contains("boom"));
+}
+
+#[test]
+fn recorder_replay() {
+    let log = Arc::new(Mutex::new(Vec::<ScriptRecord>::new()));
+    let options = ScriptOptions::new().recorder(log.clone());
+    let mut con = MockConnection::new();
+
+    let _: () = redis_lua::lua!(return redis.call("set", #$key, $value);)
+        .key("a")
+        .value("v")
+        .invoke_with_options(&mut con, &options)
+        .unwrap();
+
+    let mut replayed = MockConnection::new().reply(redis::Value::Okay);
+    let record = log.lock().unwrap()[0].clone();
+    assert_eq!(record.replay(&mut replayed), Ok(redis::Value::Okay));
+
+    let call = replayed.last_call().unwrap();
+    assert_eq!(call.command, "EVAL");
+    assert_eq!(call.source, record.source());
+    assert_eq!(call.keys, vec![b"a".to_vec()]);
+    assert_eq!(call.args, vec![b"v".to_vec()]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn recorder_json() {
+    let log = Arc::new(Mutex::new(Vec::<ScriptRecord>::new()));
+    let options = ScriptOptions::new().recorder(log.clone());
+    let mut con = MockConnection::new().reply(redis::Value::Int(1));
+
+    let _: usize = redis_lua::lua!(return #$key;)
+        .key("k")
+        .invoke_with_options(&mut con, &options)
+        .unwrap();
+
+    let record = log.lock().unwrap()[0].clone();
+    let json = redis_lua::json_arg(&record).unwrap();
+    let json = json.as_str();
+    assert!(json.starts_with(r#"{"names":[],"source":"#));
+    assert!(json.ends_with(r#""keys":[[107]],"args":[],"result":1}"#));
+}
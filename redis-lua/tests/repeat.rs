@@ -0,0 +1,64 @@
+use redis_lua::Script;
+
+fn add(x: usize) -> impl Script + Clone {
+    redis_lua::lua!(return redis.call("incrby", #$key, $x);)
+        .key("repeat:sum")
+        .x(x)
+}
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn repeat_source() {
+    let script = add(1).repeat(3).apply(add(2)).apply(add(3));
+    let plan = script.explain();
+
+    assert_eq!(plan.source().matches("incrby").count(), 1);
+    assert!(plan.source().contains("for __redis_lua_i0 = 0, 2 do"));
+    assert!(plan
+        .source()
+        .contains("KEYS[1 + __redis_lua_i0 * 1], ARGV[1 + __redis_lua_i0 * 1]"));
+    assert_eq!(plan.keys(), vec![b"repeat:sum".to_vec(); 3]);
+    assert_eq!(plan.args(), &[b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]);
+}
+
+#[test]
+fn repeat_last_values() {
+    let plan = add(1).repeat(2).explain();
+    assert_eq!(plan.args(), &[b"1".to_vec(), b"1".to_vec()]);
+}
+
+#[test]
+#[should_panic(expected = "more than 2 sets")]
+fn repeat_apply_too_many() {
+    let _ = add(1).repeat(2).apply(add(2)).apply(add(3));
+}
+
+#[test]
+fn repeat_sum() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let _: () = redis::cmd("del").arg("repeat:sum").query(&mut cli).unwrap();
+
+    let script = add(1).repeat(4).apply(add(2)).apply(add(3)).apply(add(4));
+    let res: Vec<usize> = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, vec![1, 3, 6, 10]);
+
+    let sum: usize = redis::cmd("get").arg("repeat:sum").query(&mut cli).unwrap();
+    assert_eq!(sum, 10);
+}
+
+#[test]
+fn repeat_joined() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let pair = |a: usize, b: usize| {
+        let sum = redis_lua::lua!(return $a + $b;).a(a).b(b);
+        let tail = redis_lua::lua!(return @a * 10;);
+        sum.join(tail)
+    };
+    let res: Vec<usize> = pair(1, 2)
+        .repeat(2)
+        .apply(pair(3, 4))
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, vec![10, 30]);
+}
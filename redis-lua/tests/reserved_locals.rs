@@ -0,0 +1,33 @@
+use redis_lua::Script;
+
+fn scripts() -> impl Script {
+    let x = 1;
+    let y = 2;
+    let s1 = redis_lua::lua!(
+        local _a1 = 10
+        local _s0_a0 = 100
+        return @x + _a1 + _s0_a0;
+    );
+    let s2 = redis_lua::lua!(
+        local _s1_a0 = 1000
+        return @y + _s1_a0;
+    );
+    s1.join(s2).collect()
+}
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn reserved_locals_source() {
+    let source = scripts().explain().source().to_string();
+    assert!(source.contains("local __redis_lua_s0_a0 = "));
+    assert!(source.contains("local __redis_lua_s1_a0 = "));
+    assert!(source.contains("local _a1 = 10"));
+    assert!(!source.contains("__internal"));
+}
+
+#[test]
+fn reserved_locals() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: Vec<usize> = scripts().invoke(&mut cli).unwrap();
+    assert_eq!(res, vec![111, 1002]);
+}
@@ -0,0 +1,98 @@
+use redis_lua::{RetryPolicy, Script};
+use std::time::Duration;
+
+/// A connection which fails with the given errors before replying `1`.
+struct Flaky {
+    errors: Vec<redis::ErrorKind>,
+    calls: usize,
+}
+
+impl Flaky {
+    fn new(errors: &[redis::ErrorKind]) -> Self {
+        Self {
+            errors: errors.iter().rev().cloned().collect(),
+            calls: 0,
+        }
+    }
+}
+
+impl redis::ConnectionLike for Flaky {
+    fn req_packed_command(&mut self, _: &[u8]) -> redis::RedisResult<redis::Value> {
+        self.calls += 1;
+        match self.errors.pop() {
+            Some(kind) => Err((kind, "failed").into()),
+            None => Ok(redis::Value::Int(1)),
+        }
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        _: &[u8],
+        _: usize,
+        _: usize,
+    ) -> redis::RedisResult<Vec<redis::Value>> {
+        unimplemented!()
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+
+    fn check_connection(&mut self) -> bool {
+        true
+    }
+
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::new().backoff(Duration::from_millis(1))
+}
+
+#[test]
+fn retry_io() {
+    use redis::ErrorKind::*;
+
+    let mut con = Flaky::new(&[IoError, TryAgain]);
+    let res: usize = redis_lua::lua!(return $x;)
+        .x(1)
+        .invoke_retry(&mut con, policy())
+        .unwrap();
+    assert_eq!(res, 1);
+    assert_eq!(con.calls, 3);
+}
+
+#[test]
+fn retry_exhausted() {
+    use redis::ErrorKind::*;
+
+    let mut con = Flaky::new(&[IoError, IoError, IoError]);
+    let err = redis_lua::lua!(return 1;)
+        .invoke_retry::<usize>(&mut con, policy().max_retries(2))
+        .unwrap_err();
+    assert_eq!(err.kind(), IoError);
+    assert_eq!(con.calls, 3);
+}
+
+#[test]
+fn retry_script_error() {
+    use redis::ErrorKind::*;
+
+    let mut con = Flaky::new(&[ResponseError]);
+    let err = redis_lua::lua!(return 1;)
+        .invoke_retry::<usize>(&mut con, policy())
+        .unwrap_err();
+    assert_eq!(err.kind(), ResponseError);
+    assert_eq!(con.calls, 1);
+}
+
+#[test]
+fn retryable() {
+    let policy = RetryPolicy::new();
+    let wrongtype = redis::RedisError::from((redis::ErrorKind::ExtensionError, "WRONGTYPE"));
+    assert!(!policy.is_retryable(&wrongtype));
+    let io = redis::RedisError::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+    assert!(policy.is_retryable(&io));
+}
@@ -0,0 +1,44 @@
+use redis_lua::{Script, ScriptOptions};
+
+fn info<S: Script>(script: &S) -> (Vec<redis_lua::Info>, Vec<redis_lua::ScriptArg>) {
+    let mut info = vec![];
+    let mut args = vec![];
+    script.info(&mut info, &mut args);
+    (info, args)
+}
+
+#[test]
+fn script_size() {
+    let script = redis_lua::lua!(return $x + 1;).x(1);
+    let (info, args) = info(&script);
+    let len = script.source().len();
+
+    assert!(redis_lua::try_gen_script(&info, &args, redis_lua::DEFAULT_MAX_SCRIPT_LEN).is_ok());
+    assert!(redis_lua::try_gen_script(&info, &args, len).is_ok());
+
+    let err = redis_lua::try_gen_script(&info, &args, len - 1).unwrap_err();
+    assert_eq!(err.kind(), redis::ErrorKind::ClientError);
+    assert!(err.to_string().contains("script too large"));
+}
+
+#[test]
+fn script_size_joined() {
+    let script = (0..100).fold(Box::new(()) as Box<dyn Script>, |acc, i| {
+        Box::new(acc.join(redis_lua::lua!(return $x + 1;).x(i)))
+    });
+    let (info, args) = info(&script);
+
+    assert!(redis_lua::try_gen_script(&info, &args, 1024).is_err());
+}
+
+#[test]
+fn script_size_options() {
+    let options = ScriptOptions::new().max_script_len(10);
+
+    // The script is rejected before connecting to Redis.
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let err = redis_lua::lua!(return 1;)
+        .invoke_with_options::<()>(&mut cli, &options)
+        .unwrap_err();
+    assert!(err.to_string().contains("script too large"));
+}
@@ -0,0 +1,8 @@
+-- Move the amount from one counter to another.
+local from = tonumber(redis.call("get", #$from) or "0")
+if from < @amount then
+    return redis.error_reply("INSUFFICIENT_BALANCE")
+end
+
+redis.call("decrby", #$from, @amount)
+return redis.call("incrby", #$to, @amount)
@@ -0,0 +1,16 @@
+#[test]
+fn signature() {
+    let y = 1;
+    let script = redis_lua::lua!(return redis.call("get", #$key) + $x + @y;);
+    assert_eq!(script.signature(), "#$key, $x");
+
+    let script = script.key("a");
+    assert_eq!(script.signature(), "#$key, $x");
+    assert_eq!(script.x(1).signature(), "#$key, $x");
+}
+
+#[test]
+fn signature_novars() {
+    let script = redis_lua::lua!(return 1;);
+    assert_eq!(script.signature(), "");
+}
@@ -0,0 +1,23 @@
+use redis_lua::Script;
+
+#[test]
+fn source() {
+    let script = redis_lua::lua!(return $x + 1;).x(1);
+
+    let source = script.source();
+    assert!(source.contains("return"));
+    assert!(source.contains("ARGV[1]"));
+    assert_eq!(script.sha1(), redis::Script::new(&source).get_hash());
+}
+
+#[test]
+fn source_join() {
+    let script1 = redis_lua::lua!(return $x + 1;);
+    let script2 = redis_lua::lua!(return $y + 2;);
+    let script = (script1 + script2).x(1).y(2);
+
+    let source = script.source();
+    assert_eq!(source.matches("function()").count(), 2);
+    assert!(source.contains("ARGV[2]"));
+    assert_eq!(script.sha1(), script.prepare().get_hash());
+}
@@ -0,0 +1,76 @@
+#![cfg(not(feature = "minify"))]
+
+use redis_lua::Script;
+
+fn line_of(source: &str, pattern: &str) -> usize {
+    source
+        .lines()
+        .position(|line| line.contains(pattern))
+        .unwrap()
+        + 1
+}
+
+#[test]
+fn locate() {
+    let line = line!();
+    let script = redis_lua::lua!(
+        local x = $x + 1
+
+        return x * 2
+    )
+    .x(1);
+
+    let source = script.source();
+    let generated = line_of(&source, "* 2");
+    assert_eq!(script.locate(generated), Some((file!(), line + 4)));
+    assert_eq!(script.locate(generated + 1), None);
+}
+
+#[test]
+fn locate_join() {
+    let line1 = line!() + 1;
+    let script1 = redis_lua::lua!(return 1 + 10;);
+    let line2 = line!() + 2;
+    let script2 = redis_lua::lua!(
+        return 2 + 20;
+    );
+    let script = script1 + script2;
+
+    let source = script.source();
+    assert_eq!(
+        script.locate(line_of(&source, "1 + 10")),
+        Some((file!(), line1))
+    );
+    assert_eq!(
+        script.locate(line_of(&source, "2 + 20")),
+        Some((file!(), line2))
+    );
+}
+
+#[test]
+fn locate_protected() {
+    let line = line!() + 1;
+    let script = redis_lua::lua!(protected, { return error("fail") });
+
+    let source = script.source();
+    assert_eq!(
+        script.locate(line_of(&source, "error")),
+        Some((file!(), line))
+    );
+}
+
+#[tokio::test]
+async fn locate_error() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let line = line!();
+    let script = redis_lua::lua!(
+        local x = 1
+        return redis.call("nosuchcommand", x)
+    );
+
+    let err = script.invoke_async::<_, ()>(&mut con).await.unwrap_err();
+    let location = format!("({}:{})", file!(), line + 3);
+    assert!(err.to_string().contains(&location), "{}", err);
+}
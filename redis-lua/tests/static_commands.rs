@@ -0,0 +1,15 @@
+#![cfg(feature = "static-commands")]
+
+use redis_lua::Script;
+
+#[test]
+fn static_commands_opt_out() {
+    let script = redis_lua::lua!(dynamic_commands, return redis.call($cmd, #$key););
+    assert!(script.cmd("get").key("a").source().contains("redis.call("));
+}
+
+#[test]
+fn static_commands_allowed() {
+    let script = redis_lua::lua!(return redis.call("get", #$key););
+    assert!(script.key("a").source().contains("redis.call(\"get\""));
+}
@@ -0,0 +1,77 @@
+use redis_lua::Script;
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn then_args() {
+    let guard = redis_lua::lua!(return redis.call("get", #$key) == $expected;);
+    let action = redis_lua::lua!(return redis.call("set", #$key, $value););
+    let script = guard
+        .key("then:a")
+        .expected("1")
+        .then(action.key("then:a").value("2"));
+
+    // The values of the guard come first, followed by the ones of the action.
+    let plan = script.explain();
+    assert_eq!(plan.keys(), &[b"then:a".to_vec(), b"then:a".to_vec()]);
+    assert_eq!(plan.args(), &[b"1".to_vec(), b"2".to_vec()]);
+    assert!(plan
+        .source()
+        .contains("if __redis_lua_g and __redis_lua_g ~= 0"));
+}
+
+#[tokio::test]
+async fn then_cas() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let guard = redis_lua::lua!(return redis.call("get", #$key) == $expected;);
+    let action = redis_lua::lua!(
+        redis.call("set", #$key, $value)
+        return $value
+    );
+    let cas = |expected: &str, value: &str| {
+        guard
+            .clone()
+            .key("then:cas")
+            .expected(expected.to_string())
+            .then(action.clone().key("then:cas").value(value.to_string()))
+    };
+
+    let _: () = redis_lua::lua!(redis.call("set", "then:cas", "a"))
+        .invoke(&mut cli)
+        .unwrap();
+
+    let res: Option<String> = cas("a", "b").invoke(&mut cli).unwrap();
+    assert_eq!(res, Some("b".into()));
+
+    // The value is no longer `a`, so the action is skipped.
+    let res: Option<String> = cas("a", "c").invoke(&mut cli).unwrap();
+    assert_eq!(res, None);
+
+    let res: String = redis_lua::lua!(return redis.call("get", "then:cas"))
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!(res, "b");
+}
+
+#[tokio::test]
+async fn then_falsy() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+
+    let action = redis_lua::lua!(return 1;);
+    let guards: Vec<Box<dyn Script>> = vec![
+        Box::new(redis_lua::lua!(return 0;)),
+        Box::new(redis_lua::lua!(return "";)),
+        Box::new(redis_lua::lua!(return {};)),
+        Box::new(redis_lua::lua!(return false;)),
+    ];
+    for guard in guards {
+        let res: Option<usize> = guard.then(action.clone()).invoke(&mut cli).unwrap();
+        assert_eq!(res, None);
+    }
+
+    let err = redis_lua::lua!(return redis.error_reply("conflict");)
+        .then(action)
+        .invoke::<Option<usize>>(&mut cli)
+        .unwrap_err();
+    assert!(err.to_string().contains("conflict"));
+}
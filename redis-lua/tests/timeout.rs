@@ -0,0 +1,32 @@
+#![cfg(feature = "tokio")]
+
+use redis_lua::{Script, ScriptError};
+use std::time::Duration;
+
+#[test]
+fn timeout() {
+    let rt = tokio1::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    rt.block_on(async {
+        let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+        let mut con = cli.get_multiplexed_tokio_connection().await.unwrap();
+
+        let script = redis_lua::lua!(
+            local n = 0
+            for i = 1, 100000000 do
+                n = n + i
+            end
+            return n;
+        );
+        let res = script
+            .invoke_async_timeout::<_, usize>(&mut con, Duration::from_millis(100))
+            .await;
+        assert!(matches!(res, Err(ScriptError::Timeout)));
+
+        // The script is still running on the server.
+        redis_lua::script_kill_async(&mut con).await.unwrap();
+    });
+}
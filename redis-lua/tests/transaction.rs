@@ -0,0 +1,36 @@
+use redis_lua::ScriptTransaction;
+
+#[tokio::test]
+async fn transaction() {
+    let script1 = redis_lua::lua!(return redis.call("incrby", #$key, $x););
+    let script2 = redis_lua::lua!(return redis.call("get", #$key) .. $y;);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut set = redis::cmd("SET");
+    set.arg("transaction:a").arg(1);
+    let mut del = redis::cmd("DEL");
+    del.arg("transaction:a");
+
+    let (x, y, z): (usize, String, usize) = ScriptTransaction::new()
+        .cmd_ignore(set)
+        .script(script1.key("transaction:a").x(2))
+        .script(script2.key("transaction:a").y("!"))
+        .cmd(del)
+        .invoke(&mut cli)
+        .unwrap();
+    assert_eq!((x, y.as_str(), z), (3, "3!", 1));
+}
+
+#[tokio::test]
+async fn transaction_async() {
+    let script = redis_lua::lua!(return $x + $y;);
+
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_async_connection().await.unwrap();
+
+    let tx = ScriptTransaction::new()
+        .script(script.clone().x(1).y(2))
+        .script(script.x(3).y(4));
+    let res: (usize, usize) = tx.invoke_async(&mut con).await.unwrap();
+    assert_eq!(res, (3, 7));
+}
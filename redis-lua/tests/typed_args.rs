@@ -0,0 +1,47 @@
+use redis_lua::Script;
+use std::collections::HashMap;
+
+#[test]
+fn typed_args_plan() {
+    let script = redis_lua::lua!(return redis.call("incrby", #$key: String, $x: i64););
+
+    // Integer literals are inferred as the annotated type.
+    let plan = script.key("typed:a".to_string()).x(-3).explain();
+    assert_eq!(plan.keys(), &[b"typed:a".to_vec()]);
+    assert_eq!(plan.args(), &[b"-3".to_vec()]);
+}
+
+#[test]
+fn typed_args_partial() {
+    // Only the annotated arguments are constrained.
+    let script = redis_lua::lua!(return string.upper($x) .. $y: u8;);
+    let plan = script.x("a").y(1).explain();
+    assert_eq!(plan.args(), &[b"a".to_vec(), b"1".to_vec()]);
+}
+
+#[test]
+fn typed_args_join() {
+    let script1 = redis_lua::lua!(return $x: u32 + $y;);
+    let script2 = redis_lua::lua!(return $z: bool;);
+
+    let script = (script1 + script2).x(1).y(2).z(true);
+    assert_eq!(script.explain().args().len(), 3);
+}
+
+#[test]
+fn typed_args_bind_named() {
+    let script = redis_lua::lua!(return $x: u64 + $y: u64;);
+
+    let args: HashMap<&str, u64> = vec![("x", 1), ("y", 2)].into_iter().collect();
+    let plan = script.bind_named(&args).unwrap().explain();
+    assert_eq!(plan.args(), &[b"1".to_vec(), b"2".to_vec()]);
+}
+
+#[tokio::test]
+async fn typed_args_invoke() {
+    let script = redis_lua::lua!(return $x: i64 * 2;);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: i64 = script.x(-2).invoke(&mut cli).unwrap();
+    assert_eq!(res, -4);
+}
@@ -0,0 +1,67 @@
+use redis_lua::{Script, ScriptError};
+
+#[derive(Debug, PartialEq)]
+enum Error {
+    InsufficientBalance,
+}
+
+impl std::str::FromStr for Error {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "INSUFFICIENT_BALANCE" => Ok(Error::InsufficientBalance),
+            _ => Err(()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn typed_error() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let script = redis_lua::lua!(return redis.error_reply("INSUFFICIENT_BALANCE"););
+    let res = script.invoke_typed::<Error, ()>(&mut con);
+    assert!(matches!(
+        res,
+        Err(ScriptError::Script(Error::InsufficientBalance))
+    ));
+
+    let script = redis_lua::lua!(return redis.error_reply("ERR unknown failure"););
+    let res = script.invoke_typed::<Error, ()>(&mut con);
+    assert!(matches!(res, Err(ScriptError::Unmatched(e)) if e == "unknown failure"));
+
+    let script = redis_lua::lua!(return 1;);
+    let res = script.invoke_typed::<Error, usize>(&mut con);
+    assert!(matches!(res, Ok(1)));
+}
+
+#[tokio::test]
+async fn invoke_err() {
+    let cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let mut con = cli.get_connection().unwrap();
+
+    let script = redis_lua::lua!(return redis.error_reply("ERR unknown failure"););
+    let res = script.invoke_err::<()>(&mut con);
+    assert!(matches!(res, Err(ScriptError::Unmatched(e)) if e == "unknown failure"));
+
+    let script = redis_lua::lua!(return nil + 1;);
+    let res = script.invoke_err::<()>(&mut con);
+    assert!(matches!(res, Err(ScriptError::Unmatched(e)) if e.contains("Error running script")));
+
+    let script = redis_lua::lua!(return "abc";);
+    let res = script.invoke_err::<usize>(&mut con);
+    assert!(matches!(res, Err(ScriptError::Decode(_))));
+
+    let script = redis_lua::lua!(return 1;);
+    let res = script.invoke_err::<usize>(&mut con);
+    assert!(matches!(res, Ok(1)));
+}
+
+#[test]
+fn from_reply_connection() {
+    let cli = redis::Client::open("redis://127.0.0.1:1").unwrap();
+    let res = cli.get_connection().map_err(ScriptError::from_reply);
+    assert!(matches!(res, Err(ScriptError::Redis(_))));
+}
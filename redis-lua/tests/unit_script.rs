@@ -0,0 +1,18 @@
+use redis_lua::Script;
+
+#[cfg(not(feature = "minify"))]
+#[test]
+fn unit_script_source() {
+    assert_eq!(().source(), "return nil;\n");
+    assert_eq!(().with_keys(vec!["a"]).explain().num_keys(), 1);
+}
+
+#[tokio::test]
+async fn unit_script_invoke() {
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: Option<usize> = ().invoke(&mut cli).unwrap();
+    assert_eq!(res, None);
+
+    let res: Vec<usize> = ().collect().invoke(&mut cli).unwrap();
+    assert!(res.is_empty());
+}
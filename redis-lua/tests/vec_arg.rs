@@ -0,0 +1,18 @@
+#[tokio::test]
+async fn vec_arg() {
+    let ids: Vec<u64> = vec![1, 2, 3];
+    let offset = 10;
+
+    let script = redis_lua::lua!(
+        local sum = 0
+        local extra = $rest
+        for _, id in ipairs(@ids) do
+            sum = sum + id
+        end
+        return sum + @offset + #extra;
+    );
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let res: usize = script.rest(&[4u64, 5][..]).invoke(&mut cli).unwrap();
+    assert_eq!(res, 18);
+}
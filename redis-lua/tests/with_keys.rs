@@ -0,0 +1,31 @@
+use redis_lua::Script;
+
+#[tokio::test]
+async fn with_keys() {
+    let script = redis_lua::lua!(
+        return {#KEYS, redis.call("get", #$key)};
+    );
+    let script = script
+        .key("with_keys:a")
+        .with_keys(vec!["with_keys:b", "with_keys:c"]);
+
+    let mut cli = redis::Client::open("redis://127.0.0.1").unwrap();
+    let _: () = redis::cmd("SET")
+        .arg("with_keys:a")
+        .arg(1)
+        .query(&mut cli)
+        .unwrap();
+
+    let res: (usize, usize) = script.invoke(&mut cli).unwrap();
+    assert_eq!(res, (1, 1));
+}
+
+#[test]
+fn with_keys_source() {
+    let script = redis_lua::lua!(return #$key;).key("a");
+
+    let source = script.clone().with_keys(vec!["b"]).source();
+    assert!(source.contains("KEYS[2]"));
+    assert!(!source.contains("KEYS[1]"));
+    assert_ne!(script.source(), source);
+}